@@ -0,0 +1,119 @@
+use std::{env, time::Instant};
+use gameoflife::library;
+use gameoflife::node;
+use gameoflife::random;
+use gameoflife::rule::Rule;
+use gameoflife::stepper::StepperKind;
+use gameoflife::Cell;
+
+// Run with `cargo bench`, optionally followed by a substring to select cases:
+//
+//     cargo bench -- soup
+
+struct Case {
+    name: &'static str,
+    kind: StepperKind,
+    side: usize,
+    generations: usize,
+    cells: Vec<Cell>,
+}
+
+fn centred(name: &str, side: usize) -> Vec<Cell> {
+    library::get(name).unwrap().centred(side)
+}
+
+fn soup(side: usize) -> Vec<Cell> {
+    random::soup(1, side, side, 0.5).centred(side)
+}
+
+// A few gliders and lightweight spaceships scattered across a mostly empty
+// grid.
+fn spaceships(side: usize) -> Vec<Cell> {
+    let glider = library::get("glider").unwrap();
+    let lwss = library::get("lightweight spaceship").unwrap();
+    let mut cells = vec![Cell::Dead; side * side];
+    for (i, ship) in [&glider, &lwss, &glider, &lwss].into_iter().enumerate() {
+        let left = side / 8 + i * side / 5;
+        let top = side / 8 + (i % 2) * side / 2;
+        for y in 0..ship.height {
+            for x in 0..ship.width {
+                cells[(left + x) + (top + y) * side] = ship.cells[x + y * ship.width];
+            }
+        }
+    }
+    cells
+}
+
+fn cases() -> Vec<Case> {
+    let mut cases = Vec::new();
+    for kind in StepperKind::ALL {
+        // the quadtree engine is slower per generation on chaotic patterns,
+        // so it gets smaller grids and shorter runs
+        let (side, generations) = match kind {
+            StepperKind::Quadtree => (64, 20),
+            StepperKind::Dense => (512, 500),
+        };
+        cases.push(Case { name: "soup", kind, side, generations, cells: soup(side) });
+        cases.push(Case { name: "gun", kind, side, generations, cells: centred("gosper glider gun", side) });
+        cases.push(Case { name: "spaceships", kind, side, generations, cells: spaceships(side) });
+        cases.push(Case { name: "r-pentomino", kind, side, generations, cells: centred("r-pentomino", side) });
+    }
+    // grids four times the area, to see how lookups and the memo scale
+    cases.push(Case { name: "soup at 256", kind: StepperKind::Quadtree, side: 256, generations: 5, cells: soup(256) });
+    cases.push(Case {
+        name: "gun at 256",
+        kind: StepperKind::Quadtree,
+        side: 256,
+        generations: 5,
+        cells: centred("gosper glider gun", 256),
+    });
+    // long runs, which the quadtree engine takes in jumps while the pattern
+    // is clear of the edge; the spacefiller's population grows with the
+    // square of the generation, which fills the memo with new squares
+    for kind in StepperKind::ALL {
+        cases.push(Case { name: "r-pentomino to 1103", kind, side: 1024, generations: 1103, cells: centred("r-pentomino", 1024) });
+        cases.push(Case { name: "acorn to 5206", kind, side: 4096, generations: 5206, cells: centred("acorn", 4096) });
+        cases.push(Case { name: "max to 1000", kind, side: 2048, generations: 1000, cells: centred("max", 2048) });
+    }
+    cases
+}
+
+fn main() {
+    let filter = env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    println!(
+        "{:<22} {:<9} {:>5} {:>6} {:>12} {:>10} {:>10} {:>9} {:>9}",
+        "case", "engine", "side", "gens", "gens/s", "hits", "misses", "hit rate", "entries"
+    );
+    for case in cases() {
+        if filter.as_ref().is_some_and(|filter| !case.name.contains(filter.as_str())) {
+            continue;
+        }
+        node::clear_cache();
+        let mut stepper = case.kind.create(case.side, Rule::default()).unwrap();
+        stepper.load(&case.cells).unwrap();
+
+        let start = Instant::now();
+        stepper.step(case.generations);
+        let elapsed = start.elapsed().as_secs_f64();
+
+        let stats = node::cache_stats();
+        let lookups = stats.hits + stats.misses;
+        let hit_rate = if lookups > 0 {
+            format!("{:.1}%", 100.0 * stats.hits as f64 / lookups as f64)
+        } else {
+            "-".to_string()
+        };
+        println!(
+            "{:<22} {:<9} {:>5} {:>6} {:>12.1} {:>10} {:>10} {:>9} {:>9}",
+            case.name,
+            case.kind,
+            case.side,
+            case.generations,
+            case.generations as f64 / elapsed,
+            stats.hits,
+            stats.misses,
+            hit_rate,
+            stats.entries
+        );
+    }
+}
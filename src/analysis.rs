@@ -0,0 +1,222 @@
+use std::{collections::HashMap, fmt};
+use crate::error::Error;
+use crate::node::Node;
+use crate::pattern::{self, Pattern};
+use crate::rule::Rule;
+use crate::stepper::{Stepper, StepperKind};
+use crate::universe::Cell;
+
+// Phases wider or taller than this are not tracked: building a `Node` for
+// every generation of a large, growing pattern would take far too long.
+pub const MAX_EXTENT: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Classification {
+    Dies,
+    StillLife,
+    Oscillator { period: usize },
+    // `dx` and `dy` are the displacement over one period.
+    Spaceship { period: usize, dx: isize, dy: isize },
+    // No repetition was seen within the generations that were run.
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Analysis {
+    pub classification: Classification,
+    // The generation at which the pattern died out or entered its cycle.
+    pub settled_at: usize,
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// Speeds are written as a reduced fraction of c, e.g. c/4 or 2c/5.
+fn speed(distance: usize, period: usize) -> String {
+    let divisor = gcd(distance, period);
+    let (distance, period) = (distance / divisor, period / divisor);
+    match (distance, period) {
+        (1, 1) => "c".to_string(),
+        (1, _) => format!("c/{}", period),
+        (_, 1) => format!("{}c", distance),
+        _ => format!("{}c/{}", distance, period),
+    }
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Classification::Dies => write!(f, "dies out"),
+            Classification::StillLife => write!(f, "still life"),
+            Classification::Oscillator { period } => write!(f, "p{} oscillator", period),
+            Classification::Spaceship { period, dx, dy } => {
+                let (x, y) = (dx.unsigned_abs(), dy.unsigned_abs());
+                if x == 0 || y == 0 {
+                    write!(f, "{} orthogonal spaceship", speed(x.max(y), period))
+                } else if x == y {
+                    write!(f, "{} diagonal spaceship", speed(x, period))
+                } else {
+                    write!(f, "({},{}){} oblique spaceship", x.max(y), x.min(y), speed(1, period))
+                }
+            }
+            Classification::Unknown => write!(f, "no period found"),
+        }
+    }
+}
+
+// The cells of a `side` grid inside the bounding box at (`left`, `top`),
+// padded out to the smallest power-of-two square, so that two phases that
+// differ only by a translation give equal nodes.
+fn normalise(cells: &[Cell], side: usize, left: usize, top: usize, width: usize, height: usize) -> Node {
+    let extent = width.max(height).next_power_of_two().max(4);
+    let mut padded = vec![Cell::Dead; extent * extent];
+    for y in 0..height {
+        for x in 0..width {
+            padded[x + y * extent] = cells[(x + left) + (y + top) * side];
+        }
+    }
+    Node::new(padded).expect("padded to a power-of-two square")
+}
+
+// What the next generation depends on besides the cells: the generation
+// before under a second-order rule, and which way the blocks lie under a
+// block rule.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Phase {
+    cells: Node,
+    previous: Option<Node>,
+    parity: usize,
+}
+
+// The current and previous generations of `stepper`, cropped to the box
+// around both, as `classify_phase` takes them.
+pub fn phase(stepper: &dyn Stepper) -> (Pattern, Pattern) {
+    let (cells, previous, side) = (stepper.cells(), stepper.previous(), stepper.side());
+    let either: Vec<Cell> = cells.iter().zip(&previous).map(|(cell, previous)| if *cell == Cell::Dead { *previous } else { *cell }).collect();
+    let Some((left, top, width, height)) = pattern::bounds(&either, side) else {
+        let empty = Pattern { width: 0, height: 0, cells: vec![] };
+        return (empty.clone(), empty);
+    };
+    (Pattern::crop(&cells, side, left, top, width, height), Pattern::crop(&previous, side, left, top, width, height))
+}
+
+// Moves everything alive in the grid by (`dx`, `dy`), keeping the previous
+// generation for second-order rules and the generation count for block rules.
+fn shift(stepper: &mut dyn Stepper, dx: isize, dy: isize) -> Result<(), Error> {
+    let side = stepper.side() as isize;
+    let moved = |cells: Vec<Cell>| {
+        let mut moved = vec![Cell::Dead; cells.len()];
+        for (i, cell) in cells.into_iter().enumerate().filter(|(_, cell)| *cell != Cell::Dead) {
+            let (x, y) = (i as isize % side + dx, i as isize / side + dy);
+            if (0..side).contains(&x) && (0..side).contains(&y) {
+                moved[(x + y * side) as usize] = cell;
+            }
+        }
+        moved
+    };
+    let (cells, previous, generation) = (stepper.cells(), stepper.previous(), stepper.generation());
+    stepper.load(&moved(cells))?;
+    stepper.load_previous(&moved(previous))?;
+    stepper.set_generation(generation);
+    Ok(())
+}
+
+// Runs `pattern` for up to `max_generations`, watching for a phase that has
+// been seen before, possibly translated.
+pub fn classify(pattern: &Pattern, rule: &Rule, max_generations: usize) -> Result<Analysis, Error> {
+    let previous = Pattern { width: pattern.width, height: pattern.height, cells: vec![Cell::Dead; pattern.cells.len()] };
+    classify_phase(pattern, &previous, 0, rule, max_generations)
+}
+
+// Like `classify`, but from the middle of a run: `previous` is the generation
+// before `pattern`, the same size and placed the same way, which second-order
+// rules bring back, and `generation` says which way block rules line up.
+pub fn classify_phase(pattern: &Pattern, previous: &Pattern, generation: usize, rule: &Rule, max_generations: usize) -> Result<Analysis, Error> {
+    // Only phases up to `MAX_EXTENT` across are tracked, so the grid only
+    // needs room for one of those with twice the rule's radius on each side.
+    // Whenever the pattern drifts to within the radius of an edge, where the
+    // next generation could spill over it, it is moved back to the middle,
+    // and positions are kept relative to where it started.
+    let radius = rule.radius();
+    let side = pattern.width.max(pattern.height).max(MAX_EXTENT) + 4 * radius;
+    let mut stepper = StepperKind::Dense.create(side, *rule)?;
+    stepper.load(&pattern.centred(side))?;
+    if rule.is_second_order() {
+        stepper.load_previous(&previous.centred(side))?;
+    }
+    stepper.set_generation(generation);
+
+    let mut seen: HashMap<Phase, usize> = HashMap::new();
+    let mut phases: Vec<(Phase, isize, isize)> = vec![];
+    let (mut moved_x, mut moved_y) = (0, 0);
+    for generation in 0..=max_generations {
+        if generation > 0 {
+            stepper.step(1);
+        }
+        // a second-order rule brings back cells of the previous generation,
+        // so it is only dead once both are empty
+        let cells = stepper.cells();
+        let previous = rule.is_second_order().then(|| stepper.previous());
+        let occupied: Vec<Cell> = match &previous {
+            Some(previous) => cells.iter().zip(previous).map(|(cell, previous)| if *cell == Cell::Dead { *previous } else { *cell }).collect(),
+            None => cells.clone(),
+        };
+        let Some((left, top, width, height)) = pattern::bounds(&occupied, side) else {
+            return Ok(Analysis { classification: Classification::Dies, settled_at: generation });
+        };
+        if width > MAX_EXTENT || height > MAX_EXTENT {
+            break;
+        }
+        let phase = Phase {
+            cells: normalise(&cells, side, left, top, width, height),
+            previous: previous.map(|previous| normalise(&previous, side, left, top, width, height)),
+            parity: if rule.blocks().is_some() { stepper.generation() % 2 } else { 0 },
+        };
+        let (x, y) = (left as isize - moved_x, top as isize - moved_y);
+        if let Some(&earlier) = seen.get(&phase) {
+            phases.push((phase, x, y));
+            let (period, dx, dy) = cycle(&phases[earlier..]);
+            let classification = match (period, dx, dy) {
+                (1, 0, 0) => Classification::StillLife,
+                (_, 0, 0) => Classification::Oscillator { period },
+                _ => Classification::Spaceship { period, dx, dy },
+            };
+            return Ok(Analysis { classification, settled_at: earlier });
+        }
+        seen.insert(phase.clone(), generation);
+        phases.push((phase, x, y));
+
+        if left < radius || top < radius || left + width + radius > side || top + height + radius > side {
+            // by an even distance, which leaves blocks lined up the same way
+            let dx = ((side - width) / 2) as isize - left as isize;
+            let dy = ((side - height) / 2) as isize - top as isize;
+            let (dx, dy) = (dx & !1, dy & !1);
+            shift(stepper.as_mut(), dx, dy)?;
+            moved_x += dx;
+            moved_y += dy;
+        }
+    }
+    Ok(Analysis { classification: Classification::Unknown, settled_at: max_generations })
+}
+
+// The period and displacement of a cycle, given its phases from the first to
+// the first repeat. Under a block rule the phases also hold which way the
+// blocks lie, so the cells themselves may repeat in half the cycle, as a
+// block rule's still life does.
+fn cycle(phases: &[(Phase, isize, isize)]) -> (usize, isize, isize) {
+    let period = phases.len() - 1;
+    let (first, last) = (&phases[0], &phases[period]);
+    let half = period / 2;
+    if period.is_multiple_of(2) && phases[0].0.parity != phases[half].0.parity {
+        let (dx, dy) = (phases[half].1 - first.1, phases[half].2 - first.2);
+        let repeats = (0..=half).all(|t| {
+            let (a, b) = (&phases[t], &phases[t + half]);
+            a.0.cells == b.0.cells && a.0.previous == b.0.previous && (b.1 - a.1, b.2 - a.2) == (dx, dy)
+        });
+        if repeats {
+            return (half, dx, dy);
+        }
+    }
+    (period, last.1 - first.1, last.2 - first.2)
+}
@@ -0,0 +1,174 @@
+use crate::analysis::{self, Classification};
+use crate::error::Error;
+use crate::pattern::Pattern;
+use crate::rule::Rule;
+use crate::stepper::StepperKind;
+use crate::universe::Cell;
+
+// Catagolue-style object names such as `xs4_33` (block), `xp2_7` (blinker) and
+// `xq4_153` (glider). The prefix gives the kind of object, `xs` for still
+// lifes, `xp` for oscillators and `xq` for spaceships, followed by the
+// population of a still life or the period of anything else. The rest is the
+// extended Wechsler code of the object, taken in whichever phase and
+// orientation gives the shortest, then alphabetically first, code.
+
+// How far ahead a pattern is run to find its period.
+const CLASSIFY_GENERATIONS: usize = 1000;
+
+const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+// Names what `pattern` settles into, or returns `None` if it dies out or no
+// period is found.
+pub fn encode(pattern: &Pattern, rule: &Rule) -> Result<Option<String>, Error> {
+    let analysis = analysis::classify(pattern, rule, CLASSIFY_GENERATIONS)?;
+    let settled = advance(pattern, rule, analysis.settled_at)?;
+    encode_as(&settled, rule, analysis.classification)
+}
+
+// Names `object`, which must already be periodic with the given
+// classification.
+pub fn encode_as(object: &Pattern, rule: &Rule, classification: Classification) -> Result<Option<String>, Error> {
+    let (prefix, period) = match classification {
+        Classification::StillLife => (format!("xs{}", object.population()), 1),
+        Classification::Oscillator { period } => (format!("xp{}", period), period),
+        Classification::Spaceship { period, .. } => (format!("xq{}", period), period),
+        Classification::Dies | Classification::Unknown => return Ok(None),
+    };
+    let wechsler = phases(object, rule, period)?
+        .iter()
+        .flat_map(Pattern::orientations)
+        .map(|orientation| to_wechsler(&orientation))
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .expect("there is always at least one phase");
+    Ok(Some(format!("{}_{}", prefix, wechsler)))
+}
+
+// Builds the object named by an apgcode, in its canonical phase and
+// orientation.
+pub fn decode(code: &str) -> Result<Pattern, Error> {
+    let malformed = || Error::Parse(format!("'{}' is not an apgcode", code));
+    let (prefix, wechsler) = code.split_once('_').ok_or_else(malformed)?;
+    let number = prefix
+        .strip_prefix("xs")
+        .or_else(|| prefix.strip_prefix("xp"))
+        .or_else(|| prefix.strip_prefix("xq"))
+        .ok_or_else(malformed)?;
+    let number: usize = number.parse().map_err(|_| malformed())?;
+
+    let pattern = from_wechsler(wechsler)?;
+    if prefix.starts_with("xs") && pattern.population() != number {
+        return Err(Error::Parse(format!("'{}' has {} cells, not {}", code, pattern.population(), number)));
+    }
+    Ok(pattern)
+}
+
+// Runs `pattern` for `generations` and crops the result.
+fn advance(pattern: &Pattern, rule: &Rule, generations: usize) -> Result<Pattern, Error> {
+    if generations == 0 {
+        return Ok(pattern.clone());
+    }
+    // nothing moves further than the rule's radius in a generation
+    let side = pattern.width.max(pattern.height) + (2 * generations + 2) * rule.radius();
+    let mut stepper = StepperKind::Dense.create(side, *rule)?;
+    stepper.load(&pattern.centred(side))?;
+    stepper.step(generations);
+    Ok(Pattern::from_grid(&stepper.cells(), side))
+}
+
+// The phases an object passes through over `period` generations.
+fn phases(object: &Pattern, rule: &Rule, period: usize) -> Result<Vec<Pattern>, Error> {
+    (0..period).map(|generation| advance(object, rule, generation)).collect()
+}
+
+// Extended Wechsler format: the pattern is cut into strips five rows tall,
+// separated by `z`. Each column of a strip is one character whose bits are
+// its cells, top row lowest. Runs of empty columns are shortened to `w` (two),
+// `x` (three) or `y` plus a digit (four or more), and trailing empty columns
+// are dropped.
+pub fn to_wechsler(pattern: &Pattern) -> String {
+    let mut strips = Vec::new();
+    for top in (0..pattern.height).step_by(5) {
+        let columns: Vec<usize> = (0..pattern.width)
+            .map(|x| {
+                (0..5)
+                    .filter(|row| top + row < pattern.height && pattern.get(x, top + row) == Cell::Alive)
+                    .map(|row| 1 << row)
+                    .sum()
+            })
+            .collect();
+        let used = columns.iter().rposition(|column| *column != 0).map_or(0, |last| last + 1);
+
+        let mut strip = String::new();
+        let mut zeros = 0;
+        for column in &columns[..used] {
+            if *column == 0 {
+                zeros += 1;
+                continue;
+            }
+            push_zeros(&mut strip, zeros);
+            zeros = 0;
+            strip.push(DIGITS[*column] as char);
+        }
+        strips.push(strip);
+    }
+    strips.join("z")
+}
+
+fn push_zeros(strip: &mut String, mut zeros: usize) {
+    while zeros > 0 {
+        let run = zeros.min(4 + DIGITS.len() - 1);
+        match run {
+            1 => strip.push('0'),
+            2 => strip.push('w'),
+            3 => strip.push('x'),
+            _ => {
+                strip.push('y');
+                strip.push(DIGITS[run - 4] as char);
+            }
+        }
+        zeros -= run;
+    }
+}
+
+fn digit(c: char) -> Option<usize> {
+    DIGITS.iter().position(|d| *d as char == c)
+}
+
+// Parses extended Wechsler format, cropping the result to its live cells.
+pub fn from_wechsler(text: &str) -> Result<Pattern, Error> {
+    let malformed = |c: char| Error::Parse(format!("unexpected '{}' in Wechsler code '{}'", c, text));
+    let mut live = Vec::new();
+    let (mut x, mut strip) = (0, 0);
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            'w' => x += 2,
+            'x' => x += 3,
+            // read before `z` is seen as a separator, as `yz` is a run of 39
+            'y' => {
+                let run = chars.next().ok_or_else(|| malformed(c))?;
+                x += 4 + digit(run).ok_or_else(|| malformed(run))?;
+            }
+            'z' => {
+                x = 0;
+                strip += 1;
+            }
+            _ => {
+                let column = digit(c).filter(|column| *column < 32).ok_or_else(|| malformed(c))?;
+                for row in 0..5 {
+                    if column & (1 << row) != 0 {
+                        live.push((x, strip * 5 + row));
+                    }
+                }
+                x += 1;
+            }
+        }
+    }
+
+    let side = live.iter().map(|(x, y)| x.max(y) + 1).max().unwrap_or(0);
+    let mut grid = vec![Cell::Dead; side * side];
+    for (x, y) in live {
+        grid[x + y * side] = Cell::Alive;
+    }
+    Ok(Pattern::from_grid(&grid, side))
+}
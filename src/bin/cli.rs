@@ -0,0 +1,246 @@
+use std::{env, error::Error, fs, io::{Read, Write}, process};
+use gameoflife::analysis::{self, Classification};
+use gameoflife::apgcode;
+use gameoflife::census::{Census, CensusOptions};
+use gameoflife::metrics::{Metrics, MetricsOptions};
+use gameoflife::pattern::Pattern;
+use gameoflife::predecessor::{self, Boundary, PredecessorOptions};
+use gameoflife::rule::Rule;
+use gameoflife::ruletable;
+use gameoflife::search::{self, SearchOptions};
+use gameoflife::stepper::StepperKind;
+
+const USAGE: &str = "usage: cli [--engine quadtree|dense] [--rule RULE | --rule-file FILE] [--side N] [--generations N] [--classify] [PATTERN]
+       cli --census N [--seed S] [--rule RULE] [--output FILE]
+       cli --metrics N [--seed S] [--rule RULE | --rules FILE] [--output FILE]
+       cli --search WxH [--period P] [--rule RULE] [--output FILE]
+       cli --predecessor [--margin M] [--boundary dead|free] [--count N] [--dimacs FILE] [--rule RULE] [--output FILE] [PATTERN]
+
+Runs a plaintext or RLE pattern (read from PATTERN, or stdin) centred in an empty
+grid and prints the result. With --classify, reports whether the pattern is a
+still life, oscillator or spaceship instead, looking up to --generations
+(default 1000) ahead, followed by its apgcode.
+
+With --census, runs N random 16x16 soups from seed S (default 0) until they
+settle, and reports how often each object turned up, with the soups behind
+the rarest finds. The report goes to FILE, or stdout.
+
+With --metrics, runs N random soups from seed S under each rule, given one
+per line in --rules FILE or by --rule, and writes a line per rule with a
+guess at its behaviour (stable, complex, chaotic or explosive), the final
+density, growth, sensitivity to a single cell and the periods soups settled
+into.
+
+With --search, finds every still life, or every oscillator of period P, that
+fits in a W by H box, and writes each as RLE named by its apgcode.
+
+With --predecessor, looks for up to N (default 1) patterns that become
+PATTERN in one generation, within its box grown by M cells (default 1) on
+each side, using a SAT solver. By default everything around PATTERN must be
+dead afterwards, which rules out B0 rules; with --boundary free, which needs
+M of at least 1, only PATTERN's own cells must match, so finding none proves
+it is an orphan (a Garden of Eden). --dimacs also writes the problem to FILE
+for an external solver.
+
+--rule-file loads a Golly .rule file with a @TABLE or @TREE in place of --rule.";
+
+struct Options {
+    engine: StepperKind,
+    rule: Rule,
+    side: usize,
+    generations: Option<usize>,
+    classify: bool,
+    census: Option<usize>,
+    metrics: Option<usize>,
+    rules: Option<String>,
+    search: Option<(usize, usize)>,
+    period: usize,
+    predecessor: bool,
+    margin: usize,
+    boundary: Boundary,
+    count: usize,
+    dimacs: Option<String>,
+    seed: u64,
+    output: Option<String>,
+    path: Option<String>,
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+    let mut options = Options {
+        engine: StepperKind::Dense,
+        rule: Rule::default(),
+        side: 64,
+        generations: None,
+        classify: false,
+        census: None,
+        metrics: None,
+        rules: None,
+        search: None,
+        period: 1,
+        predecessor: false,
+        margin: 1,
+        boundary: Boundary::Dead,
+        count: 1,
+        dimacs: None,
+        seed: 0,
+        output: None,
+        path: None,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--engine" => options.engine = value()?.parse()?,
+            "--rule" => options.rule = value()?.parse()?,
+            "--rule-file" => {
+                let path = value()?;
+                let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+                options.rule = Rule::from_table(ruletable::parse(&text)?);
+            }
+            "--side" => options.side = value()?.parse().map_err(|_| "invalid side".to_string())?,
+            "--generations" => options.generations = Some(value()?.parse().map_err(|_| "invalid generation count".to_string())?),
+            "--classify" => options.classify = true,
+            "--census" => options.census = Some(value()?.parse().map_err(|_| "invalid soup count".to_string())?),
+            "--metrics" => options.metrics = Some(value()?.parse().map_err(|_| "invalid soup count".to_string())?),
+            "--rules" => options.rules = Some(value()?),
+            "--search" => {
+                let text = value()?;
+                let size = text
+                    .split_once('x')
+                    .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                    .ok_or_else(|| format!("invalid box size {}", text))?;
+                options.search = Some(size);
+            }
+            "--period" => options.period = value()?.parse().map_err(|_| "invalid period".to_string())?,
+            "--predecessor" => options.predecessor = true,
+            "--margin" => options.margin = value()?.parse().map_err(|_| "invalid margin".to_string())?,
+            "--boundary" => options.boundary = value()?.parse()?,
+            "--count" => options.count = value()?.parse().map_err(|_| "invalid predecessor count".to_string())?,
+            "--dimacs" => options.dimacs = Some(value()?),
+            "--seed" => options.seed = value()?.parse().map_err(|_| "invalid seed".to_string())?,
+            "--output" => options.output = Some(value()?),
+            "-h" | "--help" => return Err(USAGE.into()),
+            _ if options.path.is_none() && !arg.starts_with("--") => options.path = Some(arg),
+            _ => return Err(format!("unexpected argument {}\n\n{}", arg, USAGE).into()),
+        }
+    }
+    Ok(options)
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let options = parse_args()?;
+    if let Some(soups) = options.census {
+        let census = Census::run(CensusOptions {
+            seed: options.seed,
+            soups,
+            rule: options.rule,
+            ..CensusOptions::default()
+        })?;
+        match &options.output {
+            Some(path) => fs::write(path, census.report()).map_err(|e| format!("{}: {}", path, e))?,
+            None => print!("{}", census.report()),
+        }
+        return Ok(());
+    }
+    if let Some(soups) = options.metrics {
+        let rules = match &options.rules {
+            Some(path) => {
+                let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::parse)
+                    .collect::<Result<Vec<Rule>, _>>()?
+            }
+            None => vec![options.rule],
+        };
+        // each line is written as soon as it is measured, since long lists
+        // of rules take a while
+        let mut output: Box<dyn Write> = match &options.output {
+            Some(path) => Box::new(fs::File::create(path).map_err(|e| format!("{}: {}", path, e))?),
+            None => Box::new(std::io::stdout()),
+        };
+        for rule in rules {
+            let metrics = Metrics::measure(MetricsOptions { seed: options.seed, soups, rule, ..MetricsOptions::default() })?;
+            writeln!(output, "{}", metrics)?;
+        }
+        return Ok(());
+    }
+    if let Some((width, height)) = options.search {
+        let search_options = SearchOptions { rule: options.rule, width, height, period: options.period };
+        let report = search::report(&search::search(&search_options)?, &search_options);
+        match &options.output {
+            Some(path) => fs::write(path, report).map_err(|e| format!("{}: {}", path, e))?,
+            None => print!("{}", report),
+        }
+        return Ok(());
+    }
+    let text = match &options.path {
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+    let pattern = Pattern::from_text(&text)?;
+    if options.predecessor {
+        let predecessor_options = PredecessorOptions {
+            rule: options.rule,
+            margin: options.margin,
+            boundary: options.boundary,
+            limit: options.count,
+        };
+        if let Some(path) = &options.dimacs {
+            let encoding = predecessor::encode(&pattern, &predecessor_options)?;
+            fs::write(path, encoding.to_dimacs(&options.rule)).map_err(|e| format!("{}: {}", path, e))?;
+        }
+        let found = predecessor::predecessors(&pattern, &predecessor_options)?;
+        let mut report = String::new();
+        for (i, found) in found.iter().enumerate() {
+            report.push_str(&format!("#N predecessor {}\n", i + 1));
+            report.push_str(&found.to_rle(&options.rule));
+        }
+        if found.is_empty() {
+            match options.boundary {
+                Boundary::Dead => report.push_str(&format!("no predecessor within {} cells\n", options.margin)),
+                Boundary::Free => report.push_str("no predecessor: the pattern is an orphan\n"),
+            }
+        }
+        match &options.output {
+            Some(path) => fs::write(path, report).map_err(|e| format!("{}: {}", path, e))?,
+            None => print!("{}", report),
+        }
+        return Ok(());
+    }
+    if options.classify {
+        let analysis = analysis::classify(&pattern, &options.rule, options.generations.unwrap_or(1000))?;
+        match analysis.classification {
+            Classification::Unknown => println!("{}", analysis.classification),
+            _ => println!("{} (from generation {})", analysis.classification, analysis.settled_at),
+        }
+        if let Some(code) = apgcode::encode(&pattern, &options.rule)? {
+            println!("{}", code);
+        }
+        return Ok(());
+    }
+
+    let mut stepper = options.engine.create(options.side, options.rule)?;
+    stepper.load(&pattern.centred(options.side))?;
+    let generations = options.generations.unwrap_or(1);
+    stepper.step(generations);
+
+    print!("{}", Pattern::from_grid(&stepper.cells(), options.side).to_plaintext());
+    eprintln!("generation {}: population {}", generations, stepper.population());
+    if let Some(kind) = stepper.suggestion() {
+        eprintln!("hint: this pattern may run faster with --engine {}", kind);
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(message) = run() {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
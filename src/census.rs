@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use crate::analysis::{self, Classification};
+use crate::apgcode;
+use crate::error::Error;
+use crate::objects::{self, Grouping, Island};
+use crate::pattern::Pattern;
+use crate::random;
+use crate::rule::Rule;
+use crate::stepper::{Stepper, StepperKind};
+use crate::universe::Cell;
+
+// Soups are run in a grid this wide. Spaceships that escape the central
+// `CORE` square are counted and removed before they can reach the edge.
+const SIDE: usize = 512;
+const CORE: usize = 256;
+// How often escaped spaceships are looked for.
+const CLEANUP_INTERVAL: usize = 64;
+// A soup has stabilised once its population has repeated with a period of at
+// most `MAX_PERIOD` for the last `WINDOW` generations.
+const MAX_PERIOD: usize = 30;
+const WINDOW: usize = 120;
+// How far ahead an object is run to classify it. Islands are grouped into
+// objects if they affect each other within `MAX_PERIOD` generations.
+const CLASSIFY_GENERATIONS: usize = 256;
+// How far apart live cells can be and still share an island.
+const ISLAND_DISTANCE: usize = 1;
+
+pub const PATHOLOGICAL: &str = "PATHOLOGICAL";
+pub const UNKNOWN: &str = "zz_UNKNOWN";
+
+#[derive(Clone, Debug)]
+pub struct CensusOptions {
+    pub seed: u64,
+    pub soups: usize,
+    pub soup_size: usize,
+    pub density: f64,
+    pub rule: Rule,
+    // Soups that have not stabilised after this many generations are
+    // recorded as pathological.
+    pub max_generations: usize,
+    // Objects seen at most this many times have their soups listed in the
+    // report.
+    pub rare: usize,
+}
+
+impl Default for CensusOptions {
+    fn default() -> Self {
+        CensusOptions {
+            seed: 0,
+            soups: 100,
+            soup_size: 16,
+            density: 0.5,
+            rule: Rule::default(),
+            max_generations: 10000,
+            rare: 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Census {
+    pub options: CensusOptions,
+    pub counts: BTreeMap<String, usize>,
+    // The seeds of up to `options.rare` soups that produced each object.
+    pub samples: BTreeMap<String, Vec<u64>>,
+}
+
+// The seed of the `index`th soup of a census, so that any soup can be rebuilt
+// with `random::soup`. Hashed, so that censuses from neighbouring seeds do
+// not share soups.
+pub fn soup_seed(seed: u64, index: usize) -> u64 {
+    random::hash(seed, &[index as u64])
+}
+
+// The apgcode of an object, or `UNKNOWN` if it has no period.
+fn code(object: &Pattern, rule: &Rule, classification: Classification) -> Result<String, Error> {
+    Ok(apgcode::encode_as(object, rule, classification)?.unwrap_or_else(|| UNKNOWN.to_string()))
+}
+
+fn stabilised(populations: &[usize]) -> bool {
+    if populations.len() < WINDOW + MAX_PERIOD {
+        return false;
+    }
+    let recent = &populations[populations.len() - WINDOW - MAX_PERIOD..];
+    (1..=MAX_PERIOD).any(|period| (period..recent.len()).all(|i| recent[i] == recent[i - period]))
+}
+
+impl Census {
+    pub fn new(options: CensusOptions) -> Self {
+        Census {
+            options,
+            ..Census::default()
+        }
+    }
+
+    pub fn run(options: CensusOptions) -> Result<Census, Error> {
+        let mut census = Census::new(options);
+        for index in 0..census.options.soups {
+            census.search(soup_seed(census.options.seed, index))?;
+        }
+        Ok(census)
+    }
+
+    fn record(&mut self, code: String, seed: u64) {
+        let count = self.counts.entry(code.clone()).or_insert(0);
+        *count += 1;
+        let samples = self.samples.entry(code).or_default();
+        if samples.len() < self.options.rare && !samples.contains(&seed) {
+            samples.push(seed);
+        }
+    }
+
+    // Counts and erases spaceships that have left the core of the grid.
+    fn remove_escapees(&mut self, stepper: &mut dyn Stepper, seed: u64) -> Result<bool, Error> {
+        let core = (SIDE - CORE) / 2..(SIDE + CORE) / 2;
+        let mut removed = false;
+        for Island { left, top, pattern: object } in objects::islands(&stepper.cells(), SIDE, ISLAND_DISTANCE) {
+            let outside = left + object.width <= core.start
+                || top + object.height <= core.start
+                || left >= core.end
+                || top >= core.end;
+            if !outside {
+                continue;
+            }
+            let analysis = analysis::classify(&object, &self.options.rule, CLASSIFY_GENERATIONS)?;
+            if let Classification::Spaceship { .. } = analysis.classification {
+                self.record(code(&object, &self.options.rule, analysis.classification)?, seed);
+                for y in 0..object.height {
+                    for x in 0..object.width {
+                        if object.get(x, y) == Cell::Alive {
+                            stepper.set(left + x, top + y, Cell::Dead);
+                        }
+                    }
+                }
+                removed = true;
+            }
+        }
+        Ok(removed)
+    }
+
+    // Runs one soup to stabilisation and records the objects it leaves.
+    pub fn search(&mut self, seed: u64) -> Result<(), Error> {
+        let soup = random::soup(seed, self.options.soup_size, self.options.soup_size, self.options.density);
+        let mut stepper = StepperKind::Dense.create(SIDE, self.options.rule)?;
+        stepper.load(&soup.centred(SIDE))?;
+
+        let mut populations = vec![stepper.population()];
+        let mut generation = 0;
+        while !stabilised(&populations) {
+            if generation >= self.options.max_generations {
+                self.record(PATHOLOGICAL.to_string(), seed);
+                return Ok(());
+            }
+            stepper.step(1);
+            generation += 1;
+            populations.push(stepper.population());
+            if generation % CLEANUP_INTERVAL == 0 && self.remove_escapees(stepper.as_mut(), seed)? {
+                populations.clear();
+            }
+        }
+
+        for object in objects::objects(&stepper.cells(), SIDE, ISLAND_DISTANCE, &self.options.rule, MAX_PERIOD, Grouping::Interacting)? {
+            let analysis = analysis::classify(&object.pattern, &self.options.rule, CLASSIFY_GENERATIONS)?;
+            self.record(code(&object.pattern, &self.options.rule, analysis.classification)?, seed);
+        }
+        Ok(())
+    }
+
+    // A plain-text report: objects by descending frequency, then the seeds and
+    // contents of the soups that produced the rarest ones.
+    pub fn report(&self) -> String {
+        let options = &self.options;
+        let mut report = String::new();
+        writeln!(report, "# Census of {} soups from seed {}", options.soups, options.seed).unwrap();
+        writeln!(
+            report,
+            "# {}x{} soups at density {} under {}",
+            options.soup_size, options.soup_size, options.density, options.rule
+        )
+        .unwrap();
+        writeln!(report).unwrap();
+
+        let mut counts: Vec<(&String, &usize)> = self.counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (code, count) in &counts {
+            writeln!(report, "{:<40} {}", code, count).unwrap();
+        }
+
+        for (code, count) in counts.iter().rev().filter(|(_, count)| **count <= options.rare) {
+            writeln!(report).unwrap();
+            writeln!(report, "# {} ({} found)", code, count).unwrap();
+            for seed in &self.samples[*code] {
+                let soup = random::soup(*seed, options.soup_size, options.soup_size, options.density);
+                writeln!(report, "## soup {}", seed).unwrap();
+                report.push_str(&soup.to_plaintext());
+            }
+        }
+        report
+    }
+}
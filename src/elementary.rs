@@ -0,0 +1,186 @@
+use std::{collections::VecDeque, fmt, str::FromStr};
+use crate::error::Error;
+use crate::universe::Cell;
+
+// The most colours a totalistic rule can have while its code still fits in a
+// u64: with k colours the code has 3k - 2 digits in base k.
+pub const MAX_COLOURS: u8 = 7;
+
+// A one-dimensional rule, where each cell of a row looks at itself and the
+// cells either side of it. Cells beyond the ends of the row are always 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LineRule {
+    // Wolfram's numbering of the 256 two-colour rules: the new cell is bit
+    // 4 * left + 2 * centre + right of the number.
+    Elementary(u8),
+    // The new cell is digit left + centre + right of the code in base
+    // `colours`, counting from the lowest.
+    Totalistic { colours: u8, code: u64 },
+}
+
+impl LineRule {
+    pub fn totalistic(colours: u8, code: u64) -> Result<Self, Error> {
+        if !(2..=MAX_COLOURS).contains(&colours) {
+            return Err(Error::Parse(format!("a totalistic rule needs 2 to {} colours, not {}", MAX_COLOURS, colours)));
+        }
+        let codes = (colours as u64).pow(3 * colours as u32 - 2);
+        if code >= codes {
+            return Err(Error::Parse(format!("a {}-colour code must be below {}, not {}", colours, codes, code)));
+        }
+        Ok(LineRule::Totalistic { colours, code })
+    }
+
+    pub fn colours(&self) -> u8 {
+        match self {
+            LineRule::Elementary(_) => 2,
+            LineRule::Totalistic { colours, .. } => *colours,
+        }
+    }
+
+    pub fn next(&self, left: u8, centre: u8, right: u8) -> u8 {
+        match *self {
+            LineRule::Elementary(number) => number >> (4 * left + 2 * centre + right) & 1,
+            LineRule::Totalistic { colours, code } => {
+                let sum = (left + centre + right) as u32;
+                (code / (colours as u64).pow(sum) % colours as u64) as u8
+            }
+        }
+    }
+
+    pub fn next_row(&self, row: &[u8]) -> Vec<u8> {
+        let at = |x: usize| row.get(x).copied().unwrap_or(0);
+        (0..row.len())
+            .map(|x| self.next(if x > 0 { at(x - 1) } else { 0 }, at(x), at(x + 1)))
+            .collect()
+    }
+}
+
+// `W30` for an elementary rule, as in Golly, and `T777K3` for the totalistic
+// rule with code 777 and 3 colours.
+impl fmt::Display for LineRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LineRule::Elementary(number) => write!(f, "W{}", number),
+            LineRule::Totalistic { colours, code } => write!(f, "T{}K{}", code, colours),
+        }
+    }
+}
+
+impl FromStr for LineRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let number = |text: &str| text.parse::<u64>().map_err(|_| Error::Parse(format!("invalid number \"{}\" in \"{}\"", text, s)));
+        if let Some(number_text) = s.strip_prefix(['W', 'w']) {
+            let number = number(number_text)?;
+            return u8::try_from(number)
+                .map(LineRule::Elementary)
+                .map_err(|_| Error::Parse(format!("elementary rules go up to 255, not {}", number)));
+        }
+        if let Some(rest) = s.strip_prefix(['T', 't']) {
+            let (code, colours) = rest
+                .split_once(['K', 'k'])
+                .ok_or_else(|| Error::Parse(format!("expected K and the number of colours in \"{}\"", s)))?;
+            let colours = number(colours)?;
+            let colours = u8::try_from(colours).map_err(|_| Error::Parse(format!("too many colours: {}", colours)))?;
+            return LineRule::totalistic(colours, number(code)?);
+        }
+        Err(Error::Parse(format!("expected W or T at the start of \"{}\"", s)))
+    }
+}
+
+// Whether `text` looks like a one-dimensional rule rather than a 2D one.
+pub fn is_line_rule(text: &str) -> bool {
+    let mut chars = text.trim().chars();
+    matches!(chars.next(), Some('W' | 'w' | 'T' | 't')) && chars.next().is_some_and(|c| c.is_ascii_digit())
+}
+
+// A row of cells and the generations it went through, kept as a space-time
+// diagram `height` rows tall, oldest at the top. Once the diagram is full,
+// each step drops the top row, so the diagram scrolls up.
+pub struct SpaceTime {
+    rule: LineRule,
+    width: usize,
+    height: usize,
+    rows: VecDeque<Vec<u8>>,
+    generation: usize,
+}
+
+impl SpaceTime {
+    pub fn new(rule: LineRule, width: usize, height: usize) -> Result<Self, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidDimensions(format!("a space-time diagram cannot be {}x{}", width, height)));
+        }
+        Ok(SpaceTime { rule, width, height, rows: VecDeque::from([vec![0; width]]), generation: 0 })
+    }
+
+    // Starts again from `row`, with the diagram cleared.
+    pub fn load(&mut self, row: &[u8]) -> Result<(), Error> {
+        if row.len() != self.width {
+            return Err(Error::InvalidDimensions(format!("expected a row of {} cells, got {}", self.width, row.len())));
+        }
+        if let Some(state) = row.iter().find(|state| **state >= self.rule.colours()) {
+            return Err(Error::Parse(format!("{} has no colour {}", self.rule, state)));
+        }
+        self.rows = VecDeque::from([row.to_vec()]);
+        self.generation = 0;
+        Ok(())
+    }
+
+    pub fn step(&mut self, generations: usize) {
+        for _ in 0..generations {
+            let next = self.rule.next_row(self.row());
+            self.rows.push_back(next);
+            if self.rows.len() > self.height {
+                self.rows.pop_front();
+            }
+            self.generation += 1;
+        }
+    }
+
+    pub fn rule(&self) -> LineRule {
+        self.rule
+    }
+
+    // Carries on from the current row under another rule. Colours the rule
+    // does not have become 0.
+    pub fn set_rule(&mut self, rule: LineRule) {
+        for row in self.rows.iter_mut() {
+            row.iter_mut().filter(|state| **state >= rule.colours()).for_each(|state| *state = 0);
+        }
+        self.rule = rule;
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    // The newest row.
+    pub fn row(&self) -> &[u8] {
+        self.rows.back().expect("there is always a row")
+    }
+
+    // Changes a cell of the newest row.
+    pub fn set(&mut self, x: usize, state: u8) {
+        let row = self.rows.back_mut().expect("there is always a row");
+        row[x] = state;
+    }
+
+    // The cell at (x, y) of the diagram, where the rows not reached yet are 0.
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        Cell::from_state(self.rows.get(y).map_or(0, |row| row[x]))
+    }
+
+    // The diagram as a `width * height` grid, row-major, where the rows not
+    // reached yet are 0.
+    pub fn cells(&self) -> Vec<Cell> {
+        let mut cells: Vec<Cell> = self.rows.iter().flatten().map(|state| Cell::from_state(*state)).collect();
+        cells.resize(self.width * self.height, Cell::Dead);
+        cells
+    }
+}
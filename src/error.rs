@@ -0,0 +1,26 @@
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    // A grid or pattern whose size the engine cannot work with.
+    InvalidDimensions(String),
+    // Malformed input: pattern files, rule strings, engine names.
+    Parse(String),
+    // A well-formed rule that the engine does not implement.
+    UnsupportedRule(String),
+    // The canvas or its 2D context could not be obtained.
+    RenderContext(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidDimensions(message) => write!(f, "invalid dimensions: {}", message),
+            Error::Parse(message) => write!(f, "parse error: {}", message),
+            Error::UnsupportedRule(message) => write!(f, "unsupported rule: {}", message),
+            Error::RenderContext(message) => write!(f, "rendering failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
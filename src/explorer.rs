@@ -0,0 +1,380 @@
+use std::{collections::VecDeque, fmt, str::FromStr};
+use yew::prelude::*;
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlInputElement, HtmlSelectElement};
+use crate::error::Error;
+use crate::metrics::{Behaviour, Metrics, MetricsOptions};
+use crate::random::{self, Random};
+use crate::rule::Rule;
+use crate::stepper::{Stepper, StepperKind};
+use crate::universe::Cell;
+
+// How many previews are shown at once, each a dense grid this wide started
+// from a soup this wide in the middle.
+pub const PREVIEWS: usize = 12;
+pub const PREVIEW_SIDE: usize = 32;
+const SOUP_SIDE: usize = 16;
+const SOUP_DENSITY: f64 = 0.4;
+// Pixels per preview cell, and milliseconds between preview generations.
+const PREVIEW_SCALE: usize = 4;
+const TICK: i32 = 150;
+// Each preview's behaviour is guessed from a few short soups, small enough to
+// measure one rule each tick, so the previews fill in one by one. With dull
+// rules hidden, up to this many candidates are tried per preview.
+const TRIAGE_SOUPS: usize = 4;
+const TRIAGE_SIDE: usize = 64;
+const TRIAGE_GENERATIONS: usize = 300;
+const CANDIDATES: usize = 4;
+
+// Where the explorer's rules come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Family {
+    // Any two-state rule on the Moore neighbourhood.
+    Random,
+    // The current rule with one birth or survival count added or removed.
+    Mutations,
+}
+
+impl Family {
+    pub const ALL: [Family; 2] = [Family::Random, Family::Mutations];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Family::Random => "random",
+            Family::Mutations => "mutations",
+        }
+    }
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for Family {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Family::ALL
+            .into_iter()
+            .find(|family| family.name() == s)
+            .ok_or_else(|| Error::Parse(format!("unknown rule family \"{}\"", s)))
+    }
+}
+
+// A random Life-like rule. Birth on 0 is left out, since the whole grid would
+// flash on and off.
+pub fn random_rule(random: &mut Random) -> Rule {
+    let birth: Vec<usize> = (1..=8).filter(|_| random.chance(0.3)).collect();
+    let survival: Vec<usize> = (0..=8).filter(|_| random.chance(0.3)).collect();
+    Rule::new(&birth, &survival)
+}
+
+// The rule with one count added to or removed from its births or survivals,
+// keeping its states, neighbourhood and any second-order or chance part.
+pub fn mutation(rule: &Rule, random: &mut Random) -> Result<Rule, Error> {
+    if !rule.is_totalistic() || rule.range().is_some() {
+        return Err(Error::UnsupportedRule(format!("{} has no counts to mutate", rule)));
+    }
+    let size = rule.neighbourhood().size();
+    let mut birth: Vec<usize> = rule.births().collect();
+    let mut survival: Vec<usize> = rule.survivals().collect();
+    // counts 1 to size are births, and size + 1 onwards survivals from 0
+    let choice = 1 + random.below(2 * size as u64 + 1) as usize;
+    let (counts, count) = if choice <= size { (&mut birth, choice) } else { (&mut survival, choice - size - 1) };
+    match counts.iter().position(|c| *c == count) {
+        Some(i) => {
+            counts.remove(i);
+        }
+        None => counts.push(count),
+    }
+    let mut mutated = Rule::generations(&birth, &survival, rule.states())?.with_neighbourhood(rule.neighbourhood());
+    if rule.is_second_order() {
+        mutated = mutated.second_order()?;
+    }
+    if let Some(chances) = rule.chances() {
+        mutated = mutated.stochastic(chances)?;
+    }
+    Ok(mutated)
+}
+
+// Up to `count` different rules from `family`, none of them `current`. There
+// can be fewer mutations than asked for on small neighbourhoods.
+pub fn rules(family: Family, current: &Rule, count: usize, seed: u64) -> Result<Vec<Rule>, Error> {
+    let mut random = Random::new(seed);
+    let mut rules = Vec::with_capacity(count);
+    for _ in 0..count * 20 {
+        if rules.len() == count {
+            break;
+        }
+        let rule = match family {
+            Family::Random => random_rule(&mut random),
+            Family::Mutations => mutation(current, &mut random)?,
+        };
+        if rule != *current && !rules.contains(&rule) {
+            rules.push(rule);
+        }
+    }
+    Ok(rules)
+}
+
+// A quick guess at how `rule` behaves, from soups starting at `seed`.
+pub fn triage(rule: Rule, seed: u64) -> Result<Behaviour, Error> {
+    let options = MetricsOptions {
+        seed,
+        soups: TRIAGE_SOUPS,
+        rule,
+        side: TRIAGE_SIDE,
+        generations: TRIAGE_GENERATIONS,
+        ..MetricsOptions::default()
+    };
+    Ok(Metrics::measure(options)?.behaviour)
+}
+
+// A small universe running one rule from the same soup as the others, so
+// that they differ only by their rules.
+pub struct Preview {
+    pub rule: Rule,
+    pub behaviour: Behaviour,
+    stepper: Box<dyn Stepper>,
+}
+
+impl Preview {
+    pub fn new(rule: Rule, seed: u64) -> Result<Self, Error> {
+        let mut stepper = StepperKind::Dense.create(PREVIEW_SIDE, rule)?;
+        stepper.load(&random::soup(seed, SOUP_SIDE, SOUP_SIDE, SOUP_DENSITY).centred(PREVIEW_SIDE))?;
+        Ok(Preview { rule, behaviour: triage(rule, seed)?, stepper })
+    }
+
+    // Whether the rule is worth a look: stable and explosive rules rarely are.
+    pub fn is_lively(&self) -> bool {
+        matches!(self.behaviour, Behaviour::Complex | Behaviour::Chaotic)
+    }
+
+    pub fn step(&mut self) {
+        self.stepper.step(1);
+    }
+
+    pub fn stepper(&self) -> &dyn Stepper {
+        self.stepper.as_ref()
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    // The rule mutations start from.
+    pub current: Rule,
+    // Called with the preview's rule when one is clicked.
+    pub on_promote: Callback<Rule>,
+}
+
+pub enum Msg {
+    SetFamily(Family),
+    HideDull(bool),
+    Shuffle,
+    Tick,
+    Promote(usize),
+}
+
+// A grid of previews that run by themselves until the explorer is closed.
+pub struct Explorer {
+    family: Family,
+    hide_dull: bool,
+    seed: u64,
+    previews: Vec<Preview>,
+    // Rules still to be triaged, one each tick, until the previews are full.
+    candidates: VecDeque<Rule>,
+    canvases: Vec<NodeRef>,
+    interval: Option<(i32, Closure<dyn Fn()>)>,
+    error: Option<String>,
+}
+
+impl Component for Explorer {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut explorer = Explorer {
+            family: Family::Random,
+            hide_dull: false,
+            seed: 1,
+            previews: vec![],
+            candidates: VecDeque::new(),
+            canvases: (0..PREVIEWS).map(|_| NodeRef::default()).collect(),
+            interval: None,
+            error: None,
+        };
+        explorer.shuffle(ctx);
+        explorer
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::SetFamily(family) => {
+                self.family = family;
+                self.shuffle(ctx);
+                true
+            }
+            Msg::HideDull(hide_dull) => {
+                self.hide_dull = hide_dull;
+                self.shuffle(ctx);
+                true
+            }
+            Msg::Shuffle => {
+                self.seed += 1;
+                self.shuffle(ctx);
+                true
+            }
+            Msg::Tick => {
+                for preview in self.previews.iter_mut() {
+                    preview.step();
+                }
+                let changed = self.triage_next();
+                self.draw();
+                changed
+            }
+            Msg::Promote(index) => {
+                if let Some(preview) = self.previews.get(index) {
+                    ctx.props().on_promote.emit(preview.rule);
+                }
+                false
+            }
+        }
+    }
+
+    // mutations follow the rule they come from
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        if self.family == Family::Mutations && ctx.props().current != old_props.current {
+            self.shuffle(ctx);
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let set_family = ctx.link().callback(|event: Event| {
+            let select: HtmlSelectElement = event.target_unchecked_into();
+            Msg::SetFamily(select.value().parse().unwrap_or(Family::Random))
+        });
+        let hide_dull = ctx.link().callback(|event: Event| {
+            let input: HtmlInputElement = event.target_unchecked_into();
+            Msg::HideDull(input.checked())
+        });
+        let shuffle = ctx.link().callback(|_| Msg::Shuffle);
+        let extent = (PREVIEW_SIDE * PREVIEW_SCALE).to_string();
+        html! {
+            <div>
+                <select onchange={set_family}>
+                    { for Family::ALL.iter().map(|family| html! {
+                        <option value={family.name()} selected={*family == self.family}>{ family.name() }</option>
+                    }) }
+                </select>
+                <label>
+                    <input type="checkbox" checked={self.hide_dull} onchange={hide_dull} />
+                    { "Hide stable and explosive rules" }
+                </label>
+                <button onclick={shuffle}>{ "Shuffle" }</button>
+                <div>
+                    { for self.previews.iter().zip(&self.canvases).enumerate().map(|(i, (preview, canvas))| {
+                        let promote = ctx.link().callback(move |_| Msg::Promote(i));
+                        html! {
+                            <figure onclick={promote}>
+                                <canvas width={extent.clone()} height={extent.clone()} ref={canvas.clone()}></canvas>
+                                <figcaption>{ format!("{} ({})", preview.rule, preview.behaviour) }</figcaption>
+                            </figure>
+                        }
+                    }) }
+                </div>
+                if !self.candidates.is_empty() {
+                    <p>{ "Trying more rules..." }</p>
+                }
+                if let Some(message) = &self.error {
+                    <p class="error">{ message }</p>
+                }
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            let link = ctx.link().clone();
+            let tick = Closure::<dyn Fn()>::new(move || link.send_message(Msg::Tick));
+            let id = web_sys::window()
+                .and_then(|window| window.set_interval_with_callback_and_timeout_and_arguments_0(tick.as_ref().unchecked_ref(), TICK).ok());
+            self.interval = id.map(|id| (id, tick));
+        }
+        self.draw();
+    }
+
+    fn destroy(&mut self, _ctx: &Context<Self>) {
+        if let (Some(window), Some((id, _))) = (web_sys::window(), &self.interval) {
+            window.clear_interval_with_handle(*id);
+        }
+    }
+}
+
+impl Explorer {
+    // Starts a new set of previews from the seed, which the ticks fill in.
+    fn shuffle(&mut self, ctx: &Context<Self>) {
+        let count = if self.hide_dull { PREVIEWS * CANDIDATES } else { PREVIEWS };
+        self.previews.clear();
+        match rules(self.family, &ctx.props().current, count, self.seed) {
+            Ok(rules) => {
+                self.candidates = rules.into();
+                self.error = None;
+            }
+            Err(error) => {
+                self.candidates.clear();
+                self.error = Some(error.to_string());
+            }
+        }
+    }
+
+    // Triages the next candidate, adding a preview for it unless it is dull
+    // and those are hidden. Returns whether there is anything new to show.
+    fn triage_next(&mut self) -> bool {
+        let Some(rule) = self.candidates.pop_front() else {
+            return false;
+        };
+        match Preview::new(rule, self.seed) {
+            Ok(preview) => {
+                if !self.hide_dull || preview.is_lively() {
+                    self.previews.push(preview);
+                }
+            }
+            Err(error) => {
+                self.candidates.clear();
+                self.error = Some(error.to_string());
+            }
+        }
+        if self.previews.len() == PREVIEWS {
+            self.candidates.clear();
+        }
+        true
+    }
+
+    fn draw(&self) {
+        for (preview, canvas) in self.previews.iter().zip(&self.canvases) {
+            let Some(context) = canvas
+                .cast::<HtmlCanvasElement>()
+                .and_then(|canvas| canvas.get_context("2d").ok().flatten())
+                .and_then(|context| context.dyn_into::<CanvasRenderingContext2d>().ok())
+            else {
+                continue;
+            };
+            let extent = (PREVIEW_SIDE * PREVIEW_SCALE) as f64;
+            context.set_fill_style_str("#FFFFFF");
+            context.fill_rect(0.0, 0.0, extent, extent);
+            context.set_fill_style_str("#000000");
+            let stepper = preview.stepper();
+            for y in 0..PREVIEW_SIDE {
+                for x in 0..PREVIEW_SIDE {
+                    if stepper.get(x, y) != Cell::Dead {
+                        let scale = PREVIEW_SCALE as f64;
+                        context.fill_rect(x as f64 * scale, y as f64 * scale, scale, scale);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::universe::Cell;
+
+// Per-cell metadata for a `side * side` grid: how long each cell has been
+// alive, and whether it has ever been alive, as in LifeHistory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct History {
+    side: usize,
+    // 0 for dead cells, otherwise the number of generations the cell has been
+    // alive, counting the one it was born in
+    ages: Vec<u32>,
+    ever_alive: Vec<bool>,
+}
+
+impl History {
+    pub fn new(cells: &[Cell], side: usize) -> Self {
+        let mut history = History {
+            side,
+            ages: vec![0; cells.len()],
+            ever_alive: vec![false; cells.len()],
+        };
+        history.step(cells);
+        history
+    }
+
+    // Records the next generation.
+    pub fn step(&mut self, cells: &[Cell]) {
+        for (i, cell) in cells.iter().enumerate() {
+            if *cell == Cell::Alive {
+                self.ages[i] = self.ages[i].saturating_add(1);
+                self.ever_alive[i] = true;
+            } else {
+                self.ages[i] = 0;
+            }
+        }
+    }
+
+    // Records a cell edited by hand, which counts as newly born or killed.
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        let i = x + y * self.side;
+        self.ages[i] = match cell {
+            Cell::Alive => 1,
+            Cell::Dead | Cell::State(_) => 0,
+        };
+        self.ever_alive[i] |= cell == Cell::Alive;
+    }
+
+    pub fn age(&self, x: usize, y: usize) -> u32 {
+        self.ages[x + y * self.side]
+    }
+
+    pub fn ever_alive(&self, x: usize, y: usize) -> bool {
+        self.ever_alive[x + y * self.side]
+    }
+}
+
+// A colour gradient from `from` for newborn cells to `to` for cells `span`
+// or more generations old.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ramp {
+    pub from: [u8; 3],
+    pub to: [u8; 3],
+    pub span: u32,
+}
+
+impl Ramp {
+    // The CSS colour for a cell of the given age, which should be at least 1.
+    pub fn colour(&self, age: u32) -> String {
+        let t = age.saturating_sub(1).min(self.span) as f64 / self.span.max(1) as f64;
+        let channel = |i: usize| (self.from[i] as f64 + (self.to[i] as f64 - self.from[i] as f64) * t).round() as u8;
+        format!("#{:02X}{:02X}{:02X}", channel(0), channel(1), channel(2))
+    }
+}
+
+pub const RAMPS: &[(&str, Ramp)] = &[
+    ("fire", Ramp { from: [0xFF, 0xE0, 0x40], to: [0x80, 0x00, 0x00], span: 32 }),
+    ("ice", Ramp { from: [0x40, 0xC0, 0xFF], to: [0x00, 0x00, 0x60], span: 32 }),
+    ("grey", Ramp { from: [0xC0, 0xC0, 0xC0], to: [0x00, 0x00, 0x00], span: 16 }),
+];
+
+// Cells that were once alive but are now dead, in LifeHistory's green.
+pub const HISTORY_COLOUR: &str = "#C0F0C0";
+
+// Dying states of Generations rules, from the first, just after the cell was
+// alive, to the last, just before it is dead.
+pub const DYING: Ramp = Ramp { from: [0x20, 0x40, 0xC0], to: [0xD0, 0xE0, 0xFF], span: 1 };
+
+// The CSS colour of `state` under a rule with `states` states in all.
+pub fn state_colour(state: u8, states: u8) -> String {
+    let ramp = Ramp { span: states.saturating_sub(3) as u32, ..DYING };
+    ramp.colour(state.saturating_sub(1) as u32)
+}
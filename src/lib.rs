@@ -1,9 +1,30 @@
 use yew::prelude::*;
 
 mod universe;
-mod node;
+pub mod analysis;
+pub mod apgcode;
+pub mod census;
+pub mod elementary;
+pub mod error;
+pub mod explorer;
+pub mod history;
+pub mod library;
+pub mod life3d;
+pub mod metrics;
+pub mod node;
+pub mod objects;
+pub mod pattern;
+pub mod predecessor;
+pub mod random;
+pub mod reference;
+pub mod rule;
+pub mod ruletable;
+pub mod sat;
+pub mod search;
+pub mod stepper;
 
 use universe::Universe;
+pub use universe::Cell;
 
 #[macro_use]
 extern crate lazy_static;
@@ -0,0 +1,187 @@
+use crate::apgcode;
+use crate::pattern::Pattern;
+use crate::rule::Rule;
+
+// Well-known patterns, in plaintext format.
+pub struct Entry {
+    pub name: &'static str,
+    pub plaintext: &'static str,
+}
+
+pub const PATTERNS: &[Entry] = &[
+    Entry {
+        name: "block",
+        plaintext: "\
+OO
+OO
+",
+    },
+    Entry {
+        name: "blinker",
+        plaintext: "\
+OOO
+",
+    },
+    Entry {
+        name: "glider",
+        plaintext: "\
+.O.
+..O
+OOO
+",
+    },
+    Entry {
+        name: "lightweight spaceship",
+        plaintext: "\
+.O..O
+O....
+O...O
+OOOO.
+",
+    },
+    Entry {
+        name: "pentadecathlon",
+        plaintext: "\
+..O....O..
+OO.OOOO.OO
+..O....O..
+",
+    },
+    Entry {
+        name: "gosper glider gun",
+        plaintext: "\
+........................O...........
+......................O.O...........
+............OO......OO............OO
+...........O...O....OO............OO
+OO........O.....O...OO..............
+OO........O...O.OO....O.O...........
+..........O.....O.......O...........
+...........O...O....................
+............OO......................
+",
+    },
+    Entry {
+        name: "r-pentomino",
+        plaintext: "\
+.OO
+OO.
+.O.
+",
+    },
+    Entry {
+        name: "acorn",
+        plaintext: "\
+.O.....
+...O...
+OO..OOO
+",
+    },
+    // Tim Coe's spacefiller, whose population grows with the square of the
+    // generation.
+    Entry {
+        name: "max",
+        plaintext: "\
+..................O........
+.................OOO.......
+............OOO....OO......
+...........O..OOO..O.OO....
+..........O...O.O..O.O.....
+..........O....O.O.O.O.OO..
+............O....O.O...OO..
+OOOO.....O.O....O...O.OOO..
+O...OO.O.OOO.OO.........OO.
+O.....OO.....O.............
+.O..OO.O..O..O.OO..........
+.......O.O.O.O.O.O.....OOOO
+.O..OO.O..O..O..OO.O.OO...O
+O.....OO...O.O.O...OO.....O
+O...OO.O.OO..O..O..O.OO..O.
+OOOO.....O.O.O.O.O.O.......
+..........OO.O..O..O.OO..O.
+.............O.....OO.....O
+.OO.........OO.OOO.O.OO...O
+..OOO.O...O....O.O.....OOOO
+..OO...O.O....O............
+..OO.O.O.O.O....O..........
+.....O.O..O.O...O..........
+....OO.O..OOO..O...........
+......OO....OOO............
+.......OOO.................
+........O..................
+",
+    },
+];
+
+// WireWorld circuits, with `3` for copper and `1` and `2` for the head and
+// tail of an electron. Signals go in on the left and come out on the right.
+pub const CIRCUITS: &[Entry] = &[
+    Entry {
+        name: "diode",
+        plaintext: "\
+....33....
+33333.3333
+....33....
+",
+    },
+    Entry {
+        name: "or gate",
+        plaintext: "\
+33333......
+.....3.....
+....3333333
+.....3.....
+33333......
+",
+    },
+    Entry {
+        // the diodes stop a lone signal running back up the other input
+        name: "xor gate",
+        plaintext: "\
+.....33.............
+333333.333333.......
+.....33......3......
+..............33....
+...............33333
+..............33....
+.....33......3......
+333333.333333.......
+.....33.............
+",
+    },
+    Entry {
+        // sends out an electron every 10 generations
+        name: "clock",
+        plaintext: "\
+.2133.
+3....3333333
+.3333.
+",
+    },
+];
+
+// Looks a pattern up by name, or by apgcode, e.g. `xp2_7` for the blinker.
+// Text ending in `!`, such as `3o!`, is parsed as RLE, including the rule in
+// its header. Only the first record is loaded.
+pub fn get(name: &str) -> Option<Pattern> {
+    PATTERNS
+        .iter()
+        .chain(CIRCUITS)
+        .find(|entry| entry.name == name)
+        .map(|entry| Pattern::from_plaintext(entry.plaintext).expect("library patterns are valid plaintext"))
+        .or_else(|| apgcode::decode(name).ok())
+        .or_else(|| is_rle(name).then(|| Pattern::from_rle(name).ok()).flatten())
+}
+
+fn is_rle(name: &str) -> bool {
+    name.trim_end().ends_with('!')
+}
+
+// The rule a library pattern is meant for, if it is not Life, or the one
+// named in an RLE header.
+pub fn rule(name: &str) -> Option<Rule> {
+    if CIRCUITS.iter().any(|entry| entry.name == name) {
+        return Some(Rule::wireworld());
+    }
+    is_rle(name).then(|| Pattern::rle_rule(name).ok().flatten()).flatten()
+}
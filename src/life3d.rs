@@ -0,0 +1,485 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use crate::error::Error;
+use crate::node::{Footprint, Memo};
+use crate::random::Random;
+use crate::universe::Cell;
+
+// The most neighbours a cell has in the cubic lattice.
+pub const NEIGHBOURS: usize = 26;
+
+// One of Bays' 3D Life rules: a live cell survives with `survival.0` to
+// `survival.1` live neighbours out of its 26, and a dead cell comes alive
+// with `birth.0` to `birth.1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rule3D {
+    survival: (usize, usize),
+    birth: (usize, usize),
+}
+
+impl Rule3D {
+    pub fn new(survival: (usize, usize), birth: (usize, usize)) -> Result<Self, Error> {
+        for (low, high) in [survival, birth] {
+            if low > high || high > NEIGHBOURS {
+                return Err(Error::Parse(format!("{}..{} is not a range of 0 to {} neighbours", low, high, NEIGHBOURS)));
+            }
+        }
+        Ok(Rule3D { survival, birth })
+    }
+
+    pub fn survival(&self) -> (usize, usize) {
+        self.survival
+    }
+
+    pub fn birth(&self) -> (usize, usize) {
+        self.birth
+    }
+
+    pub fn next(&self, cell: Cell, neighbours: usize) -> Cell {
+        let (low, high) = if cell == Cell::Alive { self.survival } else { self.birth };
+        if (low..=high).contains(&neighbours) {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+}
+
+// Bays' notation, survival range then birth range, as in `4555`, or with
+// commas between the four numbers when any of them needs two digits.
+impl fmt::Display for Rule3D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let numbers = [self.survival.0, self.survival.1, self.birth.0, self.birth.1];
+        if numbers.iter().all(|number| *number < 10) {
+            write!(f, "{}{}{}{}", numbers[0], numbers[1], numbers[2], numbers[3])
+        } else {
+            write!(f, "{},{},{},{}", numbers[0], numbers[1], numbers[2], numbers[3])
+        }
+    }
+}
+
+impl FromStr for Rule3D {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let numbers: Vec<usize> = if s.contains(',') {
+            s.split(',')
+                .map(|number| number.trim().parse().map_err(|_| Error::Parse(format!("invalid count \"{}\" in \"{}\"", number, s))))
+                .collect::<Result<_, _>>()?
+        } else {
+            s.chars()
+                .map(|c| c.to_digit(10).map(|digit| digit as usize).ok_or_else(|| Error::Parse(format!("invalid count '{}' in \"{}\"", c, s))))
+                .collect::<Result<_, _>>()?
+        };
+        let [survival_low, survival_high, birth_low, birth_high] = numbers[..] else {
+            return Err(Error::Parse(format!("expected four counts in \"{}\"", s)));
+        };
+        Rule3D::new((survival_low, survival_high), (birth_low, birth_high))
+    }
+}
+
+// Whether `text` looks like a 3D rule rather than a 2D one.
+pub fn is_3d_rule(text: &str) -> bool {
+    let text = text.trim();
+    text.chars().all(|c| c.is_ascii_digit() || c == ',') && (text.len() == 4 || text.matches(',').count() == 3)
+}
+
+// The cube counterpart of `Node`: a cube of cells with a power-of-two side,
+// split into eight octants down to single cells. Cubes are hash-consed like
+// squares: each is made only once and shared by every larger cube that
+// contains it, so cubes compare and hash by id alone. Octant i has x offset
+// i & 1, y offset i >> 1 & 1 and z offset i >> 2.
+#[derive(Clone)]
+pub struct Octree(Arc<Cube>);
+
+// id: unique among the cubes ever made
+// level: the side is 2 to the power of the level
+// population: live cells
+struct Cube {
+    id: u64,
+    level: u32,
+    population: usize,
+    content: Content,
+}
+
+enum Content {
+    Cell(Cell),
+    Octants([Octree; 8]),
+}
+
+// What a cube is looked up by when it is made: its cell, or the ids of its
+// octants.
+#[derive(PartialEq, Eq, Hash)]
+enum Key {
+    Cell(Cell),
+    Octants([u64; 8]),
+}
+
+// Every cube in use, pruned the same way as the squares of `Node`.
+#[derive(Default)]
+struct Cubes {
+    table: HashMap<Key, Octree>,
+    kept: usize,
+}
+
+// The table is not pruned below this many cubes.
+const MIN_CUBES: usize = 1 << 16;
+
+impl Cubes {
+    fn prune(&mut self) {
+        // dropping a cube can leave its octants unheld in turn
+        loop {
+            let before = self.table.len();
+            self.table.retain(|_, tree| Arc::strong_count(&tree.0) > 1);
+            if self.table.len() == before {
+                break;
+            }
+        }
+        self.kept = self.table.len();
+    }
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    static ref CUBES: Mutex<Cubes> = Mutex::new(Cubes::default());
+    // The centre half of a cube a generation on, kept separately for each rule
+    // and bounded as in the 2D memo.
+    static ref MEMO: Mutex<Memo<Rule3D, Octree, Octree>> = Mutex::new(Memo::default());
+}
+
+impl Footprint for Octree {
+    fn bytes(&self) -> usize {
+        std::mem::size_of::<Cube>()
+    }
+}
+
+impl PartialEq for Octree {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id == other.0.id
+    }
+}
+
+impl Eq for Octree {}
+
+impl Hash for Octree {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.id.hash(state);
+    }
+}
+
+impl fmt::Debug for Octree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Octree {{ side: {}, population: {} }}", self.side(), self.population())
+    }
+}
+
+// The offset along each axis of octant `i`.
+fn offsets(i: usize) -> (usize, usize, usize) {
+    (i & 1, i >> 1 & 1, i >> 2)
+}
+
+impl Octree {
+    fn make(key: Key, level: u32, population: usize, content: Content) -> Octree {
+        let mut cubes = CUBES.lock().unwrap();
+        if let Some(tree) = cubes.table.get(&key) {
+            return tree.clone();
+        }
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let tree = Octree(Arc::new(Cube { id, level, population, content }));
+        cubes.table.insert(key, tree.clone());
+        if cubes.table.len() >= (2 * cubes.kept).max(MIN_CUBES) {
+            cubes.prune();
+        }
+        tree
+    }
+
+    // Only live and dead cells are kept; other states are dead.
+    fn cell(cell: Cell) -> Octree {
+        let cell = if cell == Cell::Alive { Cell::Alive } else { Cell::Dead };
+        Octree::make(Key::Cell(cell), 0, (cell == Cell::Alive) as usize, Content::Cell(cell))
+    }
+
+    fn join(octants: [Octree; 8]) -> Octree {
+        let key = Key::Octants(octants.each_ref().map(|octant| octant.0.id));
+        let population = octants.iter().map(|octant| octant.0.population).sum();
+        Octree::make(key, octants[0].0.level + 1, population, Content::Octants(octants))
+    }
+
+    // The cube at `level` with every cell dead.
+    fn empty(level: u32) -> Octree {
+        if level == 0 {
+            return Octree::cell(Cell::Dead);
+        }
+        let octant = Octree::empty(level - 1);
+        Octree::join(std::array::from_fn(|_| octant.clone()))
+    }
+
+    // The octree of a `side` cube stored x first, then y, then z.
+    pub fn new(cells: &[Cell], side: usize) -> Result<Self, Error> {
+        if !side.is_power_of_two() || side < 2 || cells.len() != side * side * side {
+            return Err(Error::InvalidDimensions(format!(
+                "an octree needs a power-of-two side of at least 2, not {} cells",
+                cells.len()
+            )));
+        }
+        Ok(Octree::build(cells, side, (0, 0, 0), side))
+    }
+
+    fn build(cells: &[Cell], side: usize, (x, y, z): (usize, usize, usize), size: usize) -> Self {
+        if size == 1 {
+            return Octree::cell(cells[x + y * side + z * side * side]);
+        }
+        let half = size / 2;
+        Octree::join(std::array::from_fn(|i| {
+            let (dx, dy, dz) = offsets(i);
+            Octree::build(cells, side, (x + dx * half, y + dy * half, z + dz * half), half)
+        }))
+    }
+
+    fn octants(&self) -> &[Octree; 8] {
+        match &self.0.content {
+            Content::Octants(octants) => octants,
+            Content::Cell(_) => unreachable!("only cubes above side 1 have octants"),
+        }
+    }
+
+    pub fn side(&self) -> usize {
+        1 << self.0.level
+    }
+
+    pub fn population(&self) -> usize {
+        self.0.population
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Cell {
+        match &self.0.content {
+            Content::Cell(cell) => *cell,
+            Content::Octants(octants) => {
+                let half = self.side() / 2;
+                octants[x / half + 2 * (y / half) + 4 * (z / half)].get(x % half, y % half, z % half)
+            }
+        }
+    }
+
+    // The cube with the cell at (x, y, z) replaced, sharing every cube that
+    // does not contain it.
+    pub fn set(&self, x: usize, y: usize, z: usize, cell: Cell) -> Octree {
+        match &self.0.content {
+            Content::Cell(_) => Octree::cell(cell),
+            Content::Octants(octants) => {
+                let half = self.side() / 2;
+                let mut octants = octants.clone();
+                let i = x / half + 2 * (y / half) + 4 * (z / half);
+                octants[i] = octants[i].set(x % half, y % half, z % half, cell);
+                Octree::join(octants)
+            }
+        }
+    }
+
+    // The cube's cells, x first, then y, then z.
+    pub fn cells(&self) -> Vec<Cell> {
+        let side = self.side();
+        let mut cells = vec![Cell::Dead; side * side * side];
+        self.write(&mut cells, side, (0, 0, 0));
+        cells
+    }
+
+    fn write(&self, cells: &mut [Cell], side: usize, (x, y, z): (usize, usize, usize)) {
+        if self.0.population == 0 {
+            return;
+        }
+        match &self.0.content {
+            Content::Cell(cell) => cells[x + y * side + z * side * side] = *cell,
+            Content::Octants(octants) => {
+                let half = self.side() / 2;
+                for (i, octant) in octants.iter().enumerate() {
+                    let (dx, dy, dz) = offsets(i);
+                    octant.write(cells, side, (x + dx * half, y + dy * half, z + dz * half));
+                }
+            }
+        }
+    }
+
+    // The quarter-side cube at (x, y, z) of the 4x4x4 grid of them.
+    fn grandchild(&self, x: usize, y: usize, z: usize) -> &Octree {
+        &self.octants()[x / 2 + 2 * (y / 2) + 4 * (z / 2)].octants()[x % 2 + 2 * (y % 2) + 4 * (z % 2)]
+    }
+
+    // The cube in the middle of one twice the side, with dead cells around it.
+    fn pad(&self) -> Octree {
+        let empty = Octree::empty(self.0.level - 1);
+        Octree::join(std::array::from_fn(|i| {
+            // each octant goes in the corner of its new octant nearest the middle
+            Octree::join(std::array::from_fn(|j| if j == 7 - i { self.octants()[i].clone() } else { empty.clone() }))
+        }))
+    }
+
+    // The cube a generation on, with the cells around it dead.
+    pub fn next(&self, rule: &Rule3D) -> Octree {
+        self.pad().successor(rule)
+    }
+
+    // The cube's cells a generation on, with the cells around it dead.
+    pub fn evolve(&self, rule: &Rule3D) -> Vec<Cell> {
+        self.next(rule).cells()
+    }
+
+    // The centre half of the cube a generation on, worked out only from the
+    // cells in the cube. Cubes of side 4 go on cell by cell; larger ones are
+    // put together from the results of the 27 overlapping half-side cubes
+    // offset by a quarter side, each of which covers an eighth of the result
+    // along every axis.
+    fn successor(&self, rule: &Rule3D) -> Octree {
+        if self.0.population == 0 && rule.birth.0 > 0 {
+            return Octree::empty(self.0.level - 1);
+        }
+        let key = match MEMO.lock().unwrap().get(rule, self.clone()) {
+            Ok(result) => return result,
+            Err(key) => key,
+        };
+        let result = if self.0.level == 2 {
+            Octree::join(std::array::from_fn(|i| {
+                let (x, y, z) = offsets(i);
+                let (x, y, z) = (x + 1, y + 1, z + 1);
+                Octree::cell(rule.next(self.get(x, y, z), self.neighbours(x, y, z)))
+            }))
+        } else {
+            let results: Vec<Octree> = (0..27)
+                .map(|i| {
+                    let (a, b, c) = (i % 3, i / 3 % 3, i / 9);
+                    Octree::join(std::array::from_fn(|j| {
+                        let (dx, dy, dz) = offsets(j);
+                        self.grandchild(a + dx, b + dy, c + dz).clone()
+                    }))
+                    .successor(rule)
+                })
+                .collect();
+            // along each axis, the half of octant o nearer the start comes from
+            // the far half of result o, and the other half from the near half
+            // of result o + 1
+            Octree::join(std::array::from_fn(|o| {
+                let (ox, oy, oz) = offsets(o);
+                Octree::join(std::array::from_fn(|e| {
+                    let (ex, ey, ez) = offsets(e);
+                    let result = &results[(ox + ex) + 3 * (oy + ey) + 9 * (oz + ez)];
+                    result.octants()[(1 - ex) + 2 * (1 - ey) + 4 * (1 - ez)].clone()
+                }))
+            }))
+        };
+        MEMO.lock().unwrap().insert(rule, key, result.clone());
+        result
+    }
+
+    fn neighbours(&self, x: usize, y: usize, z: usize) -> usize {
+        let mut count = 0;
+        for dz in 0..3 {
+            for dy in 0..3 {
+                for dx in 0..3 {
+                    if (dx, dy, dz) != (1, 1, 1) && self.get(x + dx - 1, y + dy - 1, z + dz - 1) == Cell::Alive {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+}
+
+// A `side` cube of cells under a 3D rule, held as an octree. Cells outside
+// the cube are treated as permanently dead.
+pub struct Lattice {
+    rule: Rule3D,
+    side: usize,
+    root: Octree,
+    generation: usize,
+}
+
+impl Lattice {
+    pub fn new(side: usize, rule: Rule3D) -> Result<Self, Error> {
+        if !side.is_power_of_two() || side < 4 {
+            return Err(Error::InvalidDimensions(format!("a lattice needs a power-of-two side of at least 4, not {}", side)));
+        }
+        Ok(Lattice { rule, side, root: Octree::empty(side.trailing_zeros()), generation: 0 })
+    }
+
+    pub fn rule(&self) -> Rule3D {
+        self.rule
+    }
+
+    pub fn set_rule(&mut self, rule: Rule3D) {
+        self.rule = rule;
+    }
+
+    pub fn side(&self) -> usize {
+        self.side
+    }
+
+    pub fn load(&mut self, cells: &[Cell]) -> Result<(), Error> {
+        let volume = self.side * self.side * self.side;
+        if cells.len() != volume {
+            return Err(Error::InvalidDimensions(format!("expected {} cells for a side of {}, got {}", volume, self.side, cells.len())));
+        }
+        self.root = Octree::new(cells, self.side)?;
+        Ok(())
+    }
+
+    pub fn step(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.root = self.root.next(&self.rule);
+            self.generation += 1;
+        }
+    }
+
+    pub fn cells(&self) -> Vec<Cell> {
+        self.root.cells()
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Cell {
+        self.root.get(x, y, z)
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, cell: Cell) {
+        self.root = self.root.set(x, y, z, cell);
+    }
+
+    // The `side * side` layer of cells at height `z`.
+    pub fn slice(&self, z: usize) -> Vec<Cell> {
+        (0..self.side).flat_map(|y| (0..self.side).map(move |x| (x, y))).map(|(x, y)| self.root.get(x, y, z)).collect()
+    }
+
+    pub fn population(&self) -> usize {
+        self.root.population()
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+}
+
+// A `side` cube that is empty apart from an `extent` cube of random cells in
+// the middle, each alive with probability `density`.
+pub fn soup(seed: u64, side: usize, extent: usize, density: f64) -> Vec<Cell> {
+    let mut random = Random::new(seed);
+    let mut cells = vec![Cell::Dead; side * side * side];
+    let start = (side - extent.min(side)) / 2;
+    for z in start..start + extent.min(side) {
+        for y in start..start + extent.min(side) {
+            for x in start..start + extent.min(side) {
+                if random.chance(density) {
+                    cells[x + y * side + z * side * side] = Cell::Alive;
+                }
+            }
+        }
+    }
+    cells
+}
@@ -0,0 +1,255 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use crate::error::Error;
+use crate::random;
+use crate::rule::Rule;
+use crate::stepper::{Stepper, StepperKind};
+use crate::universe::Cell;
+
+// The longest cycle looked for. A soup has settled once its grid repeats a
+// grid from at most this many generations before.
+pub const MAX_PERIOD: usize = 30;
+
+// A rule is explosive when soups grow to this many times their starting
+// population on average.
+const EXPLOSIVE_GROWTH: f64 = 4.0;
+// A rule is stable when at least this fraction of soups settle, within this
+// fraction of the generations run on average.
+const STABLE_SETTLED: f64 = 0.9;
+const STABLE_TRANSIENT: f64 = 0.1;
+// Beyond this sensitivity an unsettled rule is chaotic rather than complex.
+const CHAOTIC_SENSITIVITY: f64 = 0.5;
+
+#[derive(Clone, Debug)]
+pub struct MetricsOptions {
+    pub seed: u64,
+    pub soups: usize,
+    pub soup_size: usize,
+    pub density: f64,
+    pub rule: Rule,
+    // Each soup runs in a dense grid this wide for this many generations.
+    pub side: usize,
+    pub generations: usize,
+}
+
+impl Default for MetricsOptions {
+    fn default() -> Self {
+        MetricsOptions {
+            seed: 0,
+            soups: 16,
+            soup_size: 16,
+            density: 0.5,
+            rule: Rule::default(),
+            side: 128,
+            generations: 1000,
+        }
+    }
+}
+
+// A guess at how a rule behaves, after Wolfram's classes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Behaviour {
+    // Soups die out or settle quickly into still lifes and oscillators.
+    Stable,
+    // Soups run for a long time before settling, as in Life.
+    Complex,
+    // Soups stay in a disordered state that a single cell changes entirely.
+    Chaotic,
+    // Soups grow without bound.
+    Explosive,
+}
+
+impl Behaviour {
+    // The nearest of Wolfram's four classes. Rules that die out are counted
+    // as class 2, since the soups cannot tell them apart.
+    pub fn class(&self) -> u8 {
+        match self {
+            Behaviour::Stable => 2,
+            Behaviour::Chaotic | Behaviour::Explosive => 3,
+            Behaviour::Complex => 4,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Behaviour::Stable => "stable",
+            Behaviour::Complex => "complex",
+            Behaviour::Chaotic => "chaotic",
+            Behaviour::Explosive => "explosive",
+        }
+    }
+}
+
+impl fmt::Display for Behaviour {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+// The period of a cycle and the generation it was entered at.
+pub type Cycle = (usize, usize);
+
+// How one soup went.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Run {
+    pub initial: usize,
+    pub population: usize,
+    // The cycle the soup settled into, if any.
+    pub settled: Option<Cycle>,
+    // The fraction of cells alive in either run that differ from the run
+    // with the soup's middle cell flipped.
+    pub sensitivity: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    pub options: MetricsOptions,
+    pub runs: Vec<Run>,
+    // The mean fraction of the grid alive at the end.
+    pub density: f64,
+    // The mean ratio of the final population to the soup's.
+    pub growth: f64,
+    // How many soups settled with each period.
+    pub periods: BTreeMap<usize, usize>,
+    // The mean generation settled soups entered their cycle at.
+    pub transient: f64,
+    pub sensitivity: f64,
+    pub behaviour: Behaviour,
+}
+
+// A fingerprint of everything the next generation depends on: FNV-1a over
+// the cells' states, which is much quicker than hashing each `Cell`.
+fn state(stepper: &dyn Stepper) -> u64 {
+    let mut cells = stepper.cells();
+    if stepper.rule().is_second_order() {
+        cells.extend(stepper.previous());
+    }
+    let parity = if stepper.rule().blocks().is_some() { stepper.generation() % 2 } else { 0 };
+    cells.iter().fold(0xCBF2_9CE4_8422_2325 ^ parity as u64, |hash, cell| (hash ^ cell.state() as u64).wrapping_mul(0x0100_0000_01B3))
+}
+
+// Runs `soup` for `generations`, returning the final grid and, if it
+// settled, its period and roughly when it started repeating. Only grids
+// whose population has been seen recently are fingerprinted, so the start
+// can be found up to a period late.
+fn run(soup: &[Cell], options: &MetricsOptions) -> Result<(Vec<Cell>, Option<Cycle>), Error> {
+    let mut stepper = StepperKind::Dense.create(options.side, options.rule)?;
+    stepper.load(soup)?;
+    let mut recent: VecDeque<(usize, Option<u64>)> = VecDeque::from([(stepper.population(), None)]);
+    for generation in 1..=options.generations {
+        stepper.step(1);
+        let population = stepper.population();
+        let mut fingerprint = None;
+        // stochastic rules can repeat a grid without repeating what follows
+        if !options.rule.is_stochastic() {
+            for (back, (earlier, earlier_fingerprint)) in recent.iter().rev().enumerate() {
+                if *earlier != population {
+                    continue;
+                }
+                let current = *fingerprint.get_or_insert_with(|| state(stepper.as_ref()));
+                if *earlier_fingerprint == Some(current) {
+                    let period = back + 1;
+                    return Ok((stepper.cells(), Some((period, generation - period))));
+                }
+            }
+        }
+        recent.push_back((population, fingerprint));
+        if recent.len() > MAX_PERIOD {
+            recent.pop_front();
+        }
+    }
+    Ok((stepper.cells(), None))
+}
+
+fn alive(cell: &Cell) -> bool {
+    *cell != Cell::Dead
+}
+
+// Runs soup `index` and its copy with the middle cell flipped.
+pub fn measure_soup(options: &MetricsOptions, index: usize) -> Result<Run, Error> {
+    let seed = random::hash(options.seed, &[index as u64]);
+    let mut soup = random::soup(seed, options.soup_size, options.soup_size, options.density);
+    let initial = soup.cells.iter().filter(|cell| alive(cell)).count();
+    let (cells, settled) = run(&soup.centred(options.side), options)?;
+
+    let middle = soup.width / 2 + soup.height / 2 * soup.width;
+    soup.cells[middle] = if alive(&soup.cells[middle]) { Cell::Dead } else { Cell::Alive };
+    let (perturbed, _) = run(&soup.centred(options.side), options)?;
+    let either = cells.iter().zip(&perturbed).filter(|(a, b)| alive(a) || alive(b)).count();
+    let differ = cells.iter().zip(&perturbed).filter(|(a, b)| a != b).count();
+    let sensitivity = if either == 0 { 0.0 } else { differ as f64 / either as f64 };
+
+    let population = cells.iter().filter(|cell| alive(cell)).count();
+    Ok(Run { initial, population, settled, sensitivity })
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+impl Metrics {
+    // Runs `options.soups` soups from `options.seed` under `options.rule`.
+    pub fn measure(options: MetricsOptions) -> Result<Metrics, Error> {
+        if options.soups == 0 || options.soup_size == 0 || options.soup_size > options.side {
+            return Err(Error::InvalidDimensions(format!(
+                "cannot run {} soups of side {} in a grid of side {}",
+                options.soups, options.soup_size, options.side
+            )));
+        }
+        let runs = (0..options.soups).map(|index| measure_soup(&options, index)).collect::<Result<Vec<_>, _>>()?;
+
+        let area = (options.side * options.side) as f64;
+        let density = mean(runs.iter().map(|run| run.population as f64 / area));
+        let growth = mean(runs.iter().map(|run| run.population as f64 / run.initial.max(1) as f64));
+        let mut periods = BTreeMap::new();
+        for (period, _) in runs.iter().filter_map(|run| run.settled) {
+            *periods.entry(period).or_insert(0) += 1;
+        }
+        let transient = mean(runs.iter().filter_map(|run| run.settled).map(|(_, at)| at as f64));
+        let sensitivity = mean(runs.iter().map(|run| run.sensitivity));
+
+        let settled = periods.values().sum::<usize>() as f64 / runs.len() as f64;
+        let behaviour = if growth >= EXPLOSIVE_GROWTH {
+            Behaviour::Explosive
+        } else if settled >= STABLE_SETTLED && transient <= STABLE_TRANSIENT * options.generations as f64 {
+            Behaviour::Stable
+        } else if settled < 1.0 - STABLE_SETTLED && sensitivity > CHAOTIC_SENSITIVITY {
+            Behaviour::Chaotic
+        } else {
+            Behaviour::Complex
+        };
+        Ok(Metrics { options, runs, density, growth, periods, transient, sensitivity, behaviour })
+    }
+
+    // How many soups settled.
+    pub fn settled(&self) -> usize {
+        self.periods.values().sum()
+    }
+}
+
+// One line per rule, so that the metrics of many rules can be sorted and
+// compared.
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\tdensity {:.3}\tgrowth {:.2}\tsensitivity {:.2}\tsettled {}/{} by {:.0}\tperiods",
+            self.options.rule,
+            self.behaviour,
+            self.density,
+            self.growth,
+            self.sensitivity,
+            self.settled(),
+            self.runs.len(),
+            self.transient
+        )?;
+        if self.periods.is_empty() {
+            write!(f, " none")?;
+        }
+        for (period, count) in &self.periods {
+            write!(f, " p{}:{}", period, count)?;
+        }
+        Ok(())
+    }
+}
@@ -1,460 +1,511 @@
-use std::{collections::HashMap, sync::Mutex};
-use gloo_console::log;
-use derivative::Derivative;
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use crate::error::Error;
+use crate::reference;
+use crate::rule::Rule;
 use crate::universe::Cell;
 
-#[derive(Eq, Derivative, Clone)]
-#[derivative(PartialEq, Hash)]
-pub enum NodePointer {
-    Node(Box<Node>),
-    Cell(Box<Cell>),
+// A square of cells with a power-of-two side. Squares are hash-consed: each is
+// made only once and shared by every larger square that contains it, so two
+// nodes with the same cells are the same node, and nodes compare and hash by
+// id alone.
+#[derive(Clone)]
+pub struct Node(Arc<Square>);
+
+// id: unique among the squares ever made
+// level: the side is 2 to the power of the level
+// population: live cells
+// blank: every cell is dead, which is not the same as no live cells under
+// rules with dying states
+struct Square {
+    id: u64,
+    level: u32,
+    population: usize,
+    blank: bool,
+    content: Content,
 }
 
-// nw: northwest node
-// ne: northeast node
-// sw: southwest node
-// se: southeast node
-// nn: north auxiliary node
-// ee: east auxiliary node
-// ss: south auxiliary node
-// ww: west auxiliary node
-// cc: central auxiliary node
-#[derive(Eq, Derivative, Clone)]
-#[derivative(PartialEq, Hash)]
-pub struct Node {
-    nw: NodePointer,
-    ne: NodePointer,
-    sw: NodePointer,
-    se: NodePointer,
-    nn: NodePointer,
-    ee: NodePointer,
-    ss: NodePointer,
-    ww: NodePointer,
-    cc: NodePointer,
-    depth: usize,
-    area: usize,
+enum Content {
+    Cell(Cell),
+    // nw, ne, sw, se
+    Quarters([Node; 4]),
 }
 
-lazy_static! {
-    static ref HASHLIFE: Mutex<HashMap<Node, Vec<Cell>>> = {
-        let m = HashMap::new();
-        Mutex::new(m)
-    };
+const NW: usize = 0;
+const NE: usize = 1;
+const SW: usize = 2;
+const SE: usize = 3;
+
+// What a square is looked up by when it is made: its cell, or the ids of its
+// quarters.
+#[derive(PartialEq, Eq, Hash)]
+enum Key {
+    Cell(Cell),
+    Quarters([u64; 4]),
+}
+
+// Every square in use, so that making one that already exists gives it back.
+// Squares nothing else holds any more are dropped once the table has grown to
+// twice the size it had after the last time that was done.
+#[derive(Default)]
+struct Squares {
+    table: HashMap<Key, Node>,
+    kept: usize,
+}
+
+// The table is not pruned below this many squares.
+const MIN_SQUARES: usize = 1 << 16;
+
+impl Squares {
+    fn prune(&mut self) {
+        // dropping a square can leave its quarters unheld in turn
+        loop {
+            let before = self.table.len();
+            self.table.retain(|_, node| Arc::strong_count(&node.0) > 1);
+            if self.table.len() == before {
+                break;
+            }
+        }
+        self.kept = self.table.len();
+    }
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+// hits: lookups answered from the memo table
+// misses: lookups that had to be computed
+// entries: results currently stored in the memo table
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub entries: usize,
+}
+
+// Each generation of the memo holds keys and results taking up to about this
+// many bytes, so the memo as a whole holds up to twice as many, besides the
+// hash maps' own overhead. Nodes are shared, so the squares the 2D memo
+// refers to take up no more than this, and often far less.
+const MAX_MEMO_BYTES: usize = 16 << 20;
+
+// Something a memo keeps, which knows roughly how much it takes up.
+pub(crate) trait Footprint {
+    fn bytes(&self) -> usize;
+}
+
+impl Footprint for Vec<Cell> {
+    fn bytes(&self) -> usize {
+        self.len() * mem::size_of::<Cell>()
+    }
+}
+
+impl Footprint for Node {
+    fn bytes(&self) -> usize {
+        mem::size_of::<Square>()
+    }
+}
+
+// Results are kept separately for each rule, since the same node evolves
+// differently under each. New results go into the recent generation; once it
+// is full it becomes the older one and the previous older one is dropped. A
+// result found in the older generation moves back to the recent one, so the
+// results still in use outlive those that are not.
+pub(crate) struct Memo<R, K, V> {
+    recent: HashMap<R, HashMap<K, V>>,
+    older: HashMap<R, HashMap<K, V>>,
+    // bytes of keys and results in the recent generation
+    bytes: usize,
+    hits: usize,
+    misses: usize,
 }
 
-impl Default for Node {
+impl<R, K, V> Default for Memo<R, K, V> {
     fn default() -> Self {
-        Node {
-            nw: NodePointer::Cell(Box::new(Cell::Dead)),
-            ne: NodePointer::Cell(Box::new(Cell::Dead)),
-            sw: NodePointer::Cell(Box::new(Cell::Dead)),
-            se: NodePointer::Cell(Box::new(Cell::Dead)),
-            nn: NodePointer::Cell(Box::new(Cell::Dead)),
-            ee: NodePointer::Cell(Box::new(Cell::Dead)),
-            ss: NodePointer::Cell(Box::new(Cell::Dead)),
-            ww: NodePointer::Cell(Box::new(Cell::Dead)),
-            cc: NodePointer::Cell(Box::new(Cell::Dead)),
-            depth: 0,
-            area: 0,
+        Memo { recent: HashMap::new(), older: HashMap::new(), bytes: 0, hits: 0, misses: 0 }
+    }
+}
+
+impl<R: Copy + Eq + Hash, K: Footprint + Eq + Hash, V: Footprint + Clone> Memo<R, K, V> {
+    pub(crate) fn get(&mut self, rule: &R, key: K) -> Result<V, K> {
+        if let Some(result) = self.recent.get(rule).and_then(|results| results.get(&key)) {
+            self.hits += 1;
+            return Ok(result.clone());
+        }
+        if let Some(result) = self.older.get_mut(rule).and_then(|results| results.remove(&key)) {
+            self.hits += 1;
+            self.insert(rule, key, result.clone());
+            return Ok(result);
         }
+        self.misses += 1;
+        Err(key)
+    }
+
+    pub(crate) fn insert(&mut self, rule: &R, key: K, result: V) {
+        let bytes = key.bytes() + result.bytes();
+        if self.bytes + bytes > MAX_MEMO_BYTES {
+            self.older = mem::take(&mut self.recent);
+            self.bytes = 0;
+        }
+        self.bytes += bytes;
+        self.recent.entry(*rule).or_default().insert(key, result);
+    }
+
+    fn entries(&self) -> usize {
+        self.recent.values().chain(self.older.values()).map(|results| results.len()).sum()
     }
 }
 
-impl Node {
-    pub fn new(cells: Vec<Cell>) -> Self {
-        let length = cells.len();
-        if (length as f32).sqrt() as usize % 1 != 0 {
-            panic!("Not a power of two");
-        } 
-
-        let depth = (length as f32).sqrt() as usize;
-        if depth == 2 {
-            Node {
-                nw: NodePointer::Cell(Box::new(cells[0])),
-                ne: NodePointer::Cell(Box::new(cells[1])),
-                sw: NodePointer::Cell(Box::new(cells[2])),
-                se: NodePointer::Cell(Box::new(cells[3])),
-                nn: NodePointer::Cell(Box::new(Cell::Dead)),
-                ee: NodePointer::Cell(Box::new(Cell::Dead)),
-                ss: NodePointer::Cell(Box::new(Cell::Dead)),
-                ww: NodePointer::Cell(Box::new(Cell::Dead)),
-                cc: NodePointer::Cell(Box::new(Cell::Dead)),
-                depth: 2,
-                area: 4,
-            }
-        } else {
-            let mut nw_vec: Vec<Cell> = Vec::new();
-            for i in 0..(depth / 2) {
-                for j in 0..(depth / 2) {
-                    nw_vec.push(cells[j + i * depth]);
-                }
-            }
+// What a memoised result is: the centre half of a node `2^jump` generations
+// on, or under a block rule the whole node a generation on.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Step {
+    Successor(u32),
+    Blocks,
+}
 
-            let mut ne_vec: Vec<Cell> = Vec::new();
-            for i in 0..(depth / 2) {
-                for j in (depth / 2)..depth {
-                    ne_vec.push(cells[j + i * depth]);
-                }
-            }
+lazy_static! {
+    static ref SQUARES: Mutex<Squares> = Mutex::new(Squares::default());
+    // Results are looked up by the rule and the step they are the result of.
+    static ref HASHLIFE: Mutex<Memo<(Rule, Step), Node, Node>> = Mutex::new(Memo::default());
+}
 
-            let mut sw_vec: Vec<Cell> = Vec::new();
-            for i in (depth / 2)..depth {
-                for j in 0..(depth / 2) {
-                    sw_vec.push(cells[j + i * depth]);
-                }
-            }
+pub fn cache_stats() -> CacheStats {
+    let memo = HASHLIFE.lock().unwrap();
+    CacheStats { hits: memo.hits, misses: memo.misses, entries: memo.entries() }
+}
 
-            let mut se_vec: Vec<Cell> = Vec::new();
-            for i in (depth / 2)..depth {
-                for j in (depth / 2)..depth {
-                    se_vec.push(cells[j + i * depth]);
-                }
-            }
+// Forgets every memoized result and resets the hit and miss counters, along
+// with the squares that only the results held.
+pub fn clear_cache() {
+    *HASHLIFE.lock().unwrap() = Memo::default();
+    SQUARES.lock().unwrap().prune();
+}
 
-            let mut nn_vec: Vec<Cell> = Vec::new();
-            for i in 0..(depth / 2) {
-                for j in (depth / 4)..(3 * depth / 4) {
-                    nn_vec.push(cells[j + i * depth]);
-                }
-            }
+// The level of the nodes that `successor` works out directly rather than from
+// smaller nodes. Putting a node's result together from the nine overlapping
+// results below it only works if the rule's radius is at most a quarter of
+// the side of the nodes it is put together from, so rules with a larger radius
+// need larger leaves.
+fn leaf_level(rule: &Rule) -> u32 {
+    (4 * rule.radius()).next_power_of_two().trailing_zeros()
+}
 
-            let mut ee_vec: Vec<Cell> = Vec::new();
-            for i in (depth / 4)..(3 * depth / 4) {
-                for j in (depth / 2)..depth {
-                    ee_vec.push(cells[j + i * depth]);
-                }
-            }
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id == other.0.id
+    }
+}
 
-            let mut ss_vec: Vec<Cell> = Vec::new();
-            for i in (depth / 2)..depth {
-                for j in (depth / 4)..(3 * depth / 4) {
-                    ss_vec.push(cells[j + i * depth]);
-                }
-            }
+impl Eq for Node {}
 
-            let mut ww_vec: Vec<Cell> = Vec::new();
-            for i in (depth / 4)..(3 * depth / 4) {
-                for j in 0..(depth / 2) {
-                    ww_vec.push(cells[j + i * depth]);
-                }
-            }
+impl Hash for Node {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.id.hash(state);
+    }
+}
 
-            let mut cc_vec: Vec<Cell> = Vec::new();
-            for i in (depth / 4)..(3 * depth / 4) {
-                for j in (depth / 4)..(3 * depth / 4) {
-                    cc_vec.push(cells[j + i * depth]);
-                }
-            }
+impl Node {
+    fn make(key: Key, level: u32, population: usize, blank: bool, content: Content) -> Node {
+        let mut squares = SQUARES.lock().unwrap();
+        if let Some(node) = squares.table.get(&key) {
+            return node.clone();
+        }
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let node = Node(Arc::new(Square { id, level, population, blank, content }));
+        squares.table.insert(key, node.clone());
+        if squares.table.len() >= (2 * squares.kept).max(MIN_SQUARES) {
+            squares.prune();
+        }
+        node
+    }
 
-            // let test_string = format!("{:?}", cells);
-            // 
-            // log!(test_string);
-
-            Node {
-                nw: NodePointer::Node(Box::new(Node::new(nw_vec))),
-                ne: NodePointer::Node(Box::new(Node::new(ne_vec))),
-                sw: NodePointer::Node(Box::new(Node::new(sw_vec))),
-                se: NodePointer::Node(Box::new(Node::new(se_vec))),
-                nn: NodePointer::Node(Box::new(Node::new(nn_vec))),                 
-                ee: NodePointer::Node(Box::new(Node::new(ee_vec))),
-                ss: NodePointer::Node(Box::new(Node::new(ss_vec))),
-                ww: NodePointer::Node(Box::new(Node::new(ww_vec))),
-                cc: NodePointer::Node(Box::new(Node::new(cc_vec))),
-                depth,
-                area: depth * depth,
-            }
+    fn cell(cell: Cell) -> Node {
+        Node::make(Key::Cell(cell), 0, (cell == Cell::Alive) as usize, cell == Cell::Dead, Content::Cell(cell))
+    }
+
+    fn join(nw: Node, ne: Node, sw: Node, se: Node) -> Node {
+        let quarters = [nw, ne, sw, se];
+        let key = Key::Quarters(quarters.each_ref().map(|quarter| quarter.0.id));
+        let population = quarters.iter().map(|quarter| quarter.0.population).sum();
+        let blank = quarters.iter().all(|quarter| quarter.0.blank);
+        Node::make(key, quarters[NW].0.level + 1, population, blank, Content::Quarters(quarters))
+    }
+
+    // The square at `level` with every cell dead.
+    fn empty(level: u32) -> Node {
+        if level == 0 {
+            return Node::cell(Cell::Dead);
         }
+        let quarter = Node::empty(level - 1);
+        Node::join(quarter.clone(), quarter.clone(), quarter.clone(), quarter)
     }
 
-    pub fn evolve(&self) -> Vec<Cell> {
-        {
-            let mut map = HASHLIFE.lock().unwrap();
-            let result = map.get(self).cloned().unwrap_or(vec![]);
-            if result.len() != 0 { return result; }
+    // The node of a square of cells stored row by row.
+    pub fn new(cells: Vec<Cell>) -> Result<Self, Error> {
+        let length = cells.len();
+        let side = (length as f32).sqrt() as usize;
+        if side * side != length || !side.is_power_of_two() || side < 2 {
+            return Err(Error::InvalidDimensions(format!(
+                "{} cells do not form a square with a power-of-two side",
+                length
+            )));
         }
-        if self.depth == 4 {
-            let mut cell_values: Vec<Cell> = vec![Cell::Dead; 16];
-            let nodes: Vec<&NodePointer> = vec![&self.nw, &self.ne, &self.se, &self.sw];
-            let mut index = 0;
-            for j in nodes {
-                let mut i: &Box<Node> = &Box::new(Node::default());
-                if let NodePointer::Node(k) = j { i = k; }
-                let start = match index {
-                    0 => 0,
-                    1 => 2,
-                    2 => 10,
-                    3 => 8,
-                    _ => 0,
-                };
-                let mut nw_value = &Box::new(Cell::Dead);
-                let mut ne_value = &Box::new(Cell::Dead);
-                let mut se_value = &Box::new(Cell::Dead);
-                let mut sw_value = &Box::new(Cell::Dead);
-                if let NodePointer::Cell(node) = &i.nw { nw_value = node; } 
-                if let NodePointer::Cell(node) = &i.ne { ne_value = node; }
-                if let NodePointer::Cell(node) = &i.se { se_value = node; }
-                if let NodePointer::Cell(node) = &i.sw { sw_value = node; }
-                let test_string = format!("{:?}, {:?}, {:?}, {:?}, index: {}", nw_value, ne_value, se_value, sw_value, index);
-                log!(test_string);
-                cell_values[start] = *nw_value.clone();
-                cell_values[start + 1] = *ne_value.clone();
-                cell_values[start + 4] = *sw_value.clone();
-                cell_values[start + 5] = *se_value.clone();
-                index += 1;
-            }
-            log!("break");
-            let test = format!("{:?}", cell_values);
-            log!(test);
-
-            let nw_neighbours = [0, 1, 2, 4, 6, 8, 9, 10];
-            let mut nw_count = 0;
-            for i in nw_neighbours {
-                if cell_values[i] == Cell::Alive { nw_count += 1; }
-            }
+        Ok(Node::build(&cells, side, 0, 0, side))
+    }
 
-            let ne_neighbours = [1, 2, 3, 5, 7, 9, 10, 11];
-            let mut ne_count = 0;
-            for i in ne_neighbours {
-                if cell_values[i] == Cell::Alive { ne_count += 1; }
-            }
+    // The `size` square at (x, y) of a grid `side` across.
+    fn build(cells: &[Cell], side: usize, x: usize, y: usize, size: usize) -> Node {
+        if size == 1 {
+            return Node::cell(cells[x + y * side]);
+        }
+        let half = size / 2;
+        Node::join(
+            Node::build(cells, side, x, y, half),
+            Node::build(cells, side, x + half, y, half),
+            Node::build(cells, side, x, y + half, half),
+            Node::build(cells, side, x + half, y + half, half),
+        )
+    }
 
-            let se_neighbours = [5, 6, 7, 9, 11, 13, 14, 15];
-            let mut se_count = 0;
-            for i in se_neighbours {
-                if cell_values[i] == Cell::Alive { se_count += 1; }
-            }
+    fn quarters(&self) -> &[Node; 4] {
+        match &self.0.content {
+            Content::Quarters(quarters) => quarters,
+            Content::Cell(_) => unreachable!("only nodes above side 1 have quarters"),
+        }
+    }
 
-            let sw_neighbours = [4, 5, 6, 8, 10, 12, 13, 14];
-            let mut sw_count = 0;
-            for i in sw_neighbours {
-                if cell_values[i] == Cell::Alive { sw_count += 1; }
-            }
+    pub fn side(&self) -> usize {
+        1 << self.0.level
+    }
 
-            let mut temp_cells: Vec<Cell> = Vec::new();
-            let mut check_cells: Vec<Cell> = Vec::new();
-            for i in cell_values {
-                temp_cells.push(i);
-                check_cells.push(i);
-            }
+    pub fn population(&self) -> usize {
+        self.0.population
+    }
 
-            let test_nodes = [[5, nw_count], [6, ne_count], [10, se_count], [9, sw_count]];
-            for i in test_nodes {
-                if check_cells[i[0]] == Cell::Dead && i[1] == 3 {
-                    temp_cells[i[0]] = Cell::Alive;
-                } else if check_cells[i[0]] == Cell::Alive && (i[1] == 2 || i[1] == 3) {
-                    temp_cells[i[0]] = Cell::Alive;
-                } else {
-                    temp_cells[i[0]] = Cell::Dead;
-                }
-            }
-            {
-                let mut map = HASHLIFE.lock().unwrap();
-                map.insert(self.clone(), temp_cells.clone());
-            }
-            return temp_cells;
-        } else {
-            let length = (self.area as f32).sqrt() as usize;
-            let mut nw_vec: Vec<Cell> = Vec::new();
-            let mut ne_vec: Vec<Cell> = Vec::new();
-            let mut se_vec: Vec<Cell> = Vec::new();
-            let mut sw_vec: Vec<Cell> = Vec::new();
-            let mut nn_vec: Vec<Cell> = Vec::new();
-            let mut ee_vec: Vec<Cell> = Vec::new();
-            let mut ss_vec: Vec<Cell> = Vec::new();
-            let mut ww_vec: Vec<Cell> = Vec::new();
-            let mut cc_vec: Vec<Cell> = Vec::new();
-            if let NodePointer::Node(node) = &self.nw { nw_vec = node.evolve(); }
-            if let NodePointer::Node(node) = &self.ne { ne_vec = node.evolve(); }
-            if let NodePointer::Node(node) = &self.se { se_vec = node.evolve(); }
-            if let NodePointer::Node(node) = &self.sw { sw_vec = node.evolve(); }
-            if let NodePointer::Node(node) = &self.nn { nn_vec = node.evolve(); }
-            if let NodePointer::Node(node) = &self.ee { ee_vec = node.evolve(); }
-            if let NodePointer::Node(node) = &self.ss { ss_vec = node.evolve(); }
-            if let NodePointer::Node(node) = &self.ww { ww_vec = node.evolve(); }
-            if let NodePointer::Node(node) = &self.cc { cc_vec = node.evolve(); }
-
-            /*
-            let mut inter1_vec: Vec<Cell> = vec![Cell::Dead; self.area];
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter1_vec[j - length / 8 + (i - length / 8) * length / 2] = nw_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter1_vec[j + length / 8 + (i - length / 8) * length / 2] = nn_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter1_vec[j + length / 8 + (i + length / 8) * length / 2] = cc_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter1_vec[j - length / 8 + (i + length / 8) * length / 2] = ee_vec[j + i * length / 2];
-                }
-            }
-            let mut inter2_vec: Vec<Cell> = vec![Cell::Dead; self.area];
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter2_vec[j - length / 8 + (i - length / 8) * length / 2] = nn_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter2_vec[j + length / 8 + (i - length / 8) * length / 2] = ne_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter2_vec[j + length / 8 + (i + length / 8) * length / 2] = ee_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter2_vec[j - length / 8 + (i + length / 8) * length / 2] = cc_vec[j + i * length / 2];
-                }
-            }
-            let mut inter3_vec: Vec<Cell> = vec![Cell::Dead; self.area];
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter3_vec[j - length / 8 + (i - length / 8) * length / 2] = cc_vec[j + i * length / 2];
-                }
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        match &self.0.content {
+            Content::Cell(cell) => *cell,
+            Content::Quarters(quarters) => {
+                let half = self.side() / 2;
+                quarters[x / half + 2 * (y / half)].get(x % half, y % half)
             }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter3_vec[j + length / 8 + (i - length / 8) * length / 2] = ee_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter3_vec[j + length / 8 + (i + length / 8) * length / 2] = se_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter3_vec[j - length / 8 + (i + length / 8) * length / 2] = ss_vec[j + i * length / 2];
-                }
-            }
-            let mut inter4_vec: Vec<Cell> = vec![Cell::Dead; self.area];
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter4_vec[j - length / 8 + (i - length / 8) * length / 2] = ww_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter4_vec[j + length / 8 + (i - length / 8) * length / 2] = cc_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter4_vec[j + length / 8 + (i + length / 8) * length / 2] = ss_vec[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    inter4_vec[j - length / 8 + (i + length / 8) * length / 2] = sw_vec[j + i * length / 2];
-                }
-            }
-            
-
-            let inter1_node = Node::new(inter1_vec);
-            let inter2_node = Node::new(inter2_vec);
-            let inter3_node = Node::new(inter3_vec);
-            let inter4_node = Node::new(inter4_vec);
-            let inter1_res = inter1_node.evolve().clone();
-            let inter2_res = inter2_node.evolve().clone();
-            let inter3_res = inter3_node.evolve().clone();
-            let inter4_res = inter4_node.evolve().clone();
-
-            let mut res_values: Vec<Cell> = vec![Cell::Dead; self.area];
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    res_values[j - length / 8 + (i - length / 8) * length / 2] = inter1_res[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    res_values[j + length / 8 + (i - length / 8) * length / 2] = inter2_res[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    res_values[j + length / 8 + (i + length / 8) * length / 2] = inter3_res[j + i * length / 2];
-                }
-            }
-            for i in (length / 8)..(3 * length / 8) {
-                for j in (length / 8)..(3 * length / 8) {
-                    res_values[j - length / 8 + (i + length / 8) * length / 2] = inter4_res[j + i * length / 2];
-                }
+        }
+    }
+
+    // The node with the cell at (x, y) replaced, sharing every square that
+    // does not contain it.
+    pub fn set(&self, x: usize, y: usize, cell: Cell) -> Node {
+        match &self.0.content {
+            Content::Cell(_) => Node::cell(cell),
+            Content::Quarters(quarters) => {
+                let half = self.side() / 2;
+                let mut quarters = quarters.clone();
+                let i = x / half + 2 * (y / half);
+                quarters[i] = quarters[i].set(x % half, y % half, cell);
+                let [nw, ne, sw, se] = quarters;
+                Node::join(nw, ne, sw, se)
             }
-            */
+        }
+    }
 
+    // The node's cells, row by row.
+    pub fn cells(&self) -> Vec<Cell> {
+        let side = self.side();
+        let mut cells = vec![Cell::Dead; side * side];
+        self.write(&mut cells, side, 0, 0);
+        cells
+    }
 
-            let mut res_values: Vec<Cell> = vec![Cell::Dead; self.area];
-            for i in 0..(3 * length / 8) {
-                for j in 0..(3 * length / 8) {
-                    res_values[i + j * length] = nw_vec[i + j * length / 2];
+    fn write(&self, cells: &mut [Cell], side: usize, x: usize, y: usize) {
+        if self.0.blank {
+            return;
+        }
+        match &self.0.content {
+            Content::Cell(cell) => cells[x + y * side] = *cell,
+            Content::Quarters(quarters) => {
+                let half = self.side() / 2;
+                for (i, quarter) in quarters.iter().enumerate() {
+                    quarter.write(cells, side, x + (i % 2) * half, y + (i / 2) * half);
                 }
             }
+        }
+    }
 
-            for i in (3 * length / 8)..(5 * length / 8) {
-                for j in 0..(3 * length / 8) {
-                    res_values[i + j * length] = nn_vec[i - 2 * length / 8 + j * length / 2];
-                }
-            }
+    // Whether every cell in columns `left..right` and rows `top..bottom` is
+    // dead, looking only into the squares that overlap them.
+    pub fn is_blank_within(&self, left: usize, top: usize, right: usize, bottom: usize) -> bool {
+        let side = self.side();
+        if self.0.blank || left >= right.min(side) || top >= bottom.min(side) {
+            return true;
+        }
+        if left == 0 && top == 0 && right >= side && bottom >= side {
+            return false;
+        }
+        let half = side / 2;
+        self.quarters().iter().enumerate().all(|(i, quarter)| {
+            let (x, y) = ((i % 2) * half, (i / 2) * half);
+            quarter.is_blank_within(
+                left.saturating_sub(x),
+                top.saturating_sub(y),
+                right.saturating_sub(x),
+                bottom.saturating_sub(y),
+            )
+        })
+    }
 
-            for i in (5 * length / 8)..length {
-                for j in 0..(3 * length / 8) {
-                    res_values[i + j * length] = ne_vec[i - 4 * length / 8 + j * length / 2];
-                }
-            }
+    // The square of half the side in the middle of this one.
+    fn centre(&self) -> Node {
+        let [nw, ne, sw, se] = self.quarters();
+        Node::join(nw.quarters()[SE].clone(), ne.quarters()[SW].clone(), sw.quarters()[NE].clone(), se.quarters()[NW].clone())
+    }
 
-            for i in (5 * length / 8)..length {
-                for j in (3 * length / 8)..(5 * length / 8) {
-                    res_values[i + j * length] = ee_vec[i - 4 * length / 8 + (j - 2 * length / 8) * length / 2];
-                }
-            }
+    // The half-side square straddling `west` and `east`.
+    fn horizontal(west: &Node, east: &Node) -> Node {
+        let (west, east) = (west.quarters(), east.quarters());
+        Node::join(west[NE].clone(), east[NW].clone(), west[SE].clone(), east[SW].clone())
+    }
 
-            for i in (5 * length / 8)..length {
-                for j in (5 * length / 8)..length {
-                    res_values[i + j * length] = se_vec[i - 4 * length / 8 + (j - 4 * length / 8) * length / 2];
-                }
-            }
+    // The half-side square straddling `north` and `south`.
+    fn vertical(north: &Node, south: &Node) -> Node {
+        let (north, south) = (north.quarters(), south.quarters());
+        Node::join(north[SW].clone(), north[SE].clone(), south[NW].clone(), south[NE].clone())
+    }
 
-            for i in (3 * length / 8)..(5 * length / 8) {
-                for j in (5 * length / 8)..length {
-                    res_values[i + j * length] = ss_vec[i - 2 * length / 8 + (j - 4 * length / 8) * length / 2];
-                }
-            }
+    // The node in the middle of one twice the side, with dead cells around it.
+    fn pad(&self) -> Node {
+        let empty = Node::empty(self.0.level - 1);
+        let [nw, ne, sw, se] = self.quarters().clone();
+        Node::join(
+            Node::join(empty.clone(), empty.clone(), empty.clone(), nw),
+            Node::join(empty.clone(), empty.clone(), ne, empty.clone()),
+            Node::join(empty.clone(), sw, empty.clone(), empty.clone()),
+            Node::join(se, empty.clone(), empty.clone(), empty),
+        )
+    }
 
-            for i in 0..(3 * length / 8) {
-                for j in (5 * length / 8)..length {
-                    res_values[i + j * length] = sw_vec[i + (j - 4 * length / 8) * length / 2];
-                }
-            }
+    // The most generations, as a power of two, that `next` can go on at once.
+    pub fn max_jump(&self, rule: &Rule) -> u32 {
+        (self.0.level + 1).saturating_sub(leaf_level(rule))
+    }
 
-            for i in 0..(3 * length / 8) {
-                for j in (3 * length / 8)..(5 * length / 8) {
-                    res_values[i + j * length] = ww_vec[i + (j - 2 * length / 8) * length / 2];
-                }
+    // The node `2^jump` generations on, with the cells around it dead, for a
+    // jump of at most `max_jump`. Cells are only right if nothing reaches the
+    // edge of the node in that time, or if the jump is 0: after a single
+    // generation anything outside it is simply left out.
+    pub fn next(&self, rule: &Rule, jump: u32) -> Node {
+        self.pad().successor(rule, jump)
+    }
+
+    // The node's cells a generation on, with the cells around it dead.
+    pub fn evolve(&self, rule: &Rule) -> Vec<Cell> {
+        self.next(rule, 0).cells()
+    }
+
+    // The centre half of the node, `2^jump` generations on, worked out only
+    // from the cells in the node. Nodes up to the leaf level go on a
+    // generation, directly; larger ones are put together from nine overlapping
+    // half-side nodes, either from their results alone or, for the longest
+    // jump a node allows, from those of four nodes put together from them in
+    // turn, which go on as far again.
+    fn successor(&self, rule: &Rule, jump: u32) -> Node {
+        let leaf = leaf_level(rule);
+        let jump = jump.min(self.0.level.saturating_sub(leaf));
+        let key = match HASHLIFE.lock().unwrap().get(&(*rule, Step::Successor(jump)), self.clone()) {
+            Ok(result) => return result,
+            Err(key) => key,
+        };
+        let result = if self.0.level <= leaf {
+            self.evolve_leaf(rule)
+        } else {
+            let [nw, ne, sw, se] = self.quarters();
+            let nine = [
+                nw.clone(),
+                Node::horizontal(nw, ne),
+                ne.clone(),
+                Node::vertical(nw, sw),
+                self.centre(),
+                Node::vertical(ne, se),
+                sw.clone(),
+                Node::horizontal(sw, se),
+                se.clone(),
+            ];
+            if jump < self.0.level - leaf {
+                let c = nine.map(|node| node.successor(rule, jump));
+                let part = |i: usize, quarter: usize| c[i].quarters()[quarter].clone();
+                Node::join(
+                    Node::join(part(0, SE), part(1, SW), part(3, NE), part(4, NW)),
+                    Node::join(part(1, SE), part(2, SW), part(4, NE), part(5, NW)),
+                    Node::join(part(3, SE), part(4, SW), part(6, NE), part(7, NW)),
+                    Node::join(part(4, SE), part(5, SW), part(7, NE), part(8, NW)),
+                )
+            } else {
+                let c = nine.map(|node| node.successor(rule, jump - 1));
+                let round = |a: usize, b: usize, d: usize, e: usize| {
+                    Node::join(c[a].clone(), c[b].clone(), c[d].clone(), c[e].clone()).successor(rule, jump - 1)
+                };
+                Node::join(round(0, 1, 3, 4), round(1, 2, 4, 5), round(3, 4, 6, 7), round(4, 5, 7, 8))
             }
+        };
+        HASHLIFE.lock().unwrap().insert(&(*rule, Step::Successor(jump)), key, result.clone());
+        result
+    }
 
-            for i in (3 * length / 8)..(5 * length / 8) {
-                for j in (3 * length / 8)..(5 * length / 8) {
-                    res_values[i + j * length] = cc_vec[i - 2 * length / 8 + (j - 2 * length / 8) * length / 2];
-                }
+    // The centre half of a leaf a generation on, cell by cell.
+    fn evolve_leaf(&self, rule: &Rule) -> Node {
+        let side = self.side();
+        let (half, quarter) = (side / 2, side / 4);
+        let cells = self.cells();
+        let mut next = Vec::with_capacity(half * half);
+        for y in quarter..quarter + half {
+            for x in quarter..quarter + half {
+                next.push(reference::next_cell(&cells, side, x, y, rule));
             }
+        }
+        Node::build(&next, half, 0, 0, half)
+    }
 
+    // The node a generation on under a block rule whose blocks line up with
+    // the node's corner, put together from its quarters.
+    pub fn apply_blocks(&self, rule: &Rule) -> Node {
+        let blocks = rule.blocks().expect("only block rules work block by block");
+        let quarters = self.quarters();
+        if self.0.level == 1 {
+            let alive = |quarter: &Node| quarter.get(0, 0) == Cell::Alive;
+            let block = quarters.iter().enumerate().filter(|(_, quarter)| alive(quarter)).fold(0, |block, (bit, _)| block | 1 << bit);
+            let cell = |bit: usize| Node::cell(if blocks[block] >> bit & 1 == 1 { Cell::Alive } else { Cell::Dead });
+            return Node::join(cell(0), cell(1), cell(2), cell(3));
+        }
+        let key = match HASHLIFE.lock().unwrap().get(&(*rule, Step::Blocks), self.clone()) {
+            Ok(result) => return result,
+            Err(key) => key,
+        };
+        let [nw, ne, sw, se] = quarters.each_ref().map(|quarter| quarter.apply_blocks(rule));
+        let result = Node::join(nw, ne, sw, se);
+        HASHLIFE.lock().unwrap().insert(&(*rule, Step::Blocks), key, result.clone());
+        result
+    }
 
-            {
-                let mut map = HASHLIFE.lock().unwrap();
-                map.insert(self.clone(), res_values.clone());
+    // The node with every cell that is alive in `other`, a node of the same
+    // side, flipped between alive and dead, which is how second-order rules
+    // bring in the generation before.
+    pub fn flip(&self, other: &Node) -> Node {
+        if other.0.population == 0 {
+            return self.clone();
+        }
+        match (&self.0.content, &other.0.content) {
+            (Content::Cell(cell), Content::Cell(_)) => Node::cell(if *cell == Cell::Alive { Cell::Dead } else { Cell::Alive }),
+            (Content::Quarters(quarters), Content::Quarters(others)) => {
+                let [nw, ne, sw, se] = std::array::from_fn(|i| quarters[i].flip(&others[i]));
+                Node::join(nw, ne, sw, se)
             }
-            return res_values; 
+            _ => unreachable!("flipped nodes have the same side"),
         }
     }
 }
-
@@ -0,0 +1,214 @@
+use std::collections::{HashSet, VecDeque};
+use crate::analysis;
+use crate::apgcode;
+use crate::error::Error;
+use crate::pattern::Pattern;
+use crate::rule::Rule;
+use crate::stepper::StepperKind;
+use crate::universe::Cell;
+
+// How far ahead an object is run to label it.
+const LABEL_GENERATIONS: usize = 128;
+
+// A group of live cells that are connected to each other, cropped to its
+// bounding box at (`left`, `top`) in the grid it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Island {
+    pub left: usize,
+    pub top: usize,
+    pub pattern: Pattern,
+}
+
+// One or more islands that have to be considered together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Object {
+    pub left: usize,
+    pub top: usize,
+    pub pattern: Pattern,
+    pub islands: Vec<Island>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grouping {
+    // Islands are grouped only if running them together gives a different
+    // result from running them apart, as apgsearch does.
+    Interacting,
+    // Islands are also grouped if they ever share a neighbouring cell, which
+    // keeps pseudo still lifes such as the bi-block whole.
+    Touching,
+}
+
+fn cells_of(left: usize, top: usize, pattern: &Pattern) -> Vec<(usize, usize)> {
+    let mut cells = Vec::new();
+    for y in 0..pattern.height {
+        for x in 0..pattern.width {
+            if pattern.get(x, y) == Cell::Alive {
+                cells.push((left + x, top + y));
+            }
+        }
+    }
+    cells
+}
+
+// Crops a list of cells to its bounding box.
+fn crop(cells: &[(usize, usize)]) -> (usize, usize, Pattern) {
+    let left = cells.iter().map(|(x, _)| *x).min().unwrap_or(0);
+    let top = cells.iter().map(|(_, y)| *y).min().unwrap_or(0);
+    let side = cells.iter().map(|(x, y)| (x - left).max(y - top) + 1).max().unwrap_or(0);
+    let mut grid = vec![Cell::Dead; side * side];
+    for (x, y) in cells {
+        grid[(x - left) + (y - top) * side] = Cell::Alive;
+    }
+    (left, top, Pattern::from_grid(&grid, side))
+}
+
+// Splits the live cells of a `side * side` grid into islands. Two live cells
+// belong to the same island if they are at most `distance` cells apart
+// horizontally and vertically, so a distance of 1 joins cells through their
+// eight neighbours.
+pub fn islands(cells: &[Cell], side: usize, distance: usize) -> Vec<Island> {
+    let mut seen = vec![false; cells.len()];
+    let mut islands = Vec::new();
+    for start in 0..cells.len() {
+        if seen[start] || cells[start] != Cell::Alive {
+            continue;
+        }
+        seen[start] = true;
+        let mut members = Vec::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(i) = queue.pop_front() {
+            let (x, y) = (i % side, i / side);
+            members.push((x, y));
+            for ny in y.saturating_sub(distance)..(y + distance + 1).min(side) {
+                for nx in x.saturating_sub(distance)..(x + distance + 1).min(side) {
+                    let j = nx + ny * side;
+                    if !seen[j] && cells[j] == Cell::Alive {
+                        seen[j] = true;
+                        queue.push_back(j);
+                    }
+                }
+            }
+        }
+        let (left, top, pattern) = crop(&members);
+        islands.push(Island { left, top, pattern });
+    }
+    islands
+}
+
+// The live cells of each of the next `generations` generations of `cells`, in
+// the same coordinates, which may run off the top or left of the grid.
+fn trajectory(cells: &[(usize, usize)], rule: &Rule, generations: usize) -> Result<Vec<HashSet<(isize, isize)>>, Error> {
+    let (left, top, pattern) = crop(cells);
+    // nothing spreads further than the rule's radius in a generation
+    let margin = (generations + 2) * rule.radius();
+    let side = pattern.width.max(pattern.height) + 2 * margin;
+    let mut stepper = StepperKind::Dense.create(side, *rule)?;
+    let mut grid = vec![Cell::Dead; side * side];
+    for (x, y) in cells {
+        grid[(x - left + margin) + (y - top + margin) * side] = Cell::Alive;
+    }
+    stepper.load(&grid)?;
+
+    let (dx, dy) = (left as isize - margin as isize, top as isize - margin as isize);
+    let mut trajectory = Vec::with_capacity(generations + 1);
+    for generation in 0..=generations {
+        if generation > 0 {
+            stepper.step(1);
+        }
+        let live = stepper
+            .cells()
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell == Cell::Alive)
+            .map(|(i, _)| ((i % side) as isize + dx, (i / side) as isize + dy))
+            .collect();
+        trajectory.push(live);
+    }
+    Ok(trajectory)
+}
+
+struct Group {
+    cells: Vec<(usize, usize)>,
+    islands: Vec<Island>,
+    trajectory: Vec<HashSet<(isize, isize)>>,
+}
+
+// Whether some generation has a live cell of `a` within `reach` cells of one
+// of `b`, so that the two share a neighbouring cell when `reach` is twice the
+// rule's radius. Islands that never do cannot affect each other.
+fn touching(a: &Group, b: &Group, reach: isize) -> bool {
+    a.trajectory.iter().zip(&b.trajectory).any(|(a_live, b_live)| {
+        a_live.iter().any(|(x, y)| {
+            (-reach..=reach).any(|dy| (-reach..=reach).any(|dx| b_live.contains(&(x + dx, y + dy))))
+        })
+    })
+}
+
+fn interacts(a: &Group, b: &Group, rule: &Rule, generations: usize, grouping: Grouping) -> Result<bool, Error> {
+    if !touching(a, b, 2 * rule.radius() as isize) {
+        return Ok(false);
+    }
+    if grouping == Grouping::Touching {
+        return Ok(true);
+    }
+    let together = [a.cells.as_slice(), b.cells.as_slice()].concat();
+    let together = trajectory(&together, rule, generations)?;
+    Ok(together
+        .iter()
+        .zip(a.trajectory.iter().zip(&b.trajectory))
+        .any(|(both, (a_live, b_live))| *both != a_live | b_live))
+}
+
+// Splits a `side * side` grid into objects: its islands at the given
+// `distance`, grouped by whether they affect each other over the next
+// `generations` generations.
+pub fn objects(cells: &[Cell], side: usize, distance: usize, rule: &Rule, generations: usize, grouping: Grouping) -> Result<Vec<Object>, Error> {
+    let mut groups = Vec::new();
+    for island in islands(cells, side, distance) {
+        let cells = cells_of(island.left, island.top, &island.pattern);
+        let trajectory = trajectory(&cells, rule, generations)?;
+        groups.push(Group { cells, islands: vec![island], trajectory });
+    }
+
+    // merge one pair at a time until no two groups interact, since a merged
+    // group can interact with an island that neither part did alone
+    'merging: loop {
+        for i in 0..groups.len() {
+            for j in i + 1..groups.len() {
+                if interacts(&groups[i], &groups[j], rule, generations, grouping)? {
+                    let merged = groups.swap_remove(j);
+                    let group = &mut groups[i];
+                    group.cells.extend(merged.cells);
+                    group.islands.extend(merged.islands);
+                    group.trajectory = trajectory(&group.cells, rule, generations)?;
+                    continue 'merging;
+                }
+            }
+        }
+        break;
+    }
+
+    let mut objects: Vec<Object> = groups
+        .into_iter()
+        .map(|group| {
+            let (left, top, pattern) = crop(&group.cells);
+            Object { left, top, pattern, islands: group.islands }
+        })
+        .collect();
+    objects.sort_by_key(|object| (object.top, object.left));
+    Ok(objects)
+}
+
+impl Object {
+    // The object's apgcode, or a description such as "dies out" if it is not
+    // yet periodic.
+    pub fn label(&self, rule: &Rule) -> Result<String, Error> {
+        let analysis = analysis::classify(&self.pattern, rule, LABEL_GENERATIONS)?;
+        if analysis.settled_at == 0 {
+            if let Some(code) = apgcode::encode_as(&self.pattern, rule, analysis.classification)? {
+                return Ok(code);
+            }
+        }
+        Ok(analysis.classification.to_string())
+    }
+}
@@ -0,0 +1,322 @@
+use crate::error::Error;
+use crate::rule::Rule;
+use crate::universe::Cell;
+
+// Golly wraps RLE lines at this width.
+const RLE_LINE: usize = 70;
+
+// The largest side of any grid the engines are run on. RLE text is read up
+// to this size, so a header or a count cannot ask for an unbounded grid.
+pub const MAX_SIDE: usize = 4096;
+
+fn check_size(width: usize, height: usize) -> Result<(), Error> {
+    if width > MAX_SIDE || height > MAX_SIDE || width.saturating_mul(height) > MAX_SIDE * MAX_SIDE {
+        return Err(Error::InvalidDimensions(format!(
+            "a {}x{} pattern is larger than the largest grid, {}x{}",
+            width, height, MAX_SIDE, MAX_SIDE
+        )));
+    }
+    Ok(())
+}
+
+// A rectangular patch of cells, stored row-major, that can be placed into a
+// stepper's grid.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<Cell>,
+}
+
+impl Pattern {
+    // Parses the plaintext format: `O` or `*` for live cells, `.` for dead
+    // cells, and `!` at the start of a line for comments. Short rows are padded
+    // with dead cells. Cells of multi-state rules can be written as digits,
+    // their state, such as `3` for copper in WireWorld.
+    pub fn from_plaintext(text: &str) -> Result<Pattern, Error> {
+        let mut rows: Vec<Vec<Cell>> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if line.starts_with('!') {
+                continue;
+            }
+            let mut row = Vec::new();
+            for c in line.chars() {
+                row.push(match c {
+                    'O' | '*' => Cell::Alive,
+                    '.' => Cell::Dead,
+                    '0'..='9' => Cell::from_state(c as u8 - b'0'),
+                    _ => return Err(Error::Parse(format!("unexpected '{}' on line {}", c, number + 1))),
+                });
+            }
+            rows.push(row);
+        }
+
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let height = rows.len();
+        let mut cells = Vec::with_capacity(width * height);
+        for mut row in rows {
+            row.resize(width, Cell::Dead);
+            cells.extend(row);
+        }
+        Ok(Pattern { width, height, cells })
+    }
+
+    pub fn to_plaintext(&self) -> String {
+        let mut text = String::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                text.push(match self.cells[x + y * self.width] {
+                    Cell::Alive => 'O',
+                    Cell::State(state) if state < 10 => (b'0' + state) as char,
+                    // plaintext has no room for higher states
+                    Cell::Dead | Cell::State(_) => '.',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    // Parses run-length encoding: `b` or `.` for dead cells, `o` for live
+    // ones, `A` to `X` for states 1 to 24 of multi-state rules, `$` for the
+    // end of a row and `!` for the end of the pattern, each optionally after
+    // a count. `#` lines and the `x = ...` header are skipped, although the
+    // header's size is kept. Patterns wider or taller than `MAX_SIDE` are
+    // rejected.
+    pub fn from_rle(text: &str) -> Result<Pattern, Error> {
+        let (mut width, mut height) = (0, 0);
+        let mut rows: Vec<Vec<Cell>> = vec![vec![]];
+        let mut count = String::new();
+        'lines: for line in text.lines().map(str::trim) {
+            if line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('x') {
+                for field in line.split(',') {
+                    let Some((key, value)) = field.split_once('=') else { continue };
+                    let size = || value.trim().parse::<usize>().map_err(|_| Error::Parse(format!("invalid size in \"{}\"", line)));
+                    match key.trim() {
+                        "x" => width = size()?,
+                        "y" => height = size()?,
+                        _ => {}
+                    }
+                }
+                check_size(width, height)?;
+                continue;
+            }
+            for c in line.chars() {
+                let run = if count.is_empty() {
+                    1
+                } else {
+                    count.parse::<usize>().map_err(|_| Error::Parse(format!("invalid count \"{}\"", count)))?
+                };
+                let cell = match c {
+                    '0'..='9' => {
+                        count.push(c);
+                        continue;
+                    }
+                    c if c.is_whitespace() => continue,
+                    'b' | '.' => Cell::Dead,
+                    'o' => Cell::Alive,
+                    'A'..='X' => Cell::from_state(c as u8 - b'A' + 1),
+                    '$' => {
+                        check_size(0, rows.len().saturating_add(run))?;
+                        rows.extend((0..run).map(|_| vec![]));
+                        count.clear();
+                        continue;
+                    }
+                    '!' => break 'lines,
+                    _ => return Err(Error::Parse(format!("unexpected '{}' in RLE", c))),
+                };
+                let row = rows.last_mut().expect("there is always a row");
+                check_size(row.len().saturating_add(run), 1)?;
+                row.extend((0..run).map(|_| cell));
+                count.clear();
+            }
+        }
+        // trailing dead rows are only there if the header says so
+        while rows.len() > 1 && rows.last().is_some_and(Vec::is_empty) {
+            rows.pop();
+        }
+        let width = width.max(rows.iter().map(Vec::len).max().unwrap_or(0));
+        let height = height.max(if width == 0 { 0 } else { rows.len() });
+        let mut cells = Vec::with_capacity(width * height);
+        for mut row in rows.into_iter().chain(std::iter::repeat(vec![])).take(height) {
+            row.resize(width, Cell::Dead);
+            cells.extend(row);
+        }
+        Ok(Pattern { width, height, cells })
+    }
+
+    // The rule named in an RLE header, as in `x = 3, y = 3, rule = B36/S23`.
+    // It is the last field, as it may itself contain commas.
+    pub fn rle_rule(text: &str) -> Result<Option<Rule>, Error> {
+        let Some(header) = text.lines().map(str::trim).find(|line| line.starts_with('x') && line.contains('=')) else {
+            return Ok(None);
+        };
+        header
+            .find("rule")
+            .and_then(|i| header[i + "rule".len()..].trim_start().strip_prefix('='))
+            .map(|rule| rule.trim().parse())
+            .transpose()
+    }
+
+    // Parses either RLE or plaintext, going by whether the text has an RLE
+    // header or ends with `!` on a line that is not a plaintext comment.
+    pub fn from_text(text: &str) -> Result<Pattern, Error> {
+        let last = text.trim_end().lines().last().unwrap_or("");
+        let header = text.lines().any(|line| line.starts_with('x') && line.contains('='));
+        if header || (last.ends_with('!') && !last.starts_with('!')) {
+            Pattern::from_rle(text)
+        } else {
+            Pattern::from_plaintext(text)
+        }
+    }
+
+    // Run-length encoding with a header giving the size and `rule`, in
+    // lines of at most 70 characters.
+    pub fn to_rle(&self, rule: &Rule) -> String {
+        let multistate = self.cells.iter().any(|cell| matches!(cell, Cell::State(_)));
+        let mut runs: Vec<(usize, char)> = Vec::new();
+        let push = |runs: &mut Vec<(usize, char)>, tag: char| match runs.last_mut() {
+            Some((count, last)) if *last == tag => *count += 1,
+            _ => runs.push((1, tag)),
+        };
+        for y in 0..self.height {
+            let row = &self.cells[y * self.width..(y + 1) * self.width];
+            let used = row.iter().rposition(|cell| *cell != Cell::Dead).map_or(0, |x| x + 1);
+            if y > 0 {
+                push(&mut runs, '$');
+            }
+            for cell in &row[..used] {
+                let tag = match (cell.state(), multistate) {
+                    (0, false) => 'b',
+                    (0, true) => '.',
+                    (_, false) => 'o',
+                    // states beyond X need two letters, which are not written
+                    (state, true) => (b'A' + state.min(24) - 1) as char,
+                };
+                push(&mut runs, tag);
+            }
+        }
+        // blank rows at the end are implied by the header
+        while runs.last().is_some_and(|(_, tag)| *tag == '$') {
+            runs.pop();
+        }
+        runs.push((1, '!'));
+
+        let mut text = format!("x = {}, y = {}, rule = {}\n", self.width, self.height, rule);
+        let mut line = String::new();
+        for (count, tag) in runs {
+            let run = if count == 1 { tag.to_string() } else { format!("{}{}", count, tag) };
+            if line.len() + run.len() > RLE_LINE {
+                text.push_str(&line);
+                text.push('\n');
+                line.clear();
+            }
+            line.push_str(&run);
+        }
+        text.push_str(&line);
+        text.push('\n');
+        text
+    }
+
+    // Crops a row-major grid of `side * side` cells to the bounding box of its
+    // cells that are not dead.
+    pub fn from_grid(cells: &[Cell], side: usize) -> Pattern {
+        let Some((left, top, width, height)) = bounds(cells, side) else {
+            return Pattern { width: 0, height: 0, cells: vec![] };
+        };
+        Pattern::crop(cells, side, left, top, width, height)
+    }
+
+    // The `width` by `height` box at (`left`, `top`) of a `side` grid.
+    pub fn crop(cells: &[Cell], side: usize, left: usize, top: usize, width: usize, height: usize) -> Pattern {
+        let mut pattern_cells = vec![Cell::Dead; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                pattern_cells[x + y * width] = cells[(x + left) + (y + top) * side];
+            }
+        }
+        Pattern { width, height, cells: pattern_cells }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        self.cells[x + y * self.width]
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|cell| **cell == Cell::Alive).count()
+    }
+
+    // Mirrors the pattern in its main diagonal.
+    pub fn transposed(&self) -> Pattern {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for x in 0..self.width {
+            for y in 0..self.height {
+                cells.push(self.get(x, y));
+            }
+        }
+        Pattern { width: self.height, height: self.width, cells }
+    }
+
+    // Mirrors the pattern left to right.
+    pub fn flipped(&self) -> Pattern {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.height {
+            for x in (0..self.width).rev() {
+                cells.push(self.get(x, y));
+            }
+        }
+        Pattern { width: self.width, height: self.height, cells }
+    }
+
+    // The eight rotations and reflections of the pattern, starting with the
+    // pattern itself. Symmetric patterns appear more than once.
+    pub fn orientations(&self) -> Vec<Pattern> {
+        let mut orientations = Vec::with_capacity(8);
+        let mut current = self.clone();
+        for _ in 0..4 {
+            orientations.push(current.clone());
+            orientations.push(current.transposed());
+            // a transpose followed by a flip is a quarter turn
+            current = current.transposed().flipped();
+        }
+        orientations
+    }
+
+    // Places the pattern in the middle of an empty `side * side` grid. Parts
+    // that do not fit are cut off.
+    pub fn centred(&self, side: usize) -> Vec<Cell> {
+        let mut grid = vec![Cell::Dead; side * side];
+        let left = (side as isize - self.width as isize) / 2;
+        let top = (side as isize - self.height as isize) / 2;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let gx = x as isize + left;
+                let gy = y as isize + top;
+                if gx < 0 || gy < 0 || gx >= side as isize || gy >= side as isize {
+                    continue;
+                }
+                grid[gx as usize + gy as usize * side] = self.cells[x + y * self.width];
+            }
+        }
+        grid
+    }
+}
+
+// The bounding box `(left, top, width, height)` of the cells that are not
+// dead in a row-major grid of `side * side` cells, or `None` if there are none.
+pub fn bounds(cells: &[Cell], side: usize) -> Option<(usize, usize, usize, usize)> {
+    let mut live = (0..cells.len()).filter(|i| cells[*i] != Cell::Dead).map(|i| (i % side, i / side));
+    let (x, y) = live.next()?;
+    let (mut left, mut right, mut top, mut bottom) = (x, x, y, y);
+    for (x, y) in live {
+        left = left.min(x);
+        right = right.max(x);
+        top = top.min(y);
+        bottom = bottom.max(y);
+    }
+    Some((left, top, right - left + 1, bottom - top + 1))
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::error::Error;
+use crate::pattern::Pattern;
+use crate::rule::{Rule, NEIGHBOURS};
+use crate::sat::{self, Cnf, Literal};
+use crate::universe::Cell;
+
+// What the next generation must look like around the target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    // The target is the whole next generation, so every cell around it must
+    // be dead.
+    Dead,
+    // Only the target's own cells must match. With no predecessor at all,
+    // the target is an orphan: it cannot appear anywhere, in any pattern.
+    Free,
+}
+
+impl Boundary {
+    pub const ALL: [Boundary; 2] = [Boundary::Dead, Boundary::Free];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Boundary::Dead => "dead",
+            Boundary::Free => "free",
+        }
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for Boundary {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Boundary::ALL
+            .into_iter()
+            .find(|boundary| boundary.name() == s)
+            .ok_or_else(|| Error::Parse(format!("unknown boundary \"{}\"", s)))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PredecessorOptions {
+    pub rule: Rule,
+    // Predecessors are looked for in the target's box grown by this many
+    // cells on every side, and cells beyond are taken to be dead. Under a
+    // free boundary that must be at least one, so that every neighbour of
+    // the target is free, and cells further away make no difference.
+    pub margin: usize,
+    pub boundary: Boundary,
+    // The most predecessors to find.
+    pub limit: usize,
+}
+
+impl Default for PredecessorOptions {
+    fn default() -> Self {
+        PredecessorOptions { rule: Rule::default(), margin: 1, boundary: Boundary::Dead, limit: 1 }
+    }
+}
+
+// "Some pattern in a `width * height` box evolves into the target" as CNF.
+// Variable 1 + x + y * width says whether cell (x, y) of the box is alive;
+// the target sits `margin` cells in from its top left.
+#[derive(Clone, Debug)]
+pub struct Encoding {
+    pub cnf: Cnf,
+    pub width: usize,
+    pub height: usize,
+    pub margin: usize,
+}
+
+impl Encoding {
+    pub fn variable(&self, x: usize, y: usize) -> Literal {
+        (1 + x + y * self.width) as Literal
+    }
+
+    // The predecessor a satisfying assignment describes.
+    pub fn decode(&self, assignment: &[bool]) -> Pattern {
+        let cells = (0..self.width * self.height)
+            .map(|i| if assignment[i] { Cell::Alive } else { Cell::Dead })
+            .collect();
+        Pattern { width: self.width, height: self.height, cells }
+    }
+
+    pub fn to_dimacs(&self, rule: &Rule) -> String {
+        let comments = [
+            format!("predecessors under {} in a {}x{} box", rule, self.width, self.height),
+            format!("variable 1 + x + y * {} is cell (x, y), with the target from ({m}, {m})", self.width, m = self.margin),
+        ];
+        self.cnf.to_dimacs(&comments)
+    }
+}
+
+// Each cell of the next generation that has to have a given state rules out,
+// with one clause each, the arrangements of itself and its neighbours in the
+// box that would give it the other state.
+pub fn encode(target: &Pattern, options: &PredecessorOptions) -> Result<Encoding, Error> {
+    let rule = &options.rule;
+    if !rule.is_two_state_nearest() {
+        return Err(Error::UnsupportedRule(format!("cannot work backwards through {}", rule)));
+    }
+    if let Some(state) = target.cells.iter().map(|cell| cell.state()).find(|state| *state > 1) {
+        return Err(Error::Parse(format!("a two-state target cannot have a cell in state {}", state)));
+    }
+    if options.boundary == Boundary::Free && options.margin == 0 {
+        return Err(Error::InvalidDimensions("a free boundary needs a margin of at least 1".to_string()));
+    }
+    // only the ring of cells just outside the box is checked to stay dead,
+    // which under B0 would not be enough
+    if options.boundary == Boundary::Dead && rule.is_born(0) {
+        return Err(Error::UnsupportedRule(format!("{} brings dead space to life, so nothing stays dead around the pattern", rule)));
+    }
+    let margin = options.margin as isize;
+    let (width, height) = (target.width + 2 * options.margin, target.height + 2 * options.margin);
+    let mut encoding = Encoding { cnf: Cnf::new(), width, height, margin: options.margin };
+    encoding.cnf.variables = width * height;
+
+    // the cells of the next generation that must match, in box coordinates
+    let reach = match options.boundary {
+        Boundary::Dead => 1,
+        Boundary::Free => -margin,
+    };
+    for y in -reach..height as isize + reach {
+        for x in -reach..width as isize + reach {
+            let (tx, ty) = (x - margin, y - margin);
+            let inside = tx >= 0 && ty >= 0 && (tx as usize) < target.width && (ty as usize) < target.height;
+            let alive = inside && target.get(tx as usize, ty as usize) == Cell::Alive;
+
+            // the cell itself, then its neighbours as ordered in `NEIGHBOURS`,
+            // where they are in the box
+            let offsets = std::iter::once((0, 0)).chain(NEIGHBOURS.iter().copied());
+            let present: Vec<(usize, Literal)> = offsets
+                .enumerate()
+                .filter_map(|(i, (dx, dy))| {
+                    let (nx, ny) = (x + dx, y + dy);
+                    let within = nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height;
+                    within.then(|| (i, encoding.variable(nx as usize, ny as usize)))
+                })
+                .collect();
+            for arrangement in 0..1u32 << present.len() {
+                let mut centre = false;
+                let mut neighbourhood = 0u8;
+                for (bit, (i, _)) in present.iter().enumerate() {
+                    if arrangement >> bit & 1 == 1 {
+                        match i {
+                            0 => centre = true,
+                            i => neighbourhood |= 1 << (i - 1),
+                        }
+                    }
+                }
+                let next = if centre { rule.survives(neighbourhood) } else { rule.is_born(neighbourhood) };
+                if next != alive {
+                    let clause = present
+                        .iter()
+                        .enumerate()
+                        .map(|(bit, (_, variable))| if arrangement >> bit & 1 == 1 { -variable } else { *variable })
+                        .collect();
+                    encoding.cnf.add(clause);
+                }
+            }
+        }
+    }
+    Ok(encoding)
+}
+
+// Up to `options.limit` different patterns in the target's box grown by
+// `options.margin` that become the target in one generation. None at all
+// proves that there are none in that box, or under a free boundary that the
+// target is an orphan.
+pub fn predecessors(target: &Pattern, options: &PredecessorOptions) -> Result<Vec<Pattern>, Error> {
+    let mut encoding = encode(target, options)?;
+    let mut found = vec![];
+    while found.len() < options.limit {
+        let Some(assignment) = sat::solve(&encoding.cnf) else {
+            break;
+        };
+        // the next search must differ from this predecessor somewhere
+        let blocking = (0..encoding.width * encoding.height)
+            .map(|i| if assignment[i] { -(i as Literal + 1) } else { i as Literal + 1 })
+            .collect();
+        encoding.cnf.add(blocking);
+        found.push(encoding.decode(&assignment));
+    }
+    Ok(found)
+}
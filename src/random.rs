@@ -0,0 +1,52 @@
+use crate::pattern::Pattern;
+use crate::universe::Cell;
+
+// SplitMix64: tiny, seedable, and identical on every target, so a seed always
+// reproduces the same soup natively and in the browser.
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in [0, n).
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+// A random number fixed by `seed` and `values` alone, for draws that must not
+// depend on the order they are made in.
+pub fn hash(seed: u64, values: &[u64]) -> u64 {
+    values.iter().fold(Random::new(seed).next_u64(), |hash, value| Random::new(hash ^ value).next_u64())
+}
+
+// A `width * height` soup where each cell is alive with probability `density`.
+pub fn soup(seed: u64, width: usize, height: usize, density: f64) -> Pattern {
+    let mut random = Random::new(seed);
+    let cells = (0..width * height)
+        .map(|_| if random.chance(density) { Cell::Alive } else { Cell::Dead })
+        .collect();
+    Pattern { width, height, cells }
+}
@@ -0,0 +1,170 @@
+use crate::rule::{Blocks, Range, Rule, NEIGHBOURS};
+use crate::life3d::Rule3D;
+use crate::universe::Cell;
+
+// Straightforward implementation of a single generation, kept deliberately
+// simple so the engines can be checked against it. Cells outside the
+// `side * side` grid are dead.
+
+pub fn neighbours(cells: &[Cell], side: usize, x: usize, y: usize) -> usize {
+    let mut count = 0;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let nx = x as isize + dx;
+            let ny = y as isize + dy;
+            if (dx, dy) == (0, 0) || nx < 0 || ny < 0 || nx >= side as isize || ny >= side as isize {
+                continue;
+            }
+            if cells[nx as usize + ny as usize * side] == Cell::Alive {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+// The live neighbours of (x, y) as a bit for each of `NEIGHBOURS`.
+pub fn neighbourhood(cells: &[Cell], side: usize, x: usize, y: usize) -> u8 {
+    let mut neighbourhood = 0;
+    for (i, (dx, dy)) in NEIGHBOURS.iter().enumerate() {
+        let nx = x as isize + dx;
+        let ny = y as isize + dy;
+        if nx < 0 || ny < 0 || nx >= side as isize || ny >= side as isize {
+            continue;
+        }
+        if cells[nx as usize + ny as usize * side] == Cell::Alive {
+            neighbourhood |= 1 << i;
+        }
+    }
+    neighbourhood
+}
+
+// The states of the neighbours of (x, y), ordered as in `NEIGHBOURS`.
+pub fn neighbour_states(cells: &[Cell], side: usize, x: usize, y: usize) -> [u8; 8] {
+    let mut states = [0; 8];
+    for (i, (dx, dy)) in NEIGHBOURS.iter().enumerate() {
+        let nx = x as isize + dx;
+        let ny = y as isize + dy;
+        if nx < 0 || ny < 0 || nx >= side as isize || ny >= side as isize {
+            continue;
+        }
+        states[i] = cells[nx as usize + ny as usize * side].state();
+    }
+    states
+}
+
+// The number of live cells in the range of (x, y), not counting itself.
+pub fn count_in_range(cells: &[Cell], side: usize, x: usize, y: usize, range: &Range) -> usize {
+    let mut count = 0;
+    let radius = range.radius as isize;
+    for dy in -radius..=radius {
+        let width = range.width(dy.unsigned_abs()) as isize;
+        for dx in -width..=width {
+            let nx = x as isize + dx;
+            let ny = y as isize + dy;
+            if (dx, dy) == (0, 0) || nx < 0 || ny < 0 || nx >= side as isize || ny >= side as isize {
+                continue;
+            }
+            if cells[nx as usize + ny as usize * side] == Cell::Alive {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+pub fn next_cell(cells: &[Cell], side: usize, x: usize, y: usize, rule: &Rule) -> Cell {
+    if let Some(table) = rule.table() {
+        let cell = cells[x + y * side];
+        return Cell::from_state(table.next(cell.state(), &neighbour_states(cells, side, x, y)));
+    }
+    if let Some(range) = rule.range() {
+        return rule.next_counted(cells[x + y * side], count_in_range(cells, side, x, y, &range));
+    }
+    rule.next(cells[x + y * side], neighbourhood(cells, side, x, y))
+}
+
+// The next state of (x, y) under a block rule, found from the whole 2x2 block
+// it is in during `generation`. Cells outside the grid count as dead.
+pub fn block_cell(cells: &[Cell], side: usize, x: usize, y: usize, blocks: &Blocks, generation: usize) -> Cell {
+    let offset = generation % 2;
+    // the top left of the block, which may be just outside the grid
+    let (left, top) = ((x + offset) / 2 * 2, (y + offset) / 2 * 2);
+    let mut block = 0;
+    for (bit, (dx, dy)) in [(0, 0), (1, 0), (0, 1), (1, 1)].into_iter().enumerate() {
+        let (bx, by) = ((left + dx) as isize - offset as isize, (top + dy) as isize - offset as isize);
+        if bx >= 0 && by >= 0 && (bx as usize) < side && (by as usize) < side && cells[bx as usize + by as usize * side] == Cell::Alive {
+            block |= 1 << bit;
+        }
+    }
+    let bit = (x + offset - left) + 2 * (y + offset - top);
+    if blocks[block] >> bit & 1 == 1 {
+        Cell::Alive
+    } else {
+        Cell::Dead
+    }
+}
+
+pub fn step(cells: &[Cell], side: usize, rule: &Rule) -> Vec<Cell> {
+    step_at(cells, side, rule, 0)
+}
+
+// Steps the grid from `generation`, which only block rules and stochastic
+// rules depend on.
+pub fn step_at(cells: &[Cell], side: usize, rule: &Rule, generation: usize) -> Vec<Cell> {
+    let mut next = Vec::with_capacity(cells.len());
+    for y in 0..side {
+        for x in 0..side {
+            next.push(match (rule.blocks(), rule.chances()) {
+                (Some(blocks), _) => block_cell(cells, side, x, y, &blocks, generation),
+                (None, Some(chances)) => chances.apply(cells[x + y * side], next_cell(cells, side, x, y, rule), x, y, generation),
+                (None, None) => next_cell(cells, side, x, y, rule),
+            });
+        }
+    }
+    next
+}
+
+// A step of a second-order rule, which flips every cell of the first-order
+// step that was alive in the `previous` generation.
+pub fn step_second_order(previous: &[Cell], cells: &[Cell], side: usize, rule: &Rule) -> Vec<Cell> {
+    let mut next = step(cells, side, rule);
+    for (cell, before) in next.iter_mut().zip(previous) {
+        if *before == Cell::Alive {
+            *cell = if *cell == Cell::Alive { Cell::Dead } else { Cell::Alive };
+        }
+    }
+    next
+}
+
+// The live cells among the 26 around (x, y, z) in a `side` cube stored x
+// first, then y, then z.
+pub fn neighbours_3d(cells: &[Cell], side: usize, x: usize, y: usize, z: usize) -> usize {
+    let mut count = 0;
+    for dz in -1..=1 {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (nx, ny, nz) = (x as isize + dx, y as isize + dy, z as isize + dz);
+                if (dx, dy, dz) == (0, 0, 0) || [nx, ny, nz].iter().any(|c| *c < 0 || *c >= side as isize) {
+                    continue;
+                }
+                if cells[nx as usize + ny as usize * side + nz as usize * side * side] == Cell::Alive {
+                    count += 1;
+                }
+            }
+        }
+    }
+    count
+}
+
+pub fn step_3d(cells: &[Cell], side: usize, rule: &Rule3D) -> Vec<Cell> {
+    let mut next = Vec::with_capacity(cells.len());
+    for z in 0..side {
+        for y in 0..side {
+            for x in 0..side {
+                next.push(rule.next(cells[x + y * side + z * side * side], neighbours_3d(cells, side, x, y, z)));
+            }
+        }
+    }
+    next
+}
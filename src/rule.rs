@@ -0,0 +1,830 @@
+use std::{fmt, str::FromStr, sync::Arc};
+use crate::error::Error;
+use crate::random;
+use crate::ruletable::{self, RuleTable};
+use crate::universe::Cell;
+
+// The eight neighbours of a cell as (dx, dy) offsets, clockwise from north.
+// Bit `i` of a neighbourhood is set when neighbour `i` is alive.
+pub const NEIGHBOURS: [(isize, isize); 8] = [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+
+lazy_static! {
+    // The built-in WireWorld table, parsed and registered once.
+    static ref WIREWORLD: Rule = Rule { states: 4, table: ruletable::built_in("WireWorld"), ..Rule::new(&[], &[]) };
+}
+
+// Hensel's letters for each way of arranging up to four live neighbours, in
+// his order, with one neighbourhood of each. The other neighbourhoods with a
+// letter are its rotations and reflections. Five or more neighbours use the
+// letter of the dead neighbours around them, so `5c` is the opposite of `3c`.
+const LETTERS: [&[(char, u8)]; 5] = [
+    &[],
+    &[('c', 0b0000_0010), ('e', 0b0000_0001)],
+    &[('c', 0b0000_1010), ('e', 0b0000_0101), ('a', 0b0000_0011), ('i', 0b0001_0001), ('k', 0b0000_1001), ('n', 0b0010_0010)],
+    &[
+        ('c', 0b0010_1010),
+        ('e', 0b0001_0101),
+        ('a', 0b0000_0111),
+        ('i', 0b1000_0011),
+        ('k', 0b0010_0101),
+        ('n', 0b0000_1011),
+        ('j', 0b0100_0011),
+        ('q', 0b0010_0011),
+        ('r', 0b0001_0011),
+        ('y', 0b0010_1001),
+    ],
+    &[
+        ('c', 0b1010_1010),
+        ('e', 0b0101_0101),
+        ('a', 0b0000_1111),
+        ('i', 0b0001_1011),
+        ('k', 0b0100_1011),
+        ('n', 0b1000_1011),
+        ('j', 0b0101_0011),
+        ('q', 0b0010_0111),
+        ('r', 0b0001_0111),
+        ('y', 0b0010_1011),
+        ('t', 0b1001_0011),
+        ('w', 0b1000_1101),
+        ('z', 0b0011_0011),
+    ],
+];
+
+// A set of neighbourhoods, one bit for each of the 256 ways the eight
+// neighbours can be alive or dead.
+type Configurations = [u64; 4];
+
+fn contains(set: &Configurations, neighbourhood: u8) -> bool {
+    set[neighbourhood as usize / 64] >> (neighbourhood % 64) & 1 == 1
+}
+
+fn insert(set: &mut Configurations, neighbourhood: u8) {
+    set[neighbourhood as usize / 64] |= 1 << (neighbourhood % 64);
+}
+
+fn with_count(count: usize) -> impl Iterator<Item = u8> {
+    (0..=255u8).filter(move |neighbourhood| neighbourhood.count_ones() as usize == count)
+}
+
+// The rotations and reflections of a neighbourhood.
+fn symmetries(neighbourhood: u8) -> [u8; 8] {
+    let mut symmetries = [0; 8];
+    let mut current = neighbourhood;
+    for turn in 0..4 {
+        symmetries[2 * turn] = current;
+        // a mirror in the north-south axis takes neighbour i to 8 - i
+        symmetries[2 * turn + 1] = (0..8).filter(|i| current >> i & 1 == 1).map(|i| 1 << ((8 - i) % 8)).sum();
+        // a quarter turn moves every neighbour two places round
+        current = current.rotate_left(2);
+    }
+    symmetries
+}
+
+// The letters that can follow `count`, and the neighbourhoods each stands for.
+fn letters(count: usize) -> Vec<(char, Vec<u8>)> {
+    let (letters, invert) = if count > 4 { (LETTERS[8 - count], true) } else { (LETTERS[count], false) };
+    letters
+        .iter()
+        .map(|(letter, neighbourhood)| {
+            let mut class: Vec<u8> = symmetries(*neighbourhood).iter().map(|n| if invert { !n } else { *n }).collect();
+            class.sort_unstable();
+            class.dedup();
+            (*letter, class)
+        })
+        .collect()
+}
+
+// Which of the eight cells around a cell count towards its neighbourhood. On
+// the hexagonal grid each row is offset half a cell from the next, which
+// leaves the north-east and south-west cells out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Neighbourhood {
+    #[default]
+    Moore,
+    VonNeumann,
+    Hexagonal,
+    // a bit for each of `NEIGHBOURS` that counts
+    Custom(u8),
+}
+
+impl Neighbourhood {
+    pub fn from_mask(mask: u8) -> Self {
+        [Neighbourhood::Moore, Neighbourhood::VonNeumann, Neighbourhood::Hexagonal]
+            .into_iter()
+            .find(|neighbourhood| neighbourhood.mask() == mask)
+            .unwrap_or(Neighbourhood::Custom(mask))
+    }
+
+    pub fn mask(self) -> u8 {
+        match self {
+            Neighbourhood::Moore => 0b1111_1111,
+            Neighbourhood::VonNeumann => 0b0101_0101,
+            Neighbourhood::Hexagonal => 0b1101_1101,
+            Neighbourhood::Custom(mask) => mask,
+        }
+    }
+
+    // The number of neighbours a cell has.
+    pub fn size(self) -> usize {
+        self.mask().count_ones() as usize
+    }
+}
+
+// The largest radius of a Larger than Life rule.
+pub const MAX_RADIUS: usize = 16;
+
+// The shape of a Larger than Life neighbourhood: a square, a diamond, or the
+// cells whose centres lie within `radius + 1/2` of the cell's centre.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shape {
+    Moore,
+    VonNeumann,
+    Circular,
+}
+
+// The neighbourhood and conditions of a Larger than Life rule, which counts
+// every live cell within `radius` and checks the count against a range. With
+// `middle` set, a cell counts itself towards survival.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Range {
+    pub radius: usize,
+    pub shape: Shape,
+    pub middle: bool,
+    pub birth: (usize, usize),
+    pub survival: (usize, usize),
+}
+
+impl Range {
+    // How far either side of the cell row `dy` of the neighbourhood reaches.
+    pub fn width(&self, dy: usize) -> usize {
+        match self.shape {
+            Shape::Moore => self.radius,
+            Shape::VonNeumann => self.radius - dy,
+            // x^2 + y^2 < (r + 1/2)^2, in whole numbers
+            Shape::Circular => (0..=self.radius).rev().find(|dx| dx * dx + dy * dy <= self.radius * (self.radius + 1)).unwrap_or(0),
+        }
+    }
+
+    // The number of cells around a cell, not counting the cell itself.
+    pub fn size(&self) -> usize {
+        (0..=self.radius).map(|dy| if dy == 0 { 2 * self.width(0) } else { 2 * (2 * self.width(dy) + 1) }).sum()
+    }
+
+    fn is_born(&self, count: usize) -> bool {
+        (self.birth.0..=self.birth.1).contains(&count)
+    }
+
+    fn survives(&self, count: usize) -> bool {
+        let count = count + self.middle as usize;
+        (self.survival.0..=self.survival.1).contains(&count)
+    }
+}
+
+// The block each 2x2 block of cells becomes under a block rule. A block is
+// numbered by its live cells: 1 for the top left, 2 for the top right, 4 for
+// the bottom left and 8 for the bottom right. The blocks start at even
+// coordinates in even generations and at odd ones in odd generations.
+pub type Blocks = [u8; 16];
+
+// A rule on the Moore neighbourhood or part of it: a dead cell whose live
+// neighbours form a neighbourhood in `birth` comes alive, and a live cell with
+// one in `survival` stays alive. Outer-totalistic rules such as B3/S23 only
+// look at how many neighbours are alive; isotropic non-totalistic rules such
+// as B2-a/S12 also tell apart arrangements that are not rotations or
+// reflections of each other. With more than two `states` this is a
+// Generations rule: a live cell that does not survive passes through the
+// dying states 2, 3, ... in turn before it is dead, and cannot be born again
+// until then. Only live cells count as neighbours, and only those in the
+// rule's `neighbourhood`, unless the rule has a `range`, in which case it is
+// a Larger than Life rule and counts the live cells in that instead. A rule
+// loaded from a Golly rule file has a `table` instead, which gives the next
+// state from the states of the cell and its neighbours. A block rule has
+// `blocks` instead, and changes the grid 2x2 block by block. A second-order
+// rule takes the result of the rest of the rule and flips every cell that was
+// alive the generation before, which makes it reversible. A stochastic rule
+// only carries out each birth by chance, and flips cells at random as noise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rule {
+    birth: Configurations,
+    survival: Configurations,
+    states: u8,
+    neighbourhood: Neighbourhood,
+    range: Option<Range>,
+    // an index into the tables registered in `ruletable`
+    table: Option<usize>,
+    blocks: Option<Blocks>,
+    second_order: bool,
+    chances: Option<Chances>,
+}
+
+// How often births succeed and cells flip under a stochastic rule, in
+// millionths. Every draw is a hash of the seed, the cell and the generation,
+// so a seed always gives the same run, whichever engine runs it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Chances {
+    pub birth: u32,
+    pub noise: u32,
+    pub seed: u64,
+}
+
+pub const CERTAIN: u32 = 1_000_000;
+
+impl Chances {
+    fn draw(&self, x: usize, y: usize, generation: usize, purpose: u64) -> u32 {
+        (random::hash(self.seed, &[x as u64, y as u64, generation as u64, purpose]) % CERTAIN as u64) as u32
+    }
+
+    // Whether a birth the rule calls for at (x, y) happens.
+    pub fn born(&self, x: usize, y: usize, generation: usize) -> bool {
+        self.birth >= CERTAIN || self.draw(x, y, generation, 0) < self.birth
+    }
+
+    // Whether noise flips the cell at (x, y).
+    pub fn flips(&self, x: usize, y: usize, generation: usize) -> bool {
+        self.noise > 0 && self.draw(x, y, generation, 1) < self.noise
+    }
+
+    // The cell at (x, y) after chance has had its say on the rule's result.
+    pub fn apply(&self, before: Cell, after: Cell, x: usize, y: usize, generation: usize) -> Cell {
+        let mut alive = after == Cell::Alive;
+        if alive && before != Cell::Alive && !self.born(x, y, generation) {
+            alive = false;
+        }
+        if self.flips(x, y, generation) {
+            alive = !alive;
+        }
+        if alive {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+}
+
+impl Rule {
+    pub fn new(birth: &[usize], survival: &[usize]) -> Self {
+        let mut rule = Rule {
+            birth: [0; 4],
+            survival: [0; 4],
+            states: 2,
+            neighbourhood: Neighbourhood::Moore,
+            range: None,
+            table: None,
+            blocks: None,
+            second_order: false,
+            chances: None,
+        };
+        for neighbourhood in birth.iter().flat_map(|count| with_count(*count)) {
+            insert(&mut rule.birth, neighbourhood);
+        }
+        for neighbourhood in survival.iter().flat_map(|count| with_count(*count)) {
+            insert(&mut rule.survival, neighbourhood);
+        }
+        rule
+    }
+
+    // A Generations rule with `states` states in all, counting dead and alive.
+    pub fn generations(birth: &[usize], survival: &[usize], states: u8) -> Result<Self, Error> {
+        Rule::new(birth, survival).with_states(states)
+    }
+
+    fn with_states(self, states: u8) -> Result<Self, Error> {
+        if states < 2 {
+            return Err(Error::Parse(format!("a rule needs at least 2 states, not {}", states)));
+        }
+        Ok(Rule { states, ..self })
+    }
+
+    // The same rule counting only the neighbours in `neighbourhood`.
+    pub fn with_neighbourhood(self, neighbourhood: Neighbourhood) -> Self {
+        // forget the neighbourhoods that can no longer happen, so that equal
+        // rules compare equal
+        let mut rule = Rule { birth: [0; 4], survival: [0; 4], neighbourhood, ..self };
+        for count in 0..=neighbourhood.size() {
+            for n in rule.configurations(count) {
+                if contains(&self.birth, n) {
+                    insert(&mut rule.birth, n);
+                }
+                if contains(&self.survival, n) {
+                    insert(&mut rule.survival, n);
+                }
+            }
+        }
+        rule
+    }
+
+    // A Larger than Life rule.
+    pub fn larger_than_life(range: Range, states: u8) -> Result<Self, Error> {
+        if !(1..=MAX_RADIUS).contains(&range.radius) {
+            return Err(Error::Parse(format!("the radius must be from 1 to {}, not {}", MAX_RADIUS, range.radius)));
+        }
+        Rule { range: Some(range), ..Rule::new(&[], &[]) }.with_states(states)
+    }
+
+    // A rule given by a rule file's table or tree, which is registered so that
+    // it can be found by name later.
+    pub fn from_table(table: RuleTable) -> Self {
+        let states = table.states;
+        Rule { states, table: Some(ruletable::register(table)), ..Rule::new(&[], &[]) }
+    }
+
+    // The built-in WireWorld, as opposed to any other table of that name.
+    pub fn wireworld() -> Self {
+        *WIREWORLD
+    }
+
+    pub fn table(&self) -> Option<Arc<RuleTable>> {
+        self.table.map(ruletable::get)
+    }
+
+    // A block rule, also known as a Margolus neighbourhood rule.
+    pub fn block(blocks: Blocks) -> Self {
+        Rule { blocks: Some(blocks), ..Rule::new(&[], &[]) }
+    }
+
+    pub fn blocks(&self) -> Option<Blocks> {
+        self.blocks
+    }
+
+    // Fredkin's second-order version of a two-state rule, whose next
+    // generation depends on the previous one too.
+    pub fn second_order(self) -> Result<Self, Error> {
+        if self.states > 2 || self.table.is_some() || self.blocks.is_some() || self.chances.is_some() {
+            return Err(Error::UnsupportedRule(format!("{} has no second-order version", self)));
+        }
+        Ok(Rule { second_order: true, ..self })
+    }
+
+    pub fn is_second_order(&self) -> bool {
+        self.second_order
+    }
+
+    // The rule without its dependence on the previous generation.
+    pub fn first_order(self) -> Self {
+        Rule { second_order: false, ..self }
+    }
+
+    // The same two-state rule left partly to chance.
+    pub fn stochastic(self, chances: Chances) -> Result<Self, Error> {
+        if self.states > 2 || self.table.is_some() || self.blocks.is_some() || self.second_order {
+            return Err(Error::UnsupportedRule(format!("{} has no stochastic version", self)));
+        }
+        if chances.birth > CERTAIN || chances.noise > CERTAIN {
+            return Err(Error::Parse("a chance must be from 0 to 1".to_string()));
+        }
+        Ok(Rule { chances: Some(chances), ..self })
+    }
+
+    pub fn chances(&self) -> Option<Chances> {
+        self.chances
+    }
+
+    // Whether chance can change the rule's results, which rules out memoising
+    // them.
+    pub fn is_stochastic(&self) -> bool {
+        self.chances.is_some_and(|chances| chances.birth < CERTAIN || chances.noise > 0)
+    }
+
+    // Whether the next generation follows from each cell and its eight
+    // nearest neighbours being alive or dead alone, so that `is_born` and
+    // `survives` are the whole rule.
+    pub fn is_two_state_nearest(&self) -> bool {
+        self.states == 2
+            && self.table.is_none()
+            && self.blocks.is_none()
+            && self.range.is_none()
+            && !self.second_order
+            && !self.is_stochastic()
+    }
+
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    pub fn range(&self) -> Option<Range> {
+        self.range
+    }
+
+    // How far away a cell's neighbours can be, which is also the furthest
+    // anything can travel in a generation.
+    pub fn radius(&self) -> usize {
+        self.range.map_or(1, |range| range.radius)
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    // The neighbourhoods with `count` live cells, all of them in the rule's
+    // neighbourhood.
+    fn configurations(&self, count: usize) -> impl Iterator<Item = u8> {
+        let mask = self.neighbourhood.mask();
+        with_count(count).filter(move |neighbourhood| neighbourhood & !mask == 0)
+    }
+
+    // Whether the rule depends only on the number of live neighbours.
+    pub fn is_totalistic(&self) -> bool {
+        if self.table.is_some() || self.blocks.is_some() {
+            return false;
+        }
+        if self.range.is_some() {
+            return true;
+        }
+        (0..=self.neighbourhood.size()).all(|count| {
+            let mut neighbourhoods = self.configurations(count);
+            let first = neighbourhoods.next().expect("every count has a neighbourhood");
+            let (birth, survival) = (contains(&self.birth, first), contains(&self.survival, first));
+            neighbourhoods.all(|n| contains(&self.birth, n) == birth && contains(&self.survival, n) == survival)
+        })
+    }
+
+    fn max_count(&self) -> usize {
+        self.range.map_or(self.neighbourhood.size(), |range| range.size())
+    }
+
+    // The neighbour counts for which every neighbourhood gives a birth.
+    pub fn births(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..=self.max_count()).filter(|count| match self.range {
+            Some(range) => range.is_born(*count),
+            None => self.configurations(*count).all(|n| contains(&self.birth, n)),
+        })
+    }
+
+    // The neighbour counts for which every neighbourhood gives survival.
+    pub fn survivals(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..=self.max_count()).filter(|count| match self.range {
+            Some(range) => range.survives(*count),
+            None => self.configurations(*count).all(|n| contains(&self.survival, n)),
+        })
+    }
+
+    // Whether a dead cell comes alive, given its live neighbours as a bit for
+    // each of `NEIGHBOURS`. Neighbours outside the rule's neighbourhood are
+    // ignored.
+    pub fn is_born(&self, neighbourhood: u8) -> bool {
+        contains(&self.birth, neighbourhood & self.neighbourhood.mask())
+    }
+
+    pub fn survives(&self, neighbourhood: u8) -> bool {
+        contains(&self.survival, neighbourhood & self.neighbourhood.mask())
+    }
+
+    // The next state of `cell`, whose live neighbours are the bits set in
+    // `neighbourhood`, ordered as in `NEIGHBOURS`.
+    pub fn next(&self, cell: Cell, neighbourhood: u8) -> Cell {
+        self.apply(cell, self.is_born(neighbourhood), self.survives(neighbourhood))
+    }
+
+    // The next state of `cell` under a Larger than Life rule, given the number
+    // of live cells in its range, not counting itself.
+    pub fn next_counted(&self, cell: Cell, count: usize) -> Cell {
+        let range = self.range.expect("only Larger than Life rules count over a range");
+        self.apply(cell, range.is_born(count), range.survives(count))
+    }
+
+    fn apply(&self, cell: Cell, born: bool, survives: bool) -> Cell {
+        match cell {
+            Cell::Alive if survives => Cell::Alive,
+            Cell::Dead if born => Cell::Alive,
+            Cell::Dead => Cell::Dead,
+            Cell::Alive | Cell::State(_) => self.decay(cell.state()),
+        }
+    }
+
+    // The state after `state` on the way to dying.
+    fn decay(&self, state: u8) -> Cell {
+        if state + 1 < self.states {
+            Cell::State(state + 1)
+        } else {
+            Cell::Dead
+        }
+    }
+}
+
+// Conway's Game of Life, B3/S23.
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::new(&[3], &[2, 3])
+    }
+}
+
+// Writes the conditions in `set` as counts, each followed by the letters it is
+// limited to, or by `-` and the letters it excludes if that is shorter.
+fn write_conditions(f: &mut fmt::Formatter, rule: &Rule, set: &Configurations) -> fmt::Result {
+    for count in 0..=rule.neighbourhood.size() {
+        let letters = if rule.neighbourhood == Neighbourhood::Moore { letters(count) } else { vec![] };
+        if letters.is_empty() {
+            if rule.configurations(count).all(|n| contains(set, n)) {
+                write!(f, "{}", count)?;
+            }
+            continue;
+        }
+        let (with, without): (Vec<_>, Vec<_>) = letters.into_iter().partition(|(_, class)| class.iter().all(|n| contains(set, *n)));
+        if with.is_empty() {
+            continue;
+        }
+        write!(f, "{}", count)?;
+        if without.is_empty() {
+            continue;
+        }
+        let (sign, shown) = if without.len() < with.len() { ("-", without) } else { ("", with) };
+        write!(f, "{}", sign)?;
+        for (letter, _) in shown {
+            write!(f, "{}", letter)?;
+        }
+    }
+    Ok(())
+}
+
+// Generations rules are written survival first with the number of states
+// last, as in `345/2/4`, unless they need Hensel's letters, which only the
+// `B2a/S/C3` form allows. Other neighbourhoods than Moore's are given at the
+// end: `V` for von Neumann's, `H` for hexagonal and `@` with the mask in hex
+// for any other.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(chances) = self.chances {
+            write!(f, "{}", Rule { chances: None, ..*self })?;
+            if chances.birth < CERTAIN {
+                write!(f, ":P{}", chances.birth as f64 / CERTAIN as f64)?;
+            }
+            if chances.noise > 0 {
+                write!(f, ":N{}", chances.noise as f64 / CERTAIN as f64)?;
+            }
+            return write!(f, ":S{}", chances.seed);
+        }
+        if self.second_order {
+            return write!(f, "{}R", self.first_order());
+        }
+        if let Some(table) = self.table() {
+            return write!(f, "{}", table.name);
+        }
+        if let Some(blocks) = self.blocks {
+            let blocks: Vec<String> = blocks.iter().map(|block| block.to_string()).collect();
+            return write!(f, "MS,D{}", blocks.join(";"));
+        }
+        if let Some(range) = self.range {
+            let states = if self.states > 2 { self.states } else { 0 };
+            let shape = match range.shape {
+                Shape::Moore => 'M',
+                Shape::VonNeumann => 'N',
+                Shape::Circular => 'C',
+            };
+            return write!(
+                f,
+                "R{},C{},M{},S{}..{},B{}..{},N{}",
+                range.radius, states, range.middle as u8, range.survival.0, range.survival.1, range.birth.0, range.birth.1, shape
+            );
+        }
+        if self.states > 2 && self.is_totalistic() {
+            write_conditions(f, self, &self.survival)?;
+            write!(f, "/")?;
+            write_conditions(f, self, &self.birth)?;
+            write!(f, "/{}", self.states)?;
+        } else {
+            write!(f, "B")?;
+            write_conditions(f, self, &self.birth)?;
+            write!(f, "/S")?;
+            write_conditions(f, self, &self.survival)?;
+            if self.states > 2 {
+                write!(f, "/C{}", self.states)?;
+            }
+        }
+        match self.neighbourhood {
+            Neighbourhood::Moore => Ok(()),
+            Neighbourhood::VonNeumann => write!(f, "V"),
+            Neighbourhood::Hexagonal => write!(f, "H"),
+            Neighbourhood::Custom(mask) => write!(f, "@{:02X}", mask),
+        }
+    }
+}
+
+// Parses conditions such as `23`, or `2-a3k` in Hensel's notation, where
+// letters after a count limit it to those arrangements of neighbours and a
+// `-` before them excludes them instead.
+fn conditions(text: &str, neighbourhood: Neighbourhood) -> Result<Configurations, Error> {
+    let mut set = [0; 4];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = match c {
+            '0'..='8' if c as usize - '0' as usize <= neighbourhood.size() => c as usize - '0' as usize,
+            '0'..='8' => {
+                return Err(Error::Parse(format!("a cell has only {} neighbours, not {}", neighbourhood.size(), c)));
+            }
+            _ => return Err(Error::Parse(format!("invalid neighbour count '{}'", c))),
+        };
+        let negated = chars.next_if_eq(&'-').is_some();
+        let mut chosen = Vec::new();
+        while let Some(letter) = chars.next_if(char::is_ascii_lowercase) {
+            chosen.push(letter);
+        }
+        if negated && chosen.is_empty() {
+            return Err(Error::Parse(format!("expected letters after '{}-'", count)));
+        }
+        if !chosen.is_empty() && neighbourhood != Neighbourhood::Moore {
+            return Err(Error::Parse("Hensel's letters need the Moore neighbourhood".to_string()));
+        }
+        let letters = letters(count);
+        for letter in &chosen {
+            if !letters.iter().any(|(l, _)| l == letter) {
+                return Err(Error::Parse(format!("'{}{}' is not a neighbourhood", count, letter)));
+            }
+        }
+        if chosen.is_empty() {
+            for neighbourhood in with_count(count) {
+                insert(&mut set, neighbourhood);
+            }
+        }
+        for (letter, class) in letters {
+            if !chosen.is_empty() && chosen.contains(&letter) != negated {
+                for neighbourhood in class {
+                    insert(&mut set, neighbourhood);
+                }
+            }
+        }
+    }
+    Ok(set)
+}
+
+// Parses the `P0.9:N0.001:S7` after a rule, in any order, where each part is
+// optional.
+fn parse_chances(text: &str) -> Result<Chances, Error> {
+    let mut chances = Chances { birth: CERTAIN, noise: 0, seed: 0 };
+    let mut seen = Vec::new();
+    for part in text.split(':') {
+        let mut chars = part.chars();
+        let Some(letter) = chars.next().map(|c| c.to_ascii_uppercase()) else {
+            return Err(Error::Parse(format!("empty part in \"{}\"", text)));
+        };
+        if seen.contains(&letter) {
+            return Err(Error::Parse(format!("{} is given twice in \"{}\"", letter, text)));
+        }
+        seen.push(letter);
+        let value = chars.as_str();
+        match letter {
+            'P' => chances.birth = parse_chance(value)?,
+            'N' => chances.noise = parse_chance(value)?,
+            'S' => chances.seed = value.parse().map_err(|_| Error::Parse(format!("invalid seed \"{}\"", value)))?,
+            _ => return Err(Error::Parse(format!("expected P, N or S in \"{}\"", part))),
+        }
+    }
+    Ok(chances)
+}
+
+fn parse_chance(text: &str) -> Result<u32, Error> {
+    match text.parse::<f64>() {
+        Ok(chance) if (0.0..=1.0).contains(&chance) => Ok((chance * CERTAIN as f64).round() as u32),
+        _ => Err(Error::Parse(format!("a chance must be from 0 to 1, not \"{}\"", text))),
+    }
+}
+
+fn prefixed<'a>(part: &'a str, prefix: char, rule: &str) -> Result<&'a str, Error> {
+    part.strip_prefix([prefix, prefix.to_ascii_lowercase()])
+        .ok_or_else(|| Error::Parse(format!("expected {} in \"{}\"", prefix, rule)))
+}
+
+fn from_conditions(birth: &str, survival: &str, neighbourhood: Neighbourhood) -> Result<Rule, Error> {
+    let rule = Rule {
+        birth: conditions(birth, neighbourhood)?,
+        survival: conditions(survival, neighbourhood)?,
+        ..Rule::default()
+    };
+    Ok(rule.with_neighbourhood(neighbourhood))
+}
+
+// Splits the neighbourhood off the end of a rule.
+fn neighbourhood(s: &str) -> Result<(&str, Neighbourhood), Error> {
+    if let Some((rest, mask)) = s.rsplit_once('@') {
+        let mask = u8::from_str_radix(mask, 16)
+            .ok()
+            .filter(|_| mask.len() == 2)
+            .ok_or_else(|| Error::Parse(format!("invalid neighbourhood mask \"{}\"", mask)))?;
+        return Ok((rest, Neighbourhood::from_mask(mask)));
+    }
+    if let Some(rest) = s.strip_suffix(['V', 'v']) {
+        return Ok((rest, Neighbourhood::VonNeumann));
+    }
+    if let Some(rest) = s.strip_suffix(['H', 'h']) {
+        return Ok((rest, Neighbourhood::Hexagonal));
+    }
+    Ok((s, Neighbourhood::Moore))
+}
+
+// Parses the `345/2/4` and `B2/S345/C4` forms of a Generations rule.
+fn generations(s: &str, neighbourhood: Neighbourhood) -> Result<Rule, Error> {
+    let parts: Vec<&str> = s.split('/').collect();
+    let (birth, survival, states) = if s.starts_with(['B', 'b']) {
+        (prefixed(parts[0], 'B', s)?, prefixed(parts[1], 'S', s)?, prefixed(parts[2], 'C', s)?)
+    } else {
+        (parts[1], parts[0], parts[2])
+    };
+    let states = states
+        .parse()
+        .map_err(|_| Error::Parse(format!("invalid number of states \"{}\"", states)))?;
+    from_conditions(birth, survival, neighbourhood)?.with_states(states)
+}
+
+// Parses a Larger than Life rule such as Bosco's `R5,C0,M1,S34..58,B34..45,NM`:
+// the radius, the number of states, where 0 and 2 both mean an ordinary
+// two-state rule, whether a cell counts itself, the survival and birth
+// ranges, and the shape of the neighbourhood, `M` for Moore, `N` for von
+// Neumann or `C` for circular.
+fn larger_than_life(s: &str) -> Result<Rule, Error> {
+    let fields: Vec<&str> = s.split(',').collect();
+    let [radius, states, middle, survival, birth, shape] = fields[..] else {
+        return Err(Error::Parse(format!("expected six fields in \"{}\"", s)));
+    };
+    let number = |field: &str| {
+        field.parse::<usize>().map_err(|_| Error::Parse(format!("invalid number \"{}\" in \"{}\"", field, s)))
+    };
+    let range = |field: &str| {
+        let (low, high) = field
+            .split_once("..")
+            .ok_or_else(|| Error::Parse(format!("expected a range such as 2..3, not \"{}\"", field)))?;
+        Ok::<_, Error>((number(low)?, number(high)?))
+    };
+    let states = match number(prefixed(states, 'C', s)?)? {
+        0 => 2,
+        states => u8::try_from(states).map_err(|_| Error::Parse(format!("too many states in \"{}\"", s)))?,
+    };
+    let middle = match prefixed(middle, 'M', s)? {
+        "0" => false,
+        "1" => true,
+        other => return Err(Error::Parse(format!("expected M0 or M1, not M{}", other))),
+    };
+    let shape = match prefixed(shape, 'N', s)? {
+        "M" | "m" => Shape::Moore,
+        "N" | "n" => Shape::VonNeumann,
+        "C" | "c" => Shape::Circular,
+        other => return Err(Error::UnsupportedRule(format!("the N{} neighbourhood is not supported", other))),
+    };
+    let range = Range {
+        radius: number(prefixed(radius, 'R', s)?)?,
+        shape,
+        middle,
+        birth: range(prefixed(birth, 'B', s)?)?,
+        survival: range(prefixed(survival, 'S', s)?)?,
+    };
+    Rule::larger_than_life(range, states)
+}
+
+// Parses the new block for each of the 16 blocks, in MCell's `MS,D` notation,
+// such as `MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15` for the billiard ball
+// model.
+fn block(s: &str) -> Result<Rule, Error> {
+    let blocks = s
+        .split(';')
+        .map(|block| block.trim().parse::<u8>().ok().filter(|block| *block < 16))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| Error::Parse(format!("expected blocks from 0 to 15, not \"{}\"", s)))?;
+    let blocks = blocks
+        .try_into()
+        .map_err(|blocks: Vec<u8>| Error::Parse(format!("expected 16 blocks, not {}", blocks.len())))?;
+    Ok(Rule::block(blocks))
+}
+
+// Accepts `B3/S23`, `b3s23` and the older survival-first `23/3`, with or
+// without Hensel's letters, along with the Generations forms `345/2/4` and
+// `B2/S345/C4`, any of them followed by a neighbourhood such as `V`, and
+// Larger than Life rules and block rules. The name of a rule file already
+// loaded also works. A trailing `R`, as in `B3/S23R`, makes a two-state rule
+// second-order, and `:P` with the chance of a birth succeeding, `:N` with the
+// chance of a cell flipping each generation and `:S` with a seed, as in
+// `B3/S23:P0.9:N0.001:S7`, make it stochastic.
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(index) = ruletable::find(s) {
+            return Ok(Rule { states: ruletable::get(index).states, table: Some(index), ..Rule::new(&[], &[]) });
+        }
+        if let Some((rule, chances)) = s.split_once(':') {
+            return rule.parse::<Rule>()?.stochastic(parse_chances(chances)?);
+        }
+        if let Some(rule) = s.strip_suffix('R').filter(|rule| !rule.ends_with('R')) {
+            return rule.parse::<Rule>()?.second_order();
+        }
+        if let Some(blocks) = s.strip_prefix("MS,D") {
+            return block(blocks);
+        }
+        if s.starts_with(['R', 'r']) && s.contains(',') {
+            return larger_than_life(s);
+        }
+        let (s, neighbourhood) = neighbourhood(s)?;
+        if s.matches('/').count() == 2 {
+            return generations(s, neighbourhood);
+        }
+        if let Some(rest) = s.strip_prefix(['B', 'b']) {
+            let (birth, survival) = rest
+                .split_once(['S', 's'])
+                .ok_or_else(|| Error::Parse(format!("missing survival conditions in \"{}\"", s)))?;
+            let birth = birth.strip_suffix('/').unwrap_or(birth);
+            from_conditions(birth, survival, neighbourhood)
+        } else if let Some((survival, birth)) = s.split_once('/') {
+            from_conditions(birth, survival, neighbourhood)
+        } else {
+            Err(Error::Parse(format!("unrecognised rule \"{}\"", s)))
+        }
+    }
+}
@@ -0,0 +1,514 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use crate::error::Error;
+
+// Golly's rule files, which describe a cellular automaton with up to 255
+// states either by a table of transitions (`@TABLE`) or by a decision tree
+// (`@TREE`), with optional colours for the states (`@COLORS`). Tables are
+// registered by name once loaded, so that a `Rule` can refer to one by index
+// and stay `Copy`.
+
+// A set of states, one bit for each of the 256.
+type States = [u64; 4];
+
+fn has(set: &States, state: u8) -> bool {
+    set[state as usize / 64] >> (state % 64) & 1 == 1
+}
+
+fn add(set: &mut States, state: u8) {
+    set[state as usize / 64] |= 1 << (state % 64);
+}
+
+fn single(state: u8) -> States {
+    let mut set = [0; 4];
+    add(&mut set, state);
+    set
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Inputs {
+    Moore,
+    VonNeumann,
+    Hexagonal,
+}
+
+impl Inputs {
+    // The neighbours a table lists after the cell itself, as indices into
+    // `rule::NEIGHBOURS`, which follows the same clockwise order from north.
+    fn positions(self) -> &'static [usize] {
+        match self {
+            Inputs::Moore => &[0, 1, 2, 3, 4, 5, 6, 7],
+            Inputs::VonNeumann => &[0, 2, 4, 6],
+            // the north-east and south-west cells are not neighbours
+            Inputs::Hexagonal => &[0, 2, 3, 4, 6, 7],
+        }
+    }
+
+    // The orders the neighbours can be taken in under a symmetry, each as a
+    // permutation of their positions in the table. `permute` is handled
+    // separately, since it allows every order.
+    fn symmetries(self, name: &str) -> Result<Vec<Vec<usize>>, Error> {
+        let n = self.positions().len();
+        let rotations = |step: usize| -> Vec<Vec<usize>> {
+            (0..n / step).map(|turn| (0..n).map(|i| (i + turn * step) % n).collect()).collect()
+        };
+        let reflected = |orders: Vec<Vec<usize>>| -> Vec<Vec<usize>> {
+            let mut all = orders.clone();
+            all.extend(orders.iter().map(|order| (0..n).map(|i| order[(n - i) % n]).collect()));
+            all
+        };
+        let orders = match (self, name) {
+            (_, "none") => vec![(0..n).collect()],
+            (Inputs::Moore, "rotate4") => rotations(2),
+            (Inputs::Moore, "rotate8") => rotations(1),
+            (Inputs::Moore, "rotate4reflect") => reflected(rotations(2)),
+            (Inputs::Moore, "rotate8reflect") => reflected(rotations(1)),
+            (Inputs::Moore | Inputs::VonNeumann, "reflect_horizontal") => reflected(vec![(0..n).collect()]),
+            (Inputs::VonNeumann, "rotate4") => rotations(1),
+            (Inputs::VonNeumann, "rotate4reflect") => reflected(rotations(1)),
+            (Inputs::Hexagonal, "rotate2") => rotations(3),
+            (Inputs::Hexagonal, "rotate3") => rotations(2),
+            (Inputs::Hexagonal, "rotate6") => rotations(1),
+            (Inputs::Hexagonal, "rotate6reflect") => reflected(rotations(1)),
+            _ => return Err(Error::UnsupportedRule(format!("the {} symmetry is not supported for this neighbourhood", name))),
+        };
+        Ok(orders)
+    }
+}
+
+// One line of a table, with its variables expanded: a set of states for the
+// cell and each of its neighbours, and the state the cell takes if they all
+// match.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Transition {
+    inputs: Vec<States>,
+    output: u8,
+}
+
+impl Transition {
+    fn matches(&self, centre: u8, neighbours: &[u8]) -> bool {
+        has(&self.inputs[0], centre) && self.inputs[1..].iter().zip(neighbours).all(|(set, state)| has(set, *state))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Definition {
+    Table { inputs: Inputs, transitions: Vec<Transition> },
+    // Each node lists, for every state of its input, the node to go to next,
+    // or at the bottom level the resulting state. The root is the last node.
+    Tree { neighbours: usize, nodes: Vec<Vec<usize>> },
+}
+
+pub struct RuleTable {
+    pub name: String,
+    pub states: u8,
+    definition: Definition,
+    colours: Vec<Option<[u8; 3]>>,
+    // whether this is one of `BUILT_IN`, which files of the same name do not
+    // replace
+    built_in: bool,
+    // results already looked up, by the cell and its eight neighbours
+    cache: Mutex<HashMap<[u8; 9], u8>>,
+}
+
+impl RuleTable {
+    // The next state of a cell, given its neighbours in the order of
+    // `rule::NEIGHBOURS`. Cells that no transition matches stay as they are.
+    pub fn next(&self, centre: u8, neighbours: &[u8; 8]) -> u8 {
+        let mut key = [0; 9];
+        key[0] = centre;
+        key[1..].copy_from_slice(neighbours);
+        if let Some(state) = self.cache.lock().unwrap().get(&key) {
+            return *state;
+        }
+        let state = match &self.definition {
+            Definition::Table { inputs, transitions } => {
+                let listed: Vec<u8> = inputs.positions().iter().map(|i| neighbours[*i]).collect();
+                transitions.iter().find(|transition| transition.matches(centre, &listed)).map_or(centre, |transition| transition.output)
+            }
+            Definition::Tree { neighbours: count, nodes } => {
+                // Golly's order: the corners, then the sides, then the cell
+                let order: &[usize] = if *count == 4 { &[0, 6, 2, 4] } else { &[7, 1, 5, 3, 0, 6, 2, 4] };
+                let mut node = nodes.len() - 1;
+                for i in order {
+                    node = nodes[node][neighbours[*i] as usize];
+                }
+                nodes[node][centre as usize] as u8
+            }
+        };
+        self.cache.lock().unwrap().insert(key, state);
+        state
+    }
+
+    // The colour given to `state` in the file, if any.
+    pub fn colour(&self, state: u8) -> Option<[u8; 3]> {
+        self.colours.get(state as usize).copied().flatten()
+    }
+}
+
+lazy_static! {
+    static ref TABLES: Mutex<Vec<Arc<RuleTable>>> = Mutex::new(Vec::new());
+}
+
+// Adds a table to the registry and returns its index. Loading the same file
+// again gives back the index it had, so the registry does not grow. A changed
+// file of the same name gets an index of its own and is what the name finds
+// from then on, while rules already made keep the table they were made with.
+// Built-in tables are kept apart from files of the same name.
+pub fn register(table: RuleTable) -> usize {
+    let mut tables = TABLES.lock().unwrap();
+    let same = |other: &Arc<RuleTable>| {
+        other.name == table.name
+            && other.built_in == table.built_in
+            && other.states == table.states
+            && other.definition == table.definition
+            && other.colours == table.colours
+    };
+    if let Some(index) = tables.iter().position(same) {
+        return index;
+    }
+    tables.push(Arc::new(table));
+    tables.len() - 1
+}
+
+pub fn get(index: usize) -> Arc<RuleTable> {
+    TABLES.lock().unwrap()[index].clone()
+}
+
+// The names of the registered tables, in the order they were loaded.
+pub fn names() -> Vec<String> {
+    TABLES.lock().unwrap().iter().map(|table| table.name.clone()).collect()
+}
+
+// WireWorld, for building circuits: electrons made of a head (1) and a tail
+// (2) run along copper (3), which becomes a head next to one or two heads.
+pub const WIREWORLD: &str = "@RULE WireWorld
+
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,1,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+var n={0,2,3}
+var o={0,2,3}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,i,j,k,l,m,n,o,1
+3,1,1,i,j,k,l,m,n,1
+
+@COLORS
+1 0 64 255
+2 255 64 0
+3 255 200 0
+";
+
+// Rule files that can be used by name without loading them first.
+const BUILT_IN: &[&str] = &[WIREWORLD];
+
+// The table most recently loaded as `name`, or else the built-in one.
+pub fn find(name: &str) -> Option<usize> {
+    let loaded = TABLES.lock().unwrap().iter().rposition(|table| table.name == name && !table.built_in);
+    loaded.or_else(|| built_in(name))
+}
+
+// The built-in table called `name`, even if a file of that name was loaded.
+pub fn built_in(name: &str) -> Option<usize> {
+    let file = BUILT_IN.iter().find(|file| file.lines().next() == Some(&format!("@RULE {}", name)))?;
+    let table = RuleTable { built_in: true, ..parse(file).expect("built-in rule files are valid") };
+    Some(register(table))
+}
+
+fn state(token: &str, states: usize) -> Result<u8, Error> {
+    token
+        .parse::<usize>()
+        .ok()
+        .filter(|state| *state < states)
+        .map(|state| state as u8)
+        .ok_or_else(|| Error::Parse(format!("invalid state \"{}\"", token)))
+}
+
+// Splits a transition into its entries. Tables with fewer than 11 states may
+// leave out the commas when every entry is a single character.
+fn entries(line: &str) -> Vec<String> {
+    if !line.contains(',') {
+        return line.chars().filter(|c| !c.is_whitespace()).map(String::from).collect();
+    }
+    let mut entries = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    for c in line.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                entries.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    entries.push(current.trim().to_string());
+    entries
+}
+
+// The states of an entry: a state, a variable, or a list of either in braces.
+fn resolve(entry: &str, variables: &HashMap<String, States>, states: usize) -> Result<States, Error> {
+    if let Some(list) = entry.strip_prefix('{').and_then(|rest| rest.strip_suffix('}')) {
+        let mut set = [0; 4];
+        for item in list.split(',') {
+            let items = resolve(item.trim(), variables, states)?;
+            for (word, bits) in set.iter_mut().zip(items) {
+                *word |= bits;
+            }
+        }
+        return Ok(set);
+    }
+    match variables.get(entry) {
+        Some(set) => Ok(*set),
+        None => Ok(single(state(entry, states)?)),
+    }
+}
+
+// Every distinct order of `sets`, for the `permute` symmetry.
+fn permutations(sets: &[States]) -> Vec<Vec<States>> {
+    let mut order: Vec<States> = sets.to_vec();
+    order.sort();
+    let mut all = vec![order.clone()];
+    // step through the orders lexicographically, so repeats are skipped
+    loop {
+        let Some(i) = (0..order.len().saturating_sub(1)).rev().find(|i| order[*i] < order[i + 1]) else {
+            return all;
+        };
+        let j = (i + 1..order.len()).rev().find(|j| order[i] < order[*j]).expect("order[i + 1] is larger");
+        order.swap(i, j);
+        order[i + 1..].reverse();
+        all.push(order.clone());
+    }
+}
+
+fn parse_table(lines: &[&str]) -> Result<(usize, Definition), Error> {
+    let mut states = None;
+    let mut inputs = Inputs::Moore;
+    let mut symmetry = "none".to_string();
+    let mut variables: HashMap<String, States> = HashMap::new();
+    let mut transitions = Vec::new();
+    for line in lines {
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "n_states" | "num_states" => {
+                    let count = value.parse::<usize>().ok().filter(|count| (2..=255).contains(count));
+                    states = Some(count.ok_or_else(|| Error::Parse(format!("invalid number of states \"{}\"", value)))?);
+                }
+                "neighborhood" => {
+                    inputs = match value {
+                        "Moore" => Inputs::Moore,
+                        "vonNeumann" => Inputs::VonNeumann,
+                        "hexagonal" => Inputs::Hexagonal,
+                        _ => return Err(Error::UnsupportedRule(format!("the {} neighbourhood is not supported", value))),
+                    }
+                }
+                "symmetries" => symmetry = value.to_string(),
+                _ => return Err(Error::Parse(format!("unknown setting \"{}\"", key.trim()))),
+            }
+            continue;
+        }
+        let states = states.ok_or_else(|| Error::Parse("n_states must come before the transitions".to_string()))?;
+        if let Some(declaration) = line.strip_prefix("var ") {
+            let (name, values) = declaration
+                .split_once('=')
+                .ok_or_else(|| Error::Parse(format!("expected var name={{...}}, not \"{}\"", line)))?;
+            let set = resolve(values.trim(), &variables, states)?;
+            variables.insert(name.trim().to_string(), set);
+            continue;
+        }
+        transitions.extend(expand(line, &variables, states, inputs, &symmetry)?);
+    }
+    let states = states.ok_or_else(|| Error::Parse("the table has no n_states".to_string()))?;
+    Ok((states, Definition::Table { inputs, transitions }))
+}
+
+// Turns one transition line into transitions without variables or symmetry.
+// A variable used more than once in a line stands for the same state each
+// time, so those are expanded into one transition for each of their states.
+fn expand(line: &str, variables: &HashMap<String, States>, states: usize, inputs: Inputs, symmetry: &str) -> Result<Vec<Transition>, Error> {
+    let entries = entries(line);
+    if entries.len() != inputs.positions().len() + 2 {
+        return Err(Error::Parse(format!("expected {} entries in \"{}\"", inputs.positions().len() + 2, line)));
+    }
+    let mut uses: BTreeMap<&str, usize> = BTreeMap::new();
+    for entry in &entries {
+        if variables.contains_key(entry.as_str()) {
+            *uses.entry(entry.as_str()).or_default() += 1;
+        }
+    }
+    let bound: Vec<&str> = uses.into_iter().filter(|(_, count)| *count > 1).map(|(name, _)| name).collect();
+
+    let mut bindings: Vec<HashMap<&str, u8>> = vec![HashMap::new()];
+    for name in &bound {
+        let values: Vec<u8> = (0..states).map(|state| state as u8).filter(|state| has(&variables[*name], *state)).collect();
+        bindings = bindings
+            .into_iter()
+            .flat_map(|binding| {
+                values.iter().map(move |value| {
+                    let mut binding = binding.clone();
+                    binding.insert(*name, *value);
+                    binding
+                })
+            })
+            .collect();
+    }
+
+    // the orders of a line often repeat transitions, up to 8! of them under
+    // `permute`, so the ones already made are kept in a set as well
+    let mut transitions = Vec::new();
+    let mut made = HashSet::new();
+    for binding in bindings {
+        let mut sets = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            sets.push(match binding.get(entry.as_str()) {
+                Some(value) => single(*value),
+                None => resolve(entry, variables, states)?,
+            });
+        }
+        let output = sets.pop().expect("checked above");
+        let output = (0..states)
+            .map(|state| state as u8)
+            .find(|state| has(&output, *state) && output == single(*state))
+            .ok_or_else(|| Error::Parse(format!("the result in \"{}\" must be a single state", line)))?;
+
+        let neighbours = &sets[1..];
+        let orders: Vec<Vec<States>> = if symmetry == "permute" {
+            permutations(neighbours)
+        } else {
+            inputs.symmetries(symmetry)?.iter().map(|order| order.iter().map(|i| neighbours[*i]).collect()).collect()
+        };
+        for order in orders {
+            let mut inputs = vec![sets[0]];
+            inputs.extend(order);
+            let transition = Transition { inputs, output };
+            if made.insert(transition.clone()) {
+                transitions.push(transition);
+            }
+        }
+    }
+    Ok(transitions)
+}
+
+fn parse_tree(lines: &[&str]) -> Result<(usize, Definition), Error> {
+    let mut settings: HashMap<&str, usize> = HashMap::new();
+    let mut nodes: Vec<Vec<usize>> = Vec::new();
+    let mut levels: Vec<usize> = Vec::new();
+    let invalid = |line: &str| Error::Parse(format!("invalid tree line \"{}\"", line));
+    for line in lines {
+        if let Some((key, value)) = line.split_once('=') {
+            settings.insert(key.trim(), value.trim().parse().map_err(|_| invalid(line))?);
+            continue;
+        }
+        let numbers: Vec<usize> = line.split_whitespace().map(|n| n.parse().map_err(|_| invalid(line))).collect::<Result<_, _>>()?;
+        let (&level, children) = numbers.split_first().ok_or_else(|| invalid(line))?;
+        // children are results at the bottom level, and earlier nodes one
+        // level down above it
+        let valid = children.iter().all(|child| if level == 1 { *child < 256 } else { *child < nodes.len() && levels[*child] == level - 1 });
+        if level == 0 || !valid {
+            return Err(invalid(line));
+        }
+        levels.push(level);
+        nodes.push(children.to_vec());
+    }
+    let setting = |key: &str| settings.get(key).copied().ok_or_else(|| Error::Parse(format!("the tree has no {}", key)));
+    let states = setting("num_states")?;
+    let neighbours = setting("num_neighbors")?;
+    if !(2..=255).contains(&states) {
+        return Err(Error::Parse(format!("invalid number of states {}", states)));
+    }
+    if neighbours != 4 && neighbours != 8 {
+        return Err(Error::UnsupportedRule(format!("trees with {} neighbours are not supported", neighbours)));
+    }
+    if nodes.len() != setting("num_nodes")? || nodes.iter().any(|children| children.len() != states) {
+        return Err(Error::Parse("the tree's nodes do not match num_nodes and num_states".to_string()));
+    }
+    if levels.last() != Some(&(neighbours + 1)) || nodes.iter().zip(&levels).any(|(children, level)| *level == 1 && children.iter().any(|state| *state >= states)) {
+        return Err(Error::Parse("the tree does not end in a root node with valid states".to_string()));
+    }
+    Ok((states, Definition::Tree { neighbours, nodes }))
+}
+
+// Colours are given one state to a line as `state red green blue`, or as a
+// gradient across the live states as `red green blue red green blue`.
+fn parse_colours(lines: &[&str], states: usize) -> Result<Vec<Option<[u8; 3]>>, Error> {
+    let mut colours = vec![None; states];
+    for line in lines {
+        let numbers: Vec<u8> = line
+            .split_whitespace()
+            .map(|n| n.parse().map_err(|_| Error::Parse(format!("invalid colour line \"{}\"", line))))
+            .collect::<Result<_, _>>()?;
+        match numbers[..] {
+            [state, r, g, b] if (state as usize) < states => colours[state as usize] = Some([r, g, b]),
+            [r1, g1, b1, r2, g2, b2] => {
+                for (i, colour) in colours[1..].iter_mut().enumerate() {
+                    let t = if states > 2 { i as f64 / (states - 2) as f64 } else { 0.0 };
+                    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+                    *colour = Some([mix(r1, r2), mix(g1, g2), mix(b1, b2)]);
+                }
+            }
+            _ => return Err(Error::Parse(format!("invalid colour line \"{}\"", line))),
+        }
+    }
+    Ok(colours)
+}
+
+// Parses a `.rule` file. Sections other than `@RULE`, `@TABLE`, `@TREE` and
+// `@COLORS`, such as `@ICONS`, are skipped.
+pub fn parse(text: &str) -> Result<RuleTable, Error> {
+    let mut sections: HashMap<String, Vec<&str>> = HashMap::new();
+    let mut name = None;
+    let mut current = None;
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('@') {
+            let (section, rest) = header.split_once(char::is_whitespace).unwrap_or((header, ""));
+            if section == "RULE" {
+                name = Some(rest.trim().to_string());
+            }
+            current = Some(section.to_string());
+            sections.entry(section.to_string()).or_default();
+            continue;
+        }
+        if let Some(section) = &current {
+            sections.get_mut(section).expect("added with its header").push(line);
+        }
+    }
+    let name = name.filter(|name| !name.is_empty()).ok_or_else(|| Error::Parse("the file has no @RULE name".to_string()))?;
+    let (states, definition) = match (sections.get("TABLE"), sections.get("TREE")) {
+        (Some(lines), _) => parse_table(lines)?,
+        (None, Some(lines)) => parse_tree(lines)?,
+        (None, None) => return Err(Error::Parse("the file has no @TABLE or @TREE".to_string())),
+    };
+    let colours = parse_colours(sections.get("COLORS").map_or(&[][..], |lines| lines), states)?;
+    Ok(RuleTable {
+        name,
+        states: states as u8,
+        definition,
+        colours,
+        built_in: false,
+        cache: Mutex::new(HashMap::new()),
+    })
+}
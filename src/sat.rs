@@ -0,0 +1,417 @@
+use std::collections::BinaryHeap;
+use std::fmt::Write;
+use crate::error::Error;
+
+// A literal as in DIMACS: variable v, counting from 1, is `v` and its
+// negation `-v`.
+pub type Literal = i32;
+
+// A formula in conjunctive normal form: every clause must have a true
+// literal.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cnf {
+    pub variables: usize,
+    pub clauses: Vec<Vec<Literal>>,
+}
+
+impl Cnf {
+    pub fn new() -> Self {
+        Cnf::default()
+    }
+
+    // A fresh variable, as its positive literal.
+    pub fn variable(&mut self) -> Literal {
+        self.variables += 1;
+        self.variables as Literal
+    }
+
+    pub fn add(&mut self, clause: Vec<Literal>) {
+        assert!(!clause.contains(&0), "0 is not a literal");
+        let highest = clause.iter().map(|literal| literal.unsigned_abs() as usize).max().unwrap_or(0);
+        self.variables = self.variables.max(highest);
+        self.clauses.push(clause);
+    }
+
+    pub fn satisfied_by(&self, assignment: &[bool]) -> bool {
+        self.clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|literal| assignment.get(literal.unsigned_abs() as usize - 1) == Some(&(*literal > 0)))
+        })
+    }
+
+    // The DIMACS CNF format read by other solvers, with `comments` as `c`
+    // lines at the top.
+    pub fn to_dimacs(&self, comments: &[String]) -> String {
+        let mut text = String::new();
+        for comment in comments {
+            writeln!(text, "c {}", comment).unwrap();
+        }
+        writeln!(text, "p cnf {} {}", self.variables, self.clauses.len()).unwrap();
+        for clause in &self.clauses {
+            for literal in clause {
+                write!(text, "{} ", literal).unwrap();
+            }
+            writeln!(text, "0").unwrap();
+        }
+        text
+    }
+
+    pub fn from_dimacs(text: &str) -> Result<Cnf, Error> {
+        let mut cnf = Cnf::new();
+        let mut header = None;
+        let mut clause = vec![];
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('c') {
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix('p') {
+                let fields: Vec<&str> = rest.split_whitespace().collect();
+                let [format, variables, clauses] = fields[..] else {
+                    return Err(Error::Parse(format!("invalid DIMACS header \"{}\"", line)));
+                };
+                let number = |field: &str| field.parse::<usize>().map_err(|_| Error::Parse(format!("invalid DIMACS header \"{}\"", line)));
+                if format != "cnf" {
+                    return Err(Error::Parse(format!("expected a cnf problem, not \"{}\"", format)));
+                }
+                header = Some((number(variables)?, number(clauses)?));
+                continue;
+            }
+            for field in line.split_whitespace() {
+                let literal: Literal = field.parse().map_err(|_| Error::Parse(format!("invalid literal \"{}\"", field)))?;
+                if literal == 0 {
+                    cnf.add(std::mem::take(&mut clause));
+                } else {
+                    clause.push(literal);
+                }
+            }
+        }
+        if !clause.is_empty() {
+            cnf.add(clause);
+        }
+        let (variables, clauses) = header.ok_or_else(|| Error::Parse("missing DIMACS header".to_string()))?;
+        if cnf.variables > variables || cnf.clauses.len() != clauses {
+            return Err(Error::Parse(format!(
+                "the header promises {} variables and {} clauses, but there are {} and {}",
+                variables,
+                clauses,
+                cnf.variables,
+                cnf.clauses.len()
+            )));
+        }
+        cnf.variables = variables;
+        Ok(cnf)
+    }
+}
+
+// Inside the solver, variable v is 0-based and its literals are 2v, for v
+// being true, and 2v + 1.
+type Lit = usize;
+
+fn lit(literal: Literal) -> Lit {
+    2 * (literal.unsigned_abs() as usize - 1) + (literal < 0) as usize
+}
+
+fn var(lit: Lit) -> usize {
+    lit / 2
+}
+
+fn not(lit: Lit) -> Lit {
+    lit ^ 1
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Value {
+    True,
+    False,
+    Unset,
+}
+
+// Conflicts before the first restart; later restarts follow the Luby
+// sequence in multiples of this.
+const RESTART_INTERVAL: usize = 100;
+const ACTIVITY_DECAY: f64 = 0.95;
+
+// Term i, counting from 0, of the Luby sequence 1, 1, 2, 1, 1, 2, 4, ...
+fn luby(mut i: usize) -> usize {
+    let mut size = 1;
+    while size < i + 1 {
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size /= 2;
+        i %= size;
+    }
+    size.div_ceil(2)
+}
+
+// A conflict-driven clause learning solver: unit propagation with two
+// watched literals per clause, a learnt clause at the first unique
+// implication point of each conflict, variables chosen by decaying activity
+// with their last value, and restarts.
+struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    // the clauses watching each literal, looked at when it becomes false
+    watches: Vec<Vec<usize>>,
+    values: Vec<Value>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    // where each decision level starts on the trail
+    decisions: Vec<usize>,
+    propagated: usize,
+    activity: Vec<f64>,
+    increment: f64,
+    // the value each variable last had, which new decisions reuse
+    phases: Vec<bool>,
+    order: BinaryHeap<(u64, usize)>,
+    seen: Vec<bool>,
+}
+
+impl Solver {
+    fn new(variables: usize) -> Self {
+        Solver {
+            clauses: vec![],
+            watches: vec![vec![]; 2 * variables],
+            values: vec![Value::Unset; variables],
+            levels: vec![0; variables],
+            reasons: vec![None; variables],
+            trail: vec![],
+            decisions: vec![],
+            propagated: 0,
+            activity: vec![0.0; variables],
+            increment: 1.0,
+            phases: vec![false; variables],
+            order: (0..variables).map(|v| (0, v)).collect(),
+            seen: vec![false; variables],
+        }
+    }
+
+    fn value(&self, lit: Lit) -> Value {
+        match (self.values[var(lit)], lit & 1) {
+            (Value::Unset, _) => Value::Unset,
+            (Value::True, 0) | (Value::False, 1) => Value::True,
+            _ => Value::False,
+        }
+    }
+
+    fn level(&self) -> usize {
+        self.decisions.len()
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let v = var(lit);
+        self.values[v] = if lit & 1 == 0 { Value::True } else { Value::False };
+        self.levels[v] = self.level();
+        self.reasons[v] = reason;
+        self.trail.push(lit);
+    }
+
+    // Adds a clause of at least two literals, watching the first two.
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let index = self.clauses.len();
+        self.watches[clause[0]].push(index);
+        self.watches[clause[1]].push(index);
+        self.clauses.push(clause);
+        index
+    }
+
+    // Assigns every literal implied by the trail, or returns a clause that
+    // has become false.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let falsified = not(self.trail[self.propagated]);
+            self.propagated += 1;
+            let watching = std::mem::take(&mut self.watches[falsified]);
+            let mut kept = Vec::with_capacity(watching.len());
+            let mut conflict = None;
+            for (i, &index) in watching.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watching[i..]);
+                    break;
+                }
+                // the falsified literal goes second, so the first is the
+                // other watch
+                if self.clauses[index][0] == falsified {
+                    self.clauses[index].swap(0, 1);
+                }
+                let first = self.clauses[index][0];
+                if self.value(first) == Value::True {
+                    kept.push(index);
+                    continue;
+                }
+                let replacement = (2..self.clauses[index].len()).find(|&k| self.value(self.clauses[index][k]) != Value::False);
+                if let Some(k) = replacement {
+                    self.clauses[index].swap(1, k);
+                    let watch = self.clauses[index][1];
+                    self.watches[watch].push(index);
+                    continue;
+                }
+                kept.push(index);
+                match self.value(first) {
+                    Value::False => conflict = Some(index),
+                    _ => self.assign(first, Some(index)),
+                }
+            }
+            self.watches[falsified] = kept;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, v: usize) {
+        self.activity[v] += self.increment;
+        if self.activity[v] > 1e100 {
+            for activity in self.activity.iter_mut() {
+                *activity *= 1e-100;
+            }
+            self.increment *= 1e-100;
+            self.order = (0..self.activity.len()).map(|v| (self.activity[v].to_bits(), v)).collect();
+        }
+        self.order.push((self.activity[v].to_bits(), v));
+    }
+
+    // The clause learnt from `conflict`, with the literal that it asserts
+    // first, and the level to go back to.
+    fn analyse(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let mut learnt = vec![0];
+        let mut pending = 0;
+        let mut clause = conflict;
+        let mut asserted = None;
+        let mut index = self.trail.len();
+        loop {
+            // a reason clause starts with the literal it implied
+            let skip = asserted.is_some() as usize;
+            for k in skip..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                let v = var(lit);
+                if self.seen[v] || self.levels[v] == 0 {
+                    continue;
+                }
+                self.seen[v] = true;
+                self.bump(v);
+                if self.levels[v] == self.level() {
+                    pending += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+            loop {
+                index -= 1;
+                if self.seen[var(self.trail[index])] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            self.seen[var(lit)] = false;
+            pending -= 1;
+            if pending == 0 {
+                learnt[0] = not(lit);
+                break;
+            }
+            asserted = Some(lit);
+            clause = self.reasons[var(lit)].expect("only decisions have no reason, and there is one per level");
+        }
+        for lit in &learnt[1..] {
+            self.seen[var(*lit)] = false;
+        }
+        // the literal from the highest remaining level is watched second
+        let mut back = 0;
+        if learnt.len() > 1 {
+            let highest = (1..learnt.len()).max_by_key(|k| self.levels[var(learnt[*k])]).expect("there is a second literal");
+            learnt.swap(1, highest);
+            back = self.levels[var(learnt[1])];
+        }
+        self.increment /= ACTIVITY_DECAY;
+        (learnt, back)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.level() <= level {
+            return;
+        }
+        let start = self.decisions[level];
+        for lit in self.trail.drain(start..) {
+            let v = var(lit);
+            self.phases[v] = lit & 1 == 0;
+            self.values[v] = Value::Unset;
+            self.reasons[v] = None;
+            self.order.push((self.activity[v].to_bits(), v));
+        }
+        self.decisions.truncate(level);
+        self.propagated = self.trail.len();
+    }
+
+    fn decide(&mut self) -> Option<Lit> {
+        while let Some((_, v)) = self.order.pop() {
+            if self.values[v] == Value::Unset {
+                return Some(2 * v + !self.phases[v] as usize);
+            }
+        }
+        None
+    }
+
+    fn solve(&mut self) -> bool {
+        let mut restarts = 0;
+        let mut conflicts = 0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.level() == 0 {
+                    return false;
+                }
+                conflicts += 1;
+                let (learnt, back) = self.analyse(conflict);
+                self.backtrack(back);
+                let asserting = learnt[0];
+                if learnt.len() == 1 {
+                    self.assign(asserting, None);
+                } else {
+                    let index = self.attach(learnt);
+                    self.assign(asserting, Some(index));
+                }
+                continue;
+            }
+            if conflicts >= RESTART_INTERVAL * luby(restarts) {
+                restarts += 1;
+                conflicts = 0;
+                self.backtrack(0);
+            }
+            let Some(lit) = self.decide() else {
+                return true;
+            };
+            self.decisions.push(self.trail.len());
+            self.assign(lit, None);
+        }
+    }
+}
+
+// A satisfying assignment, with element v - 1 the value of variable v, or
+// `None` if there is none.
+pub fn solve(cnf: &Cnf) -> Option<Vec<bool>> {
+    let mut solver = Solver::new(cnf.variables);
+    for clause in &cnf.clauses {
+        let mut lits: Vec<Lit> = clause.iter().map(|literal| lit(*literal)).collect();
+        lits.sort_unstable();
+        lits.dedup();
+        if lits.windows(2).any(|pair| pair[1] == not(pair[0])) {
+            continue;
+        }
+        match lits[..] {
+            [] => return None,
+            [only] => match solver.value(only) {
+                Value::False => return None,
+                Value::True => {}
+                Value::Unset => solver.assign(only, None),
+            },
+            _ => {
+                solver.attach(lits);
+            }
+        }
+    }
+    if !solver.solve() {
+        return None;
+    }
+    Some(solver.values.iter().map(|value| *value == Value::True).collect())
+}
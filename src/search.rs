@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use crate::analysis::{self, Classification};
+use crate::apgcode;
+use crate::error::Error;
+use crate::objects::{self, Grouping};
+use crate::pattern::{self, Pattern};
+use crate::rule::{Rule, NEIGHBOURS};
+use crate::universe::Cell;
+
+// Each cell's phases are the bits of a byte.
+pub const MAX_PERIOD: usize = 8;
+
+#[derive(Clone, Debug)]
+pub struct SearchOptions {
+    pub rule: Rule,
+    // Every phase must fit in this box.
+    pub width: usize,
+    pub height: usize,
+    // 1 for still lifes.
+    pub period: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions { rule: Rule::default(), width: 4, height: 4, period: 1 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Found {
+    pub code: String,
+    pub pattern: Pattern,
+}
+
+// A backtracking search over the cells of the box in raster order, where a
+// cell holds its state in every phase at once. As soon as the last cell
+// around some cell is set, the rule is checked there for every phase, which
+// includes the cells just outside the box that must stay dead.
+struct Search {
+    width: usize,
+    period: usize,
+    // for each neighbourhood byte, whether a dead cell is born and whether a
+    // live one survives
+    born: [bool; 256],
+    survives: [bool; 256],
+    // The phases of each cell of the box with a border of dead cells.
+    cells: Vec<u8>,
+    // The cells, in bordered coordinates, to check once each cell of the
+    // box is set.
+    checks: Vec<Vec<(usize, usize)>>,
+    candidates: Vec<Vec<u8>>,
+}
+
+impl Search {
+    fn new(options: &SearchOptions) -> Self {
+        let (width, height) = (options.width, options.height);
+        let mut checks = vec![vec![]; width * height];
+        for y in 0..height + 2 {
+            for x in 0..width + 2 {
+                // the cell of the box around (x, y) that comes last in
+                // raster order, in box coordinates
+                checks[x.min(width - 1) + y.min(height - 1) * width].push((x, y));
+            }
+        }
+        Search {
+            width,
+            period: options.period,
+            born: std::array::from_fn(|n| options.rule.is_born(n as u8)),
+            survives: std::array::from_fn(|n| options.rule.survives(n as u8)),
+            cells: vec![0; (width + 2) * (height + 2)],
+            checks,
+            candidates: vec![],
+        }
+    }
+
+    // Whether the cell at (x, y) of the bordered grid follows the rule in
+    // every phase.
+    fn consistent(&self, x: usize, y: usize) -> bool {
+        let stride = self.width + 2;
+        let cell = self.cells[x + y * stride];
+        (0..self.period).all(|phase| {
+            let mut neighbourhood = 0u8;
+            for (i, (dx, dy)) in NEIGHBOURS.iter().enumerate() {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if nx < 0 || ny < 0 || nx as usize >= stride || ny as usize * stride >= self.cells.len() {
+                    continue;
+                }
+                neighbourhood |= (self.cells[nx as usize + ny as usize * stride] >> phase & 1) << i;
+            }
+            let next = if cell >> phase & 1 == 1 { self.survives[neighbourhood as usize] } else { self.born[neighbourhood as usize] };
+            next == (cell >> ((phase + 1) % self.period) & 1 == 1)
+        })
+    }
+
+    // Sets cell `index` of the box and goes on to the next. `mirrored` says
+    // whether the rows so far already compare below their mirror images.
+    fn extend(&mut self, index: usize, mirrored: bool) {
+        let (width, stride) = (self.width, self.width + 2);
+        let (x, y) = (index % width, index / width);
+        for phases in 0..1u16 << self.period {
+            self.cells[x + 1 + (y + 1) * stride] = phases as u8;
+            if !self.checks[index].iter().all(|&(cx, cy)| self.consistent(cx, cy)) {
+                continue;
+            }
+            let row = &self.cells[1 + (y + 1) * stride..1 + width + (y + 1) * stride];
+            let mut mirrored = mirrored;
+            if x == width - 1 {
+                // patterns that do not touch the top of the box are found
+                // again higher up
+                if y == 0 && row.iter().all(|phases| *phases == 0) {
+                    continue;
+                }
+                // of a pattern and its mirror image, only the one whose rows
+                // compare lower is kept
+                if !mirrored {
+                    match row.iter().cmp(row.iter().rev()) {
+                        std::cmp::Ordering::Greater => continue,
+                        std::cmp::Ordering::Less => mirrored = true,
+                        std::cmp::Ordering::Equal => {}
+                    }
+                }
+            }
+            if index + 1 == self.checks.len() {
+                self.candidates.push(self.cells.clone());
+            } else {
+                self.extend(index + 1, mirrored);
+            }
+        }
+        self.cells[x + 1 + (y + 1) * stride] = 0;
+    }
+}
+
+// Every still life (for a period of 1) or oscillator of exactly
+// `options.period` under `options.rule` whose phases all fit in the box, up
+// to rotation, reflection and phase, in order of population then apgcode.
+pub fn search(options: &SearchOptions) -> Result<Vec<Found>, Error> {
+    let rule = &options.rule;
+    if !rule.is_two_state_nearest() {
+        return Err(Error::UnsupportedRule(format!("{} is not a two-state rule on the eight nearest neighbours", rule)));
+    }
+    if options.width == 0 || options.height == 0 || !(1..=MAX_PERIOD).contains(&options.period) {
+        return Err(Error::InvalidDimensions(format!(
+            "cannot search a {}x{} box for period {}: periods go from 1 to {}",
+            options.width, options.height, options.period, MAX_PERIOD
+        )));
+    }
+    let mut search = Search::new(options);
+    search.extend(0, false);
+
+    let stride = options.width + 2;
+    let side = options.width.max(options.height) + 2;
+    let mut found = BTreeMap::new();
+    for candidate in search.candidates {
+        // phase 0, in a square grid around the bordered box
+        let cells: Vec<Cell> = (0..side * side)
+            .map(|i| {
+                let (x, y) = (i % side, i / side);
+                let phases = if x < stride && y < options.height + 2 { candidate[x + y * stride] } else { 0 };
+                if phases & 1 == 1 { Cell::Alive } else { Cell::Dead }
+            })
+            .collect();
+        if pattern::bounds(&cells, side).is_none() {
+            continue;
+        }
+        let pattern = Pattern::from_grid(&cells, side);
+        // the engine confirms the period, which rules out smaller ones
+        let classification = analysis::classify(&pattern, rule, options.period)?.classification;
+        let expected = match options.period {
+            1 => Classification::StillLife,
+            period => Classification::Oscillator { period },
+        };
+        if classification != expected {
+            continue;
+        }
+        // pseudo objects, such as two blocks side by side, are made of
+        // islands that would each do the same alone, which shows within a
+        // period; parts whose cells touch are one island, so they stay whole
+        if objects::objects(&cells, side, 1, rule, options.period, Grouping::Interacting)?.len() > 1 {
+            continue;
+        }
+        // an oscillator turns up in each of its phases, so the sparsest is kept
+        if let Some(code) = apgcode::encode_as(&pattern, rule, classification)? {
+            let kept = found.entry(code).or_insert_with(|| pattern.clone());
+            if pattern.population() < kept.population() {
+                *kept = pattern;
+            }
+        }
+    }
+    let mut found: Vec<Found> = found.into_iter().map(|(code, pattern)| Found { code, pattern }).collect();
+    found.sort_by_key(|found| found.pattern.population());
+    Ok(found)
+}
+
+// The results as a series of RLE patterns, each named by its apgcode.
+pub fn report(found: &[Found], options: &SearchOptions) -> String {
+    let kind = if options.period == 1 { "still life".to_string() } else { format!("p{} oscillator", options.period) };
+    let mut report = String::new();
+    for Found { code, pattern } in found {
+        report.push_str(&format!("#N {}\n#C {} found in a {}x{} box\n", code, kind, options.width, options.height));
+        report.push_str(&pattern.to_rle(&options.rule));
+    }
+    report
+}
@@ -0,0 +1,781 @@
+use std::{fmt, str::FromStr};
+use crate::error::Error;
+use crate::node::{self, CacheStats, Node};
+use crate::reference;
+use crate::rule::{Blocks, Chances, Range, Rule};
+use crate::ruletable::RuleTable;
+use crate::universe::Cell;
+
+// Engines advance a square grid of `side() * side()` cells, stored row-major.
+// Cells outside the grid are treated as permanently dead.
+pub trait Stepper {
+    fn kind(&self) -> StepperKind;
+    fn rule(&self) -> Rule;
+    fn side(&self) -> usize;
+    fn load(&mut self, cells: &[Cell]) -> Result<(), Error>;
+    fn step(&mut self, generations: usize);
+    fn cells(&self) -> Vec<Cell>;
+    fn get(&self, x: usize, y: usize) -> Cell;
+    fn set(&mut self, x: usize, y: usize, cell: Cell);
+    fn population(&self) -> usize;
+    // How many generations have been stepped, which block rules use to know
+    // which way the grid is divided into blocks. It counts down while time is
+    // reversed, stopping at 0.
+    fn generation(&self) -> usize;
+    fn set_generation(&mut self, generation: usize);
+    fn is_reversed(&self) -> bool;
+    fn set_reversed(&mut self, reversed: bool);
+    // The generation before the current one, which second-order rules XOR
+    // into the next. Other rules do not keep it, and loading clears it.
+    fn previous(&self) -> Vec<Cell>;
+    fn load_previous(&mut self, cells: &[Cell]) -> Result<(), Error>;
+
+    // Swaps the current and previous generations. Under a second-order rule
+    // the next step then gives the generation before the previous one, so
+    // stepping on retraces the run backwards.
+    fn reverse(&mut self) -> Result<(), Error> {
+        let (previous, current) = (self.previous(), self.cells());
+        self.load(&previous)?;
+        self.load_previous(&current)?;
+        let reversed = !self.is_reversed();
+        self.set_reversed(reversed);
+        // the grid has gone a generation the new way already
+        let generation = self.generation();
+        self.set_generation(if reversed { generation.saturating_sub(1) } else { generation + 1 });
+        Ok(())
+    }
+
+    // Another engine that is likely to run the current pattern faster, if any.
+    fn suggestion(&self) -> Option<StepperKind> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StepperKind {
+    Quadtree,
+    Dense,
+}
+
+impl StepperKind {
+    pub const ALL: [StepperKind; 2] = [StepperKind::Quadtree, StepperKind::Dense];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StepperKind::Quadtree => "quadtree",
+            StepperKind::Dense => "dense",
+        }
+    }
+
+    pub fn create(&self, side: usize, rule: Rule) -> Result<Box<dyn Stepper>, Error> {
+        Ok(match self {
+            StepperKind::Quadtree => Box::new(QuadtreeStepper::new(side, rule)?),
+            StepperKind::Dense => Box::new(DenseStepper::new(side, rule)?),
+        })
+    }
+}
+
+impl fmt::Display for StepperKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for StepperKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StepperKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| Error::Parse(format!("unknown engine \"{}\"", s)))
+    }
+}
+
+// Switches to `kind`, carrying the current and previous cells, the
+// generation and the direction of time across.
+pub fn convert(stepper: &dyn Stepper, kind: StepperKind) -> Result<Box<dyn Stepper>, Error> {
+    let mut next = kind.create(stepper.side(), stepper.rule())?;
+    next.load(&stepper.cells())?;
+    next.load_previous(&stepper.previous())?;
+    next.set_generation(stepper.generation());
+    next.set_reversed(stepper.is_reversed());
+    Ok(next)
+}
+
+// A cell in a state the rule does not have is dead, on every engine.
+fn fit(cell: Cell, rule: &Rule) -> Cell {
+    if cell.state() < rule.states() {
+        cell
+    } else {
+        Cell::Dead
+    }
+}
+
+fn check_length(cells: &[Cell], side: usize) -> Result<(), Error> {
+    if cells.len() != side * side {
+        return Err(Error::InvalidDimensions(format!(
+            "expected {} cells for a side of {}, got {}",
+            side * side,
+            side,
+            cells.len()
+        )));
+    }
+    Ok(())
+}
+
+// Below this fraction of memo hits the quadtree engine is mostly recomputing
+// unseen neighbourhoods, which the dense engine does far more cheaply.
+const MIN_HIT_RATE: f64 = 0.5;
+// Below this fraction of live cells the dense engine spends most of its time
+// on empty space.
+const MIN_DENSITY: f64 = 0.01;
+
+// The grid is kept as a node, which stays shared with the memo from one
+// generation to the next, and is only turned back into cells when they are
+// asked for.
+pub struct QuadtreeStepper {
+    rule: Rule,
+    side: usize,
+    root: Node,
+    previous: Node,
+    generation: usize,
+    reversed: bool,
+    hits: usize,
+    misses: usize,
+}
+
+impl QuadtreeStepper {
+    pub fn new(side: usize, rule: Rule) -> Result<Self, Error> {
+        if !side.is_power_of_two() || side < 4 {
+            return Err(Error::InvalidDimensions(format!(
+                "the quadtree engine needs a power-of-two side of at least 4, not {}",
+                side
+            )));
+        }
+        let empty = Node::new(vec![Cell::Dead; side * side])?;
+        Ok(QuadtreeStepper { rule, side, root: empty.clone(), previous: empty, generation: 0, reversed: false, hits: 0, misses: 0 })
+    }
+
+    // The most generations, as a power of two and up to `generations`, that
+    // the grid can go on at once. Anything that reached the dead cells around
+    // the grid during a jump would be carried on outside it, rather than left
+    // out each generation, so a jump must keep every cell that is not dead
+    // that many times the rule's radius from the edge. Rules that bring empty
+    // space to life go a generation at a time.
+    fn jump(&self, rule: &Rule, generations: usize) -> u32 {
+        if reference::next_cell(&[Cell::Dead], 1, 0, 0, rule) != Cell::Dead {
+            return 0;
+        }
+        let side = self.side;
+        let mut jump = generations.ilog2().min(self.root.max_jump(rule));
+        while jump > 0 {
+            let border = rule.radius() << jump;
+            let clear = border < side / 2
+                && self.root.is_blank_within(0, 0, side, border)
+                && self.root.is_blank_within(0, side - border, side, side)
+                && self.root.is_blank_within(0, 0, border, side)
+                && self.root.is_blank_within(side - border, 0, side, side);
+            if clear {
+                break;
+            }
+            jump -= 1;
+        }
+        jump
+    }
+
+    // A generation of a block rule. In odd generations the blocks start a
+    // cell up and left of the grid's corner, so the grid is shifted by a cell
+    // to line them up and back afterwards, and the cells along the top and
+    // left edges, whose blocks stick out of the grid, are worked out directly.
+    fn block_generation(&self, blocks: &Blocks) -> Node {
+        if self.generation.is_multiple_of(2) {
+            return self.root.apply_blocks(&self.rule);
+        }
+        let side = self.side;
+        let cells = self.root.cells();
+        let mut shifted = vec![Cell::Dead; side * side];
+        for y in 0..side - 1 {
+            for x in 0..side - 1 {
+                shifted[x + y * side] = cells[x + 1 + (y + 1) * side];
+            }
+        }
+        let node = Node::new(shifted).expect("side is checked in QuadtreeStepper::new");
+        let result = node.apply_blocks(&self.rule).cells();
+        let mut next = vec![Cell::Dead; side * side];
+        for y in 1..side {
+            for x in 1..side {
+                next[x + y * side] = result[x - 1 + (y - 1) * side];
+            }
+        }
+        for i in 0..side {
+            next[i] = reference::block_cell(&cells, side, i, 0, blocks, self.generation);
+            next[i * side] = reference::block_cell(&cells, side, 0, i, blocks, self.generation);
+        }
+        Node::new(next).expect("side is checked in QuadtreeStepper::new")
+    }
+}
+
+impl Stepper for QuadtreeStepper {
+    fn kind(&self) -> StepperKind {
+        StepperKind::Quadtree
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn side(&self) -> usize {
+        self.side
+    }
+
+    fn load(&mut self, cells: &[Cell]) -> Result<(), Error> {
+        check_length(cells, self.side)?;
+        self.root = Node::new(cells.iter().map(|cell| fit(*cell, &self.rule)).collect())?;
+        self.previous = Node::new(vec![Cell::Dead; self.side * self.side])?;
+        Ok(())
+    }
+
+    fn step(&mut self, generations: usize) {
+        let before = node::cache_stats();
+        // second-order rules share the memo with their first-order version
+        let rule = self.rule.first_order();
+        let mut remaining = generations;
+        while remaining > 0 {
+            let mut advanced = 1;
+            if let Some(blocks) = self.rule.blocks() {
+                self.root = self.block_generation(&blocks);
+            } else if self.rule.is_stochastic() {
+                // chance gives each generation its own results, so there is
+                // nothing worth memoising
+                let cells = reference::step_at(&self.root.cells(), self.side, &self.rule, self.generation);
+                self.root = Node::new(cells).expect("side is checked in QuadtreeStepper::new");
+            } else if self.rule.is_second_order() {
+                let next = self.root.next(&rule, 0).flip(&self.previous);
+                self.previous = std::mem::replace(&mut self.root, next);
+            } else {
+                let jump = self.jump(&rule, remaining);
+                self.root = self.root.next(&rule, jump);
+                advanced = 1 << jump;
+            }
+            self.generation = if self.reversed { self.generation.saturating_sub(advanced) } else { self.generation + advanced };
+            remaining -= advanced;
+        }
+        let after: CacheStats = node::cache_stats();
+        self.hits = after.hits - before.hits;
+        self.misses = after.misses - before.misses;
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        self.root.cells()
+    }
+
+    fn get(&self, x: usize, y: usize) -> Cell {
+        self.root.get(x, y)
+    }
+
+    fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        self.root = self.root.set(x, y, fit(cell, &self.rule));
+    }
+
+    fn population(&self) -> usize {
+        self.root.population()
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: usize) {
+        self.generation = generation;
+    }
+
+    fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    fn set_reversed(&mut self, reversed: bool) {
+        self.reversed = reversed;
+    }
+
+    fn previous(&self) -> Vec<Cell> {
+        self.previous.cells()
+    }
+
+    fn load_previous(&mut self, cells: &[Cell]) -> Result<(), Error> {
+        check_length(cells, self.side)?;
+        self.previous = Node::new(cells.to_vec())?;
+        Ok(())
+    }
+
+    fn suggestion(&self) -> Option<StepperKind> {
+        if self.rule.is_stochastic() {
+            return Some(StepperKind::Dense);
+        }
+        let lookups = self.hits + self.misses;
+        if lookups > 0 && (self.hits as f64) < MIN_HIT_RATE * lookups as f64 {
+            Some(StepperKind::Dense)
+        } else {
+            None
+        }
+    }
+}
+
+// The bits set where the bit-sliced `planes` spell out `count`.
+fn count_is(planes: &[u64; 4], count: usize) -> u64 {
+    let mut mask = u64::MAX;
+    for (k, plane) in planes.iter().enumerate() {
+        mask &= if count >> k & 1 == 1 { *plane } else { !*plane };
+    }
+    mask
+}
+
+// Applies a rule that is not totalistic one cell at a time, reading each
+// cell's neighbourhood from the `ring` of shifted boards.
+fn by_neighbourhood(rule: &Rule, ring: &[u64; 8], centre: u64, empty: u64) -> u64 {
+    let mut result = 0;
+    for bit in 0..64 {
+        let neighbourhood = ring.iter().enumerate().fold(0u8, |n, (i, board)| n | ((board >> bit & 1) as u8) << i);
+        let next = if centre >> bit & 1 == 1 {
+            rule.survives(neighbourhood)
+        } else {
+            empty >> bit & 1 == 1 && rule.is_born(neighbourhood)
+        };
+        result |= (next as u64) << bit;
+    }
+    result
+}
+
+// Bit-packed grid: bit `x % 64` of word `x / 64 + y * words` holds cell (x, y).
+// Under Generations rules `refractory` marks the dying cells in the same
+// layout, and `dying` holds the state of each of them. Rules from rule files
+// keep their states above 1 there too. Second-order rules keep the live cells
+// of the generation before in `previous`.
+pub struct DenseStepper {
+    rule: Rule,
+    side: usize,
+    words: usize,
+    bits: Vec<u64>,
+    refractory: Vec<u64>,
+    dying: Vec<u8>,
+    previous: Vec<u64>,
+    generation: usize,
+    reversed: bool,
+}
+
+impl DenseStepper {
+    pub fn new(side: usize, rule: Rule) -> Result<Self, Error> {
+        if side == 0 {
+            return Err(Error::InvalidDimensions("the grid needs at least one cell".to_string()));
+        }
+        let words = side.div_ceil(64);
+        let dying = if rule.states() > 2 { vec![0; side * side] } else { vec![] };
+        Ok(DenseStepper {
+            rule,
+            side,
+            words,
+            bits: vec![0; words * side],
+            refractory: vec![0; words * side],
+            dying,
+            previous: vec![0; words * side],
+            generation: 0,
+            reversed: false,
+        })
+    }
+
+    fn last_mask(&self) -> u64 {
+        match self.side % 64 {
+            0 => u64::MAX,
+            r => (1 << r) - 1,
+        }
+    }
+
+    // Word `w` of row `y`, along with copies shifted so that each bit holds its
+    // west and east neighbour. Cells past either end of the row are dead.
+    fn shifted(&self, y: usize, w: usize) -> (u64, u64, u64) {
+        let row = &self.bits[y * self.words..(y + 1) * self.words];
+        let centre = row[w];
+        let before = if w > 0 { row[w - 1] } else { 0 };
+        let after = if w + 1 < self.words { row[w + 1] } else { 0 };
+        let west = (centre << 1) | (before >> 63);
+        let east = (centre >> 1) | (after << 63);
+        (west, centre, east)
+    }
+
+    // Whether word `w` of row `y` and the eight words around it are all dead.
+    fn is_empty_around(&self, y: usize, w: usize) -> bool {
+        let rows = y.saturating_sub(1)..(y + 2).min(self.side);
+        let words = w.saturating_sub(1)..(w + 2).min(self.words);
+        rows.into_iter().all(|row| self.bits[row * self.words + words.start..row * self.words + words.end].iter().all(|word| *word == 0))
+    }
+
+    fn is_alive(&self, x: usize, y: usize) -> bool {
+        self.bits[x / 64 + y * self.words] >> (x % 64) & 1 == 1
+    }
+
+    // A generation of a Larger than Life rule, which counts each cell's
+    // neighbours with running totals along the rows rather than bit by bit,
+    // skipping rows too far from any live cell to change.
+    fn range_generation(&mut self, range: &Range) {
+        let (side, radius) = (self.side, range.radius);
+        // totals[x + y * (side + 1)] is the number of live cells left of x in row y
+        let mut totals = vec![0; (side + 1) * side];
+        for y in 0..side {
+            for x in 0..side {
+                totals[x + 1 + y * (side + 1)] = totals[x + y * (side + 1)] + self.is_alive(x, y) as usize;
+            }
+        }
+        let skip_empty = !self.rule.births().any(|count| count == 0);
+        let occupied: Vec<bool> = (0..side).map(|y| totals[side + y * (side + 1)] > 0).collect();
+
+        let mut next = vec![0u64; self.bits.len()];
+        let mut dying = vec![0u64; self.bits.len()];
+        for y in 0..side {
+            let rows = y.saturating_sub(radius)..(y + radius + 1).min(side);
+            if skip_empty && !occupied[rows.clone()].contains(&true) {
+                continue;
+            }
+            for x in 0..side {
+                let mut count = 0;
+                for ny in rows.clone() {
+                    let width = range.width(ny.abs_diff(y));
+                    count += totals[(x + width + 1).min(side) + ny * (side + 1)] - totals[x.saturating_sub(width) + ny * (side + 1)];
+                }
+                let (w, bit) = (x / 64 + y * self.words, x % 64);
+                let cell = if self.is_alive(x, y) {
+                    count -= 1;
+                    Cell::Alive
+                } else if self.refractory[w] >> bit & 1 == 1 {
+                    Cell::State(2)
+                } else {
+                    Cell::Dead
+                };
+                if self.rule.next_counted(cell, count) == Cell::Alive {
+                    next[w] |= 1 << bit;
+                } else if cell == Cell::Alive {
+                    dying[w] |= 1 << bit;
+                }
+            }
+        }
+        self.bits = next;
+        if self.rule.states() > 2 {
+            self.decay(&dying);
+        }
+    }
+
+    // A generation of a rule from a rule file, which looks up each cell by the
+    // states around it. Rows too far from any cell that is not dead are
+    // skipped when the table leaves empty space empty.
+    fn table_generation(&mut self, table: &RuleTable) {
+        let side = self.side;
+        let cells = self.cells();
+        let skip_empty = table.next(0, &[0; 8]) == 0;
+        let occupied: Vec<bool> = cells.chunks(side).map(|row| row.iter().any(|cell| *cell != Cell::Dead)).collect();
+        let mut next = vec![Cell::Dead; side * side];
+        for y in 0..side {
+            if skip_empty && !occupied[y.saturating_sub(1)..(y + 2).min(side)].contains(&true) {
+                continue;
+            }
+            for x in 0..side {
+                let neighbours = reference::neighbour_states(&cells, side, x, y);
+                next[x + y * side] = Cell::from_state(table.next(cells[x + y * side].state(), &neighbours));
+            }
+        }
+        self.load(&next).expect("the grid is the same size");
+    }
+
+    // A generation of a block rule, block by block. Pairs of empty rows are
+    // skipped when empty blocks stay empty.
+    fn block_generation(&mut self, blocks: &Blocks) {
+        let (side, words) = (self.side, self.words);
+        let offset = self.generation % 2;
+        let is_empty = |bits: &[u64], y: usize| bits[y * words..(y + 1) * words].iter().all(|word| *word == 0);
+        let mut next = vec![0u64; self.bits.len()];
+        // the blocks start a cell outside the grid in odd generations
+        for top in (0..side + offset).step_by(2).map(|top| top as isize - offset as isize) {
+            let rows = [top, top + 1].map(|y| usize::try_from(y).ok().filter(|y| *y < side));
+            if blocks[0] == 0 && rows.iter().all(|y| y.is_none_or(|y| is_empty(&self.bits, y))) {
+                continue;
+            }
+            for left in (0..side + offset).step_by(2).map(|left| left as isize - offset as isize) {
+                let columns = [left, left + 1].map(|x| usize::try_from(x).ok().filter(|x| *x < side));
+                let cells = [(columns[0], rows[0]), (columns[1], rows[0]), (columns[0], rows[1]), (columns[1], rows[1])];
+                let block = cells
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (x, y))| matches!((x, y), (Some(x), Some(y)) if self.is_alive(*x, *y)))
+                    .fold(0, |block, (bit, _)| block | 1 << bit);
+                for (bit, cell) in cells.iter().enumerate() {
+                    if let (Some(x), Some(y)) = cell {
+                        if blocks[block] >> bit & 1 == 1 {
+                            next[x / 64 + y * words] |= 1 << (x % 64);
+                        }
+                    }
+                }
+            }
+        }
+        self.bits = next;
+    }
+
+    // Leaves each birth since `before` to chance, and then flips cells at
+    // random, with the same draws as `Chances::apply`.
+    fn apply_chances(&mut self, chances: &Chances, before: &[u64]) {
+        for y in 0..self.side {
+            for w in 0..self.words {
+                let i = w + y * self.words;
+                let mut births = self.bits[i] & !before[i];
+                while births != 0 {
+                    let bit = births.trailing_zeros() as usize;
+                    births &= births - 1;
+                    if !chances.born(w * 64 + bit, y, self.generation) {
+                        self.bits[i] &= !(1 << bit);
+                    }
+                }
+                if chances.noise > 0 {
+                    for x in w * 64..(w * 64 + 64).min(self.side) {
+                        if chances.flips(x, y, self.generation) {
+                            self.bits[i] ^= 1 << (x % 64);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn next_generation(&mut self) {
+        if let Some(blocks) = self.rule.blocks() {
+            return self.block_generation(&blocks);
+        }
+        if let Some(table) = self.rule.table() {
+            return self.table_generation(&table);
+        }
+        if let Some(range) = self.rule.range() {
+            return self.range_generation(&range);
+        }
+        let generations = self.rule.states() > 2;
+        let mut next = vec![0u64; self.bits.len()];
+        // live cells that die this generation
+        let mut dying = if generations { vec![0u64; self.bits.len()] } else { vec![] };
+        let last_mask = self.last_mask();
+        // without birth on zero neighbours, empty space stays empty
+        let skip_empty = !self.rule.is_born(0);
+        let totalistic = self.rule.is_totalistic();
+        let mask = self.rule.neighbourhood().mask();
+        let births: Vec<usize> = self.rule.births().collect();
+        let survivals: Vec<usize> = self.rule.survivals().collect();
+        let occupied: Vec<bool> = self.bits.chunks(self.words).map(|row| row.iter().any(|word| *word != 0)).collect();
+        for y in 0..self.side {
+            if skip_empty && !occupied[y.saturating_sub(1)..(y + 2).min(self.side)].contains(&true) {
+                continue;
+            }
+            for w in 0..self.words {
+                if skip_empty && self.is_empty_around(y, w) {
+                    continue;
+                }
+                let (west, centre, east) = self.shifted(y, w);
+                let (north_west, north, north_east) = if y > 0 { self.shifted(y - 1, w) } else { (0, 0, 0) };
+                let (south_west, south, south_east) = if y + 1 < self.side { self.shifted(y + 1, w) } else { (0, 0, 0) };
+                let ring = [north, north_east, east, south_east, south, south_west, west, north_west];
+
+                // dying cells cannot be born
+                let empty = !centre & !self.refractory[w + y * self.words];
+                let mut result = if totalistic {
+                    // bit-sliced counter: planes[k] holds bit k of every cell's count
+                    let mut planes = [0u64; 4];
+                    for (_, board) in ring.iter().enumerate().filter(|(i, _)| mask >> i & 1 == 1) {
+                        let mut carry = *board;
+                        for plane in planes.iter_mut() {
+                            let sum = *plane ^ carry;
+                            carry &= *plane;
+                            *plane = sum;
+                        }
+                    }
+                    let mut result = 0;
+                    for count in &births {
+                        result |= count_is(&planes, *count) & empty;
+                    }
+                    for count in &survivals {
+                        result |= count_is(&planes, *count) & centre;
+                    }
+                    result
+                } else {
+                    by_neighbourhood(&self.rule, &ring, centre, empty)
+                };
+                if w + 1 == self.words {
+                    result &= last_mask;
+                }
+                next[w + y * self.words] = result;
+                if generations {
+                    dying[w + y * self.words] = centre & !result;
+                }
+            }
+        }
+        self.bits = next;
+        if generations {
+            self.decay(&dying);
+        }
+    }
+
+    // Moves every dying cell on to its next state, and starts the cells in
+    // `dying` on their way.
+    fn decay(&mut self, dying: &[u64]) {
+        let states = self.rule.states();
+        for (i, (refractory, started)) in self.refractory.iter_mut().zip(dying).enumerate() {
+            let (x, y) = ((i % self.words) * 64, i / self.words);
+            let mut bits = *refractory;
+            while bits != 0 {
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                let state = &mut self.dying[x + bit + y * self.side];
+                *state += 1;
+                if *state >= states {
+                    *state = 0;
+                    *refractory &= !(1 << bit);
+                }
+            }
+            let mut bits = *started;
+            while bits != 0 {
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                self.dying[x + bit + y * self.side] = 2;
+            }
+            *refractory |= started;
+        }
+    }
+}
+
+impl Stepper for DenseStepper {
+    fn kind(&self) -> StepperKind {
+        StepperKind::Dense
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn side(&self) -> usize {
+        self.side
+    }
+
+    fn load(&mut self, cells: &[Cell]) -> Result<(), Error> {
+        check_length(cells, self.side)?;
+        self.bits = vec![0; self.words * self.side];
+        self.refractory = vec![0; self.words * self.side];
+        self.previous = vec![0; self.words * self.side];
+        for (i, cell) in cells.iter().enumerate() {
+            if *cell != Cell::Dead {
+                self.set(i % self.side, i / self.side, *cell);
+            }
+        }
+        Ok(())
+    }
+
+    fn step(&mut self, generations: usize) {
+        for _ in 0..generations {
+            if self.rule.is_second_order() {
+                let current = self.bits.clone();
+                self.next_generation();
+                for (word, previous) in self.bits.iter_mut().zip(&self.previous) {
+                    *word ^= previous;
+                }
+                self.previous = current;
+            } else if let Some(chances) = self.rule.chances().filter(|_| self.rule.is_stochastic()) {
+                let current = self.bits.clone();
+                self.next_generation();
+                self.apply_chances(&chances, &current);
+            } else {
+                self.next_generation();
+            }
+            self.generation = if self.reversed { self.generation.saturating_sub(1) } else { self.generation + 1 };
+        }
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        let mut cells = Vec::with_capacity(self.side * self.side);
+        for y in 0..self.side {
+            for x in 0..self.side {
+                cells.push(self.get(x, y));
+            }
+        }
+        cells
+    }
+
+    fn get(&self, x: usize, y: usize) -> Cell {
+        let i = x / 64 + y * self.words;
+        if self.bits[i] >> (x % 64) & 1 == 1 {
+            Cell::Alive
+        } else if self.refractory[i] >> (x % 64) & 1 == 1 {
+            Cell::State(self.dying[x + y * self.side])
+        } else {
+            Cell::Dead
+        }
+    }
+
+    // States the rule does not have are stored as dead.
+    fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        let i = x / 64 + y * self.words;
+        let bit = 1 << (x % 64);
+        self.bits[i] &= !bit;
+        self.refractory[i] &= !bit;
+        match cell {
+            Cell::Alive => self.bits[i] |= bit,
+            Cell::State(state) if fit(cell, &self.rule) == cell => {
+                self.refractory[i] |= bit;
+                self.dying[x + y * self.side] = state;
+            }
+            Cell::Dead | Cell::State(_) => {}
+        }
+    }
+
+    fn population(&self) -> usize {
+        self.bits.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: usize) {
+        self.generation = generation;
+    }
+
+    fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    fn set_reversed(&mut self, reversed: bool) {
+        self.reversed = reversed;
+    }
+
+    fn previous(&self) -> Vec<Cell> {
+        let mut cells = Vec::with_capacity(self.side * self.side);
+        for y in 0..self.side {
+            for x in 0..self.side {
+                let alive = self.previous[x / 64 + y * self.words] >> (x % 64) & 1 == 1;
+                cells.push(if alive { Cell::Alive } else { Cell::Dead });
+            }
+        }
+        cells
+    }
+
+    fn load_previous(&mut self, cells: &[Cell]) -> Result<(), Error> {
+        check_length(cells, self.side)?;
+        self.previous = vec![0; self.words * self.side];
+        for (i, cell) in cells.iter().enumerate() {
+            if *cell == Cell::Alive {
+                let (x, y) = (i % self.side, i / self.side);
+                self.previous[x / 64 + y * self.words] |= 1 << (x % 64);
+            }
+        }
+        Ok(())
+    }
+
+    fn suggestion(&self) -> Option<StepperKind> {
+        let area = self.side * self.side;
+        if self.side.is_power_of_two() && self.side >= 4 && (self.population() as f64) < MIN_DENSITY * area as f64 {
+            Some(StepperKind::Quadtree)
+        } else {
+            None
+        }
+    }
+}
@@ -1,30 +1,115 @@
 use std::cmp;
+use std::collections::HashMap;
 use yew::prelude::*;
-use wasm_bindgen::{JsCast, JsValue};
-use web_sys::{CanvasRenderingContext2d, Element, HtmlCanvasElement};
-use gloo_console::log;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, Element, HtmlCanvasElement, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
 use derivative::Derivative;
-use crate::node::Node;
+use crate::analysis;
+use crate::elementary::{self, LineRule, SpaceTime};
+use crate::error::Error;
+use crate::explorer::Explorer;
+use crate::history::{self, History, Ramp};
+use crate::library;
+use crate::life3d::{self, Lattice, Rule3D};
+use crate::objects::{self, Grouping};
+use crate::pattern::Pattern;
+use crate::rule::{Neighbourhood, Rule};
+use crate::ruletable;
+use crate::stepper::{self, Stepper, StepperKind};
 
 #[derive(Derivative, Eq, Clone, Copy, Debug)]
 #[derivative(PartialEq, Hash)]
 pub enum Cell {
-    Alive = 1,
-    Dead = 0,
+    Alive,
+    Dead,
+    // State 2 or above of a multi-state rule, such as the dying states of a
+    // Generations rule. Dead is state 0 and alive is state 1.
+    State(u8),
 }
 
+impl Cell {
+    pub fn from_state(state: u8) -> Cell {
+        match state {
+            0 => Cell::Dead,
+            1 => Cell::Alive,
+            _ => Cell::State(state),
+        }
+    }
+
+    pub fn state(self) -> u8 {
+        match self {
+            Cell::Dead => 0,
+            Cell::Alive => 1,
+            Cell::State(state) => state,
+        }
+    }
+}
+
+// How far ahead the current pattern is run to find its period.
+const CLASSIFY_GENERATIONS: usize = 128;
+// How far ahead islands are run to see which of them belong together.
+const OBJECT_GENERATIONS: usize = 16;
+// How far apart live cells can be and still share an island.
+const OBJECT_DISTANCE: usize = 1;
+// How many frames an object's label is kept after it was last drawn.
+const LABEL_FRAMES: usize = 16;
+// What the palette calls WireWorld's states.
+const WIREWORLD_STATES: [&str; 4] = ["empty", "electron head", "electron tail", "conductor"];
+// The soup a 3D rule starts from, in a cube this wide in the middle.
+const SOUP_3D_SEED: u64 = 1;
+const SOUP_3D_EXTENT: usize = 6;
+const SOUP_3D_DENSITY: f64 = 0.3;
+
 pub struct Universe {
     node_ref: NodeRef,
     size: usize,
     cell_size: usize,
-    cells: Node,
-    cell_vec: Vec<Cell>,
+    stepper: Box<dyn Stepper>,
+    suggestion: Option<StepperKind>,
+    classification: Option<String>,
+    // The rule and phase `classification` describes: the pattern, the
+    // generation before it and the parity of the generation, so that redraws
+    // that change none of them do not run the analysis again.
+    classified: Option<(Rule, Pattern, Pattern, usize)>,
+    show_objects: bool,
+    // Labels of the objects drawn lately, with the frame each was last used,
+    // and a count of frames drawn with objects shown.
+    labels: HashMap<(Rule, Pattern), (String, usize)>,
+    frame: usize,
+    history: History,
+    // Colours live cells by age when set, rather than plain black.
+    ramp: Option<Ramp>,
+    show_history: bool,
+    // The cell clicks paint, or `None` to toggle cells on and off.
+    brush: Option<Cell>,
+    // Under a one-dimensional rule, the space-time diagram shown instead of
+    // the 2D grid.
+    line: Option<SpaceTime>,
+    // Under a 3D rule, the lattice shown instead, either one layer at a time
+    // or, when `slice` is `None`, in an isometric view.
+    lattice: Option<Lattice>,
+    slice: Option<usize>,
+    // Whether the rule explorer is open under the grid.
+    exploring: bool,
+    error: Option<String>,
 }
 
 pub enum Msg {
     Init,
     Render(bool),
     Mutate(MouseEvent),
+    SetEngine(StepperKind),
+    Load(String),
+    ShowObjects(bool),
+    SetRamp(Option<Ramp>),
+    ShowHistory(bool),
+    SetRule(String),
+    LoadRuleFile(String),
+    SetBrush(Option<Cell>),
+    ReverseTime,
+    SetSlice(Option<usize>),
+    Explore(bool),
+    Promote(Rule),
 }
 
 impl Component for Universe {
@@ -33,59 +118,349 @@ impl Component for Universe {
 
     fn create(ctx: &Context<Self>) -> Self {
         ctx.link().send_message(Msg::Init);
-        let vec_init = vec![Cell::Dead; 1024];
-        let node = Node::new(vec_init.clone());
+        let stepper = StepperKind::Quadtree
+            .create(32, Rule::default())
+            .expect("32 is a valid quadtree side");
         Universe {
             node_ref: NodeRef::default(),
             size: 16,
             cell_size: 50,
-            cells: node,
-            cell_vec: vec_init,
+            history: History::new(&stepper.cells(), stepper.side()),
+            stepper,
+            suggestion: None,
+            classification: None,
+            classified: None,
+            show_objects: false,
+            labels: HashMap::new(),
+            frame: 0,
+            ramp: None,
+            show_history: false,
+            brush: None,
+            line: None,
+            lattice: None,
+            slice: None,
+            exploring: false,
+            error: None,
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::Init => {
-                self.init(ctx);
-                false
+                let result = self.init(ctx);
+                self.report(result)
             }
             Msg::Render(is_mut) => {
-                self.render(ctx, is_mut);
-                false
+                let classification = self.classification.clone();
+                // stepping leaves what the pattern turns into as it was
+                let result = self.render(is_mut).and_then(|_| if is_mut { self.classify() } else { Ok(()) });
+                let suggestion = self.stepper.suggestion();
+                let changed = suggestion != self.suggestion || classification != self.classification;
+                self.suggestion = suggestion;
+                self.report(result) || changed
             }
             Msg::Mutate(event) => {
-                self.mutate(event, ctx);
+                let result = self.mutate(event, ctx);
+                self.report(result)
+            }
+            Msg::SetEngine(kind) => {
+                let result = stepper::convert(self.stepper.as_ref(), kind).map(|stepper| {
+                    self.stepper = stepper;
+                    self.suggestion = None;
+                });
+                self.report(result);
+                true
+            }
+            Msg::Load(name) => {
+                let states = self.stepper.rule().states();
+                let result = self.load(&name, ctx);
+                // circuits bring their own rule, and with it the palette
+                self.report(result) || states != self.stepper.rule().states()
+            }
+            Msg::ShowObjects(show) => {
+                self.show_objects = show;
+                ctx.link().send_message(Msg::Render(true));
+                false
+            }
+            Msg::SetRamp(ramp) => {
+                self.ramp = ramp;
+                ctx.link().send_message(Msg::Render(true));
+                false
+            }
+            Msg::ShowHistory(show) => {
+                self.show_history = show;
+                ctx.link().send_message(Msg::Render(true));
+                false
+            }
+            Msg::SetRule(rule) if life3d::is_3d_rule(&rule) => {
+                let result = rule.parse::<Rule3D>().and_then(|rule| self.set_3d_rule(rule, ctx));
+                self.report(result);
+                true
+            }
+            Msg::SetRule(rule) if elementary::is_line_rule(&rule) => {
+                let result = rule.parse::<LineRule>().and_then(|rule| self.set_line_rule(rule, ctx));
+                self.report(result);
+                true
+            }
+            Msg::SetRule(rule) => {
+                let result = rule.parse::<Rule>().and_then(|rule| self.set_rule(rule, ctx));
+                self.report(result);
+                // the canvas widens for the hexagonal grid, and the palette
+                // changes with the states
+                true
+            }
+            Msg::LoadRuleFile(text) => {
+                let result = ruletable::parse(&text).and_then(|table| self.set_rule(Rule::from_table(table), ctx));
+                self.report(result);
+                true
+            }
+            Msg::SetBrush(brush) => {
+                self.brush = brush;
                 false
             }
+            Msg::Explore(exploring) => {
+                self.exploring = exploring;
+                true
+            }
+            Msg::Promote(rule) => {
+                let result = self.set_rule(rule, ctx);
+                self.exploring = false;
+                self.report(result);
+                true
+            }
+            Msg::SetSlice(slice) => {
+                self.slice = slice;
+                ctx.link().send_message(Msg::Render(true));
+                false
+            }
+            Msg::ReverseTime => {
+                // the grid goes back to the previous generation at once, and
+                // the history starts again from there; the classification
+                // follows from the swapped pair of generations on the render
+                let result = self.stepper.reverse();
+                self.history = History::new(&self.stepper.cells(), self.stepper.side());
+                ctx.link().send_message(Msg::Render(true));
+                self.report(result)
+            }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         let onclick = ctx.link().callback(|_| Msg::Render(false));
         let mutate = ctx.link().callback(|event: MouseEvent| Msg::Mutate(event));
+        let set_engine = ctx.link().callback(|event: Event| {
+            let select: HtmlSelectElement = event.target_unchecked_into();
+            Msg::SetEngine(select.value().parse().unwrap_or(StepperKind::Quadtree))
+        });
+        let load = ctx.link().callback(|event: Event| {
+            let text: HtmlTextAreaElement = event.target_unchecked_into();
+            Msg::Load(text.value())
+        });
+        let show_objects = ctx.link().callback(|event: Event| {
+            let input: HtmlInputElement = event.target_unchecked_into();
+            Msg::ShowObjects(input.checked())
+        });
+        let set_ramp = ctx.link().callback(|event: Event| {
+            let select: HtmlSelectElement = event.target_unchecked_into();
+            let ramp = history::RAMPS.iter().find(|(name, _)| *name == select.value()).map(|(_, ramp)| *ramp);
+            Msg::SetRamp(ramp)
+        });
+        let show_history = ctx.link().callback(|event: Event| {
+            let input: HtmlInputElement = event.target_unchecked_into();
+            Msg::ShowHistory(input.checked())
+        });
+        let set_rule = ctx.link().callback(|event: Event| {
+            let input: HtmlInputElement = event.target_unchecked_into();
+            Msg::SetRule(input.value())
+        });
+        let load_rule_file = ctx.link().callback(|event: Event| {
+            let text: HtmlTextAreaElement = event.target_unchecked_into();
+            Msg::LoadRuleFile(text.value())
+        });
+        let set_brush = ctx.link().callback(|event: Event| {
+            let select: HtmlSelectElement = event.target_unchecked_into();
+            Msg::SetBrush(select.value().parse().ok().map(Cell::from_state))
+        });
+        let reverse_time = ctx.link().callback(|_| Msg::ReverseTime);
+        let explore = ctx.link().callback(|event: Event| {
+            let input: HtmlInputElement = event.target_unchecked_into();
+            Msg::Explore(input.checked())
+        });
+        let promote = ctx.link().callback(Msg::Promote);
+        let set_slice = ctx.link().callback(|event: Event| {
+            let select: HtmlSelectElement = event.target_unchecked_into();
+            Msg::SetSlice(select.value().parse().ok())
+        });
+        let current = self.stepper.kind();
+        let states = match &self.line {
+            Some(line) => line.rule().colours(),
+            None => self.stepper.rule().states(),
+        };
         html! {
             <div>
                 <canvas id="world" 
-                    width={(self.size * self.cell_size + 100).to_string()}
-                    height={(self.size * self.cell_size + 100).to_string()}
+                    width={self.extent().0.to_string()}
+                    height={self.extent().1.to_string()}
                     ref={self.node_ref.clone()}
                     onclick={mutate}></canvas>
                 <button {onclick}>{ "Click" }</button>
+                if let Some(lattice) = &self.lattice {
+                    <select onchange={set_slice}>
+                        <option value="isometric" selected={self.slice.is_none()}>{ "isometric" }</option>
+                        { for (0..lattice.side()).map(|z| html! {
+                            <option value={z.to_string()} selected={self.slice == Some(z)}>{ format!("layer {}", z) }</option>
+                        }) }
+                    </select>
+                }
+                if self.line.is_none() && self.lattice.is_none() && self.stepper.rule().is_second_order() {
+                    <button onclick={reverse_time}>{ "Reverse time" }</button>
+                }
+                <select onchange={set_engine}>
+                    { for StepperKind::ALL.iter().map(|kind| html! {
+                        <option value={kind.name()} selected={*kind == current}>{ kind.name() }</option>
+                    }) }
+                </select>
+                <textarea placeholder="pattern name, apgcode or RLE" onchange={load}></textarea>
+                <input type="text" placeholder="rule, e.g. B3/S23, /2/3, B2/S34H, W30 or 4555" onchange={set_rule} />
+                <textarea placeholder="paste a Golly .rule file" onchange={load_rule_file}></textarea>
+                if states > 2 {
+                    <select onchange={set_brush}>
+                        <option value="toggle" selected={self.brush.is_none()}>{ "paint: toggle" }</option>
+                        { for (0..states).map(|state| html! {
+                            <option value={state.to_string()} selected={self.brush == Some(Cell::from_state(state))}>
+                                { format!("paint: {}", self.state_name(state)) }
+                            </option>
+                        }) }
+                    </select>
+                }
+                <label>
+                    <input type="checkbox" checked={self.show_objects} onchange={show_objects} />
+                    { "Show objects" }
+                </label>
+                <select onchange={set_ramp}>
+                    <option value="plain" selected={self.ramp.is_none()}>{ "plain" }</option>
+                    { for history::RAMPS.iter().map(|(name, ramp)| html! {
+                        <option value={*name} selected={self.ramp == Some(*ramp)}>{ format!("age: {}", name) }</option>
+                    }) }
+                </select>
+                <label>
+                    <input type="checkbox" checked={self.show_history} onchange={show_history} />
+                    { "Show history" }
+                </label>
+                <label>
+                    <input type="checkbox" checked={self.exploring} onchange={explore} />
+                    { "Explore rules" }
+                </label>
+                if self.exploring {
+                    <Explorer current={self.stepper.rule()} on_promote={promote} />
+                }
+                if let Some(classification) = &self.classification {
+                    <p>{ classification }</p>
+                }
+                if let Some(kind) = self.suggestion {
+                    <p>{ format!("This pattern may run faster on the {} engine.", kind) }</p>
+                }
+                if let Some(message) = &self.error {
+                    <p class="error">{ message }</p>
+                }
             </div>
         }
     }
 }
 
 impl Universe {
-    fn init(&mut self, ctx: &Context<Self>) {
-        let canvas: HtmlCanvasElement = self.node_ref.cast().unwrap();
-        let canvas_ctx: CanvasRenderingContext2d = canvas
+    // Shows the error, if any, under the canvas. Returns whether the view
+    // needs to change.
+    fn report(&mut self, result: Result<(), Error>) -> bool {
+        let error = result.err().map(|error| error.to_string());
+        let changed = error != self.error;
+        self.error = error;
+        changed
+    }
+
+    // Describes what the current pattern turns into, e.g. "p3 oscillator".
+    fn classify(&mut self) -> Result<(), Error> {
+        if self.line.is_some() || self.lattice.is_some() {
+            self.classification = None;
+            self.classified = None;
+            return Ok(());
+        }
+        // second-order rules depend on the generation before too, and block
+        // rules on which way the blocks lie
+        let rule = self.stepper.rule();
+        let (pattern, previous) = analysis::phase(self.stepper.as_ref());
+        let parity = if rule.blocks().is_some() { self.stepper.generation() % 2 } else { 0 };
+        let key = (rule, pattern, previous, parity);
+        if self.classified.as_ref() == Some(&key) {
+            return Ok(());
+        }
+        let (rule, pattern, previous, parity) = &key;
+        self.classification = if pattern.cells.iter().chain(&previous.cells).all(|cell| *cell == Cell::Dead) {
+            None
+        } else {
+            let analysis = analysis::classify_phase(pattern, previous, *parity, rule, CLASSIFY_GENERATIONS)?;
+            Some(analysis.classification.to_string())
+        };
+        self.classified = Some(key);
+        Ok(())
+    }
+
+    fn context(&self) -> Result<CanvasRenderingContext2d, Error> {
+        let canvas: HtmlCanvasElement = self
+            .node_ref
+            .cast()
+            .ok_or_else(|| Error::RenderContext("the canvas is not mounted".to_string()))?;
+        canvas
             .get_context("2d")
-            .unwrap()
-            .unwrap()
-            .dyn_into()
-            .unwrap();
+            .ok()
+            .flatten()
+            .and_then(|context| context.dyn_into().ok())
+            .ok_or_else(|| Error::RenderContext("the canvas has no 2D context".to_string()))
+    }
+
+    fn init(&mut self, ctx: &Context<Self>) -> Result<(), Error> {
+        self.draw_grid(&self.context()?);
+        ctx.link().send_message(Msg::Render(true));
+        Ok(())
+    }
+
+    // The canvas size, which on the hexagonal grid is wider to fit the offset
+    // rows.
+    fn extent(&self) -> (usize, usize) {
+        let extent = self.size * self.cell_size + 100;
+        if self.is_hexagonal() {
+            (extent + self.size * (self.cell_size + 1) / 2, extent)
+        } else {
+            (extent, extent)
+        }
+    }
+
+    fn is_hexagonal(&self) -> bool {
+        self.line.is_none() && self.lattice.is_none() && self.stepper.rule().neighbourhood() == Neighbourhood::Hexagonal
+    }
+
+    // How far right row `r` of the view is drawn. On the hexagonal grid each
+    // row sits half a cell left of the one above, so that a cell touches the
+    // two cells above it that are its neighbours, and the two below.
+    fn row_offset(&self, r: usize) -> f64 {
+        if self.is_hexagonal() {
+            (self.size - 1 - r) as f64 * (self.cell_size + 1) as f64 / 2.0
+        } else {
+            0.0
+        }
+    }
+
+    fn draw_grid(&self, canvas_ctx: &CanvasRenderingContext2d) {
+        if self.is_hexagonal() {
+            // a wall of bricks rather than a square grid
+            let pitch = (self.cell_size + 1) as f64;
+            for r in 0..self.size {
+                for c in 0..self.size {
+                    canvas_ctx.stroke_rect(c as f64 * pitch + self.row_offset(r) + 1.0, r as f64 * pitch + 1.0, pitch, pitch);
+                }
+            }
+            return;
+        }
         canvas_ctx.begin_path();
         let size: u32 = self.size as u32;
         let cell_size: u32 = self.cell_size as u32;
@@ -100,35 +475,36 @@ impl Universe {
             canvas_ctx.line_to(((cell_size + 1) * size + 1).into(), (i * (cell_size + 1) + 1).into());
         }
         canvas_ctx.stroke();
-
-        ctx.link().send_message(Msg::Render(true));
     }
 
-    fn render(&mut self, ctx: &Context<Self>, is_mut: bool) {
-        let canvas: HtmlCanvasElement = self.node_ref.cast().unwrap();
-        let canvas_ctx: CanvasRenderingContext2d = canvas
-            .get_context("2d")
-            .unwrap()
-            .unwrap()
-            .dyn_into()
-            .unwrap();
-        if !is_mut {
-            self.cells = Node::new(self.cell_vec.clone());
-            self.cell_vec = self.cells.evolve().clone(); 
+    fn render(&mut self, is_mut: bool) -> Result<(), Error> {
+        let canvas_ctx = self.context()?;
+        if let Some(lattice) = &mut self.lattice {
+            if !is_mut {
+                lattice.step(1);
+            }
+            self.draw_lattice(&canvas_ctx);
+            return Ok(());
         }
+        if let (false, Some(line)) = (is_mut, &mut self.line) {
+            line.step(1);
+        } else if !is_mut {
+            self.stepper.step(1);
+            self.history.step(&self.stepper.cells());
+        }
+        // start afresh, so that outlines from the last frame disappear
+        let (width, height) = self.extent();
+        canvas_ctx.clear_rect(0.0, 0.0, width as f64, height as f64);
+        self.draw_grid(&canvas_ctx);
         canvas_ctx.begin_path();
         let size = self.size;
         let cell_size = self.cell_size;
         for r in (size / 2)..(3 * size / 2) {
             for c in (size / 2)..(3 * size / 2) {
-                if self.cell_vec[c + r * size * 2] == Cell::Alive {
-                    canvas_ctx.set_fill_style(&JsValue::from("#000000"));
-                } else {
-                    canvas_ctx.set_fill_style(&JsValue::from("#FFFFFF"));
-                }
+                canvas_ctx.set_fill_style_str(&self.colour(c, r));
 
                 canvas_ctx.fill_rect(
-                    (((c - size / 2) as u32) * ((cell_size as u32) + 1) + 3).into(),
+                    f64::from(((c - size / 2) as u32) * ((cell_size as u32) + 1) + 3) + self.row_offset(r - size / 2),
                     (((r - size / 2) as u32) * ((cell_size as u32) + 1) + 3).into(),
                     (cell_size as u32 - 6).into(),
                     (cell_size as u32 - 6).into()
@@ -136,23 +512,263 @@ impl Universe {
             }
         }
         canvas_ctx.stroke();
-        log!("rendered");
+        if self.show_objects && self.line.is_none() {
+            self.outline_objects(&canvas_ctx)?;
+        }
+        Ok(())
+    }
+
+    // Draws one layer of the lattice on the usual grid, or every live cell
+    // as a cube seen from above one corner, drawn from the back forwards.
+    fn draw_lattice(&self, canvas_ctx: &CanvasRenderingContext2d) {
+        let Some(lattice) = &self.lattice else {
+            return;
+        };
+        let (width, height) = self.extent();
+        canvas_ctx.clear_rect(0.0, 0.0, width as f64, height as f64);
+        let side = lattice.side();
+        if let Some(z) = self.slice {
+            self.draw_grid(canvas_ctx);
+            let pitch = (self.cell_size + 1) as f64;
+            for y in 0..side {
+                for x in 0..side {
+                    let colour = if lattice.get(x, y, z) == Cell::Alive { "#000000" } else { "#FFFFFF" };
+                    canvas_ctx.set_fill_style_str(colour);
+                    canvas_ctx.fill_rect(x as f64 * pitch + 3.0, y as f64 * pitch + 3.0, self.cell_size as f64 - 6.0, self.cell_size as f64 - 6.0);
+                }
+            }
+            return;
+        }
+        let edge = width as f64 / (2 * side + 2) as f64;
+        let (centre_x, centre_y) = (width as f64 / 2.0, height as f64 / 2.0);
+        let point = |x: usize, y: usize, z: usize| {
+            let (x, y, z) = (x as f64, y as f64, z as f64);
+            (centre_x + (x - y) * edge, centre_y + (x + y - side as f64) * edge / 2.0 - (z - side as f64 / 2.0) * edge)
+        };
+        let mut cubes: Vec<(usize, usize, usize)> = (0..side * side * side)
+            .map(|i| (i % side, i / side % side, i / (side * side)))
+            .filter(|(x, y, z)| lattice.get(*x, *y, *z) == Cell::Alive)
+            .collect();
+        cubes.sort_by_key(|(x, y, z)| x + y + z);
+        for (x, y, z) in cubes {
+            let faces = [
+                ("#BBBBBB", [point(x, y, z + 1), point(x + 1, y, z + 1), point(x + 1, y + 1, z + 1), point(x, y + 1, z + 1)]),
+                ("#777777", [point(x + 1, y, z), point(x + 1, y + 1, z), point(x + 1, y + 1, z + 1), point(x + 1, y, z + 1)]),
+                ("#444444", [point(x, y + 1, z), point(x + 1, y + 1, z), point(x + 1, y + 1, z + 1), point(x, y + 1, z + 1)]),
+            ];
+            for (colour, corners) in faces {
+                canvas_ctx.set_fill_style_str(colour);
+                canvas_ctx.begin_path();
+                canvas_ctx.move_to(corners[0].0, corners[0].1);
+                for (x, y) in &corners[1..] {
+                    canvas_ctx.line_to(*x, *y);
+                }
+                canvas_ctx.close_path();
+                canvas_ctx.fill();
+            }
+        }
+    }
+
+    fn colour(&self, x: usize, y: usize) -> String {
+        if let Some(line) = &self.line {
+            // the view shows the middle of the row, and the diagram from
+            // the top
+            return match line.get(x, y - self.size / 2) {
+                Cell::Dead => "#FFFFFF".to_string(),
+                Cell::Alive => "#000000".to_string(),
+                Cell::State(state) => history::state_colour(state, line.rule().colours()),
+            };
+        }
+        let cell = self.stepper.get(x, y);
+        // rule files may give their own colours
+        let table = self.stepper.rule().table();
+        if let Some([r, g, b]) = table.and_then(|table| table.colour(cell.state())).filter(|_| cell != Cell::Dead) {
+            format!("#{:02X}{:02X}{:02X}", r, g, b)
+        } else if cell == Cell::Alive {
+            match self.ramp {
+                Some(ramp) => ramp.colour(self.history.age(x, y)),
+                None => "#000000".to_string(),
+            }
+        } else if let Cell::State(state) = cell {
+            history::state_colour(state, self.stepper.rule().states())
+        } else if self.show_history && self.history.ever_alive(x, y) {
+            history::HISTORY_COLOUR.to_string()
+        } else {
+            "#FFFFFF".to_string()
+        }
+    }
+
+    fn state_name(&self, state: u8) -> String {
+        match state {
+            _ if self.line.is_some() => format!("colour {}", state),
+            _ if self.stepper.rule() == Rule::wireworld() => WIREWORLD_STATES[state as usize].to_string(),
+            0 => "dead".to_string(),
+            1 => "alive".to_string(),
+            _ => format!("state {}", state),
+        }
     }
 
-    fn mutate(&mut self, event: MouseEvent, ctx: &Context<Self>) {
-        let canvas: Element = self.node_ref.cast().unwrap();
+    // Draws a box around each object in view, labelled with its apgcode.
+    fn outline_objects(&mut self, canvas_ctx: &CanvasRenderingContext2d) -> Result<(), Error> {
+        let size = self.size;
+        let pitch = (self.cell_size + 1) as f64;
+        let rule = self.stepper.rule();
+        let objects = objects::objects(&self.stepper.cells(), self.stepper.side(), OBJECT_DISTANCE, &rule, OBJECT_GENERATIONS, Grouping::Touching)?;
+        canvas_ctx.set_stroke_style_str("#FF0000");
+        canvas_ctx.set_fill_style_str("#FF0000");
+        canvas_ctx.set_font("16px sans-serif");
+        for object in objects {
+            // clip to the visible part of the grid
+            let left = object.left.max(size / 2);
+            let top = object.top.max(size / 2);
+            let right = (object.left + object.pattern.width).min(3 * size / 2);
+            let bottom = (object.top + object.pattern.height).min(3 * size / 2);
+            if left >= right || top >= bottom {
+                continue;
+            }
+            let x = (left - size / 2) as f64 * pitch + self.row_offset(top - size / 2) + 1.0;
+            let y = (top - size / 2) as f64 * pitch + 1.0;
+            let height = (bottom - top) as f64 * pitch;
+            canvas_ctx.stroke_rect(x, y, (right - left) as f64 * pitch, height);
+            let key = (rule, object.pattern.clone());
+            let label = match self.labels.get_mut(&key) {
+                Some((label, used)) => {
+                    *used = self.frame;
+                    label.clone()
+                }
+                None => {
+                    let label = object.label(&rule)?;
+                    self.labels.insert(key, (label.clone(), self.frame));
+                    label
+                }
+            };
+            canvas_ctx
+                .fill_text(&label, x + 4.0, y + height + 18.0)
+                .map_err(|_| Error::RenderContext("could not draw an object label".to_string()))?;
+        }
+        // the grid is drawn in the default black
+        canvas_ctx.set_stroke_style_str("#000000");
+        let frame = self.frame;
+        self.labels.retain(|_, (_, used)| *used + LABEL_FRAMES > frame);
+        self.frame += 1;
+        Ok(())
+    }
+
+    // Shows the space-time diagram of a one-dimensional rule, starting from a
+    // single cell in the middle, or carrying on from the current row if one
+    // is already shown.
+    fn set_line_rule(&mut self, rule: LineRule, ctx: &Context<Self>) -> Result<(), Error> {
+        self.lattice = None;
+        match &mut self.line {
+            Some(line) => line.set_rule(rule),
+            None => {
+                let side = self.stepper.side();
+                let mut line = SpaceTime::new(rule, side, side / 2)?;
+                line.set(side / 2, 1);
+                self.line = Some(line);
+            }
+        }
+        self.brush = None;
+        ctx.link().send_message(Msg::Render(true));
+        Ok(())
+    }
+
+    // Shows a lattice under a 3D rule, starting from a random soup, or
+    // carrying on with the current lattice if one is already shown.
+    fn set_3d_rule(&mut self, rule: Rule3D, ctx: &Context<Self>) -> Result<(), Error> {
+        self.line = None;
+        match &mut self.lattice {
+            Some(lattice) => lattice.set_rule(rule),
+            None => {
+                let mut lattice = Lattice::new(self.size, rule)?;
+                lattice.load(&life3d::soup(SOUP_3D_SEED, self.size, SOUP_3D_EXTENT, SOUP_3D_DENSITY))?;
+                self.lattice = Some(lattice);
+            }
+        }
+        self.brush = None;
+        ctx.link().send_message(Msg::Render(true));
+        Ok(())
+    }
+
+    // Switches to another rule, keeping the current cells and engine.
+    fn set_rule(&mut self, rule: Rule, ctx: &Context<Self>) -> Result<(), Error> {
+        self.line = None;
+        self.lattice = None;
+        let mut stepper = self.stepper.kind().create(self.stepper.side(), rule)?;
+        stepper.load(&self.stepper.cells())?;
+        stepper.load_previous(&self.stepper.previous())?;
+        stepper.set_generation(self.stepper.generation());
+        stepper.set_reversed(self.stepper.is_reversed());
+        self.stepper = stepper;
+        self.brush = None;
+        ctx.link().send_message(Msg::Render(true));
+        Ok(())
+    }
+
+    // Replaces the grid with a library pattern, the object named by an
+    // apgcode or an RLE pattern, centred, switching to the pattern's rule if
+    // it has its own.
+    fn load(&mut self, name: &str, ctx: &Context<Self>) -> Result<(), Error> {
+        let pattern = library::get(name.trim())
+            .ok_or_else(|| Error::Parse(format!("no pattern or apgcode called '{}'", name.trim())))?;
+        if let Some(rule) = library::rule(name.trim()).filter(|rule| *rule != self.stepper.rule()) {
+            self.set_rule(rule, ctx)?;
+        }
+        self.line = None;
+        self.lattice = None;
+        self.stepper.load(&pattern.centred(self.stepper.side()))?;
+        self.history = History::new(&self.stepper.cells(), self.stepper.side());
+        ctx.link().send_message(Msg::Render(true));
+        Ok(())
+    }
+
+    fn mutate(&mut self, event: MouseEvent, ctx: &Context<Self>) -> Result<(), Error> {
+        let canvas: Element = self
+            .node_ref
+            .cast()
+            .ok_or_else(|| Error::RenderContext("the canvas is not mounted".to_string()))?;
         let bounding_rect = canvas.get_bounding_client_rect();
-        let xscale = (self.size * self.cell_size + 100) as f64 / bounding_rect.width();
-        let yscale = (self.size * self.cell_size + 100) as f64 / bounding_rect.height();
+        let (width, height) = self.extent();
+        let xscale = width as f64 / bounding_rect.width();
+        let yscale = height as f64 / bounding_rect.height();
         let canvas_left = (event.client_x() as f64 - bounding_rect.left()) * xscale;
         let canvas_top = (event.client_y() as f64 - bounding_rect.top()) * yscale;
         let r = cmp::min((canvas_top / (self.cell_size + 1) as f64).floor() as usize, self.size - 1);
+        let canvas_left = canvas_left - self.row_offset(r);
+        if canvas_left < 0.0 {
+            return Ok(());
+        }
         let c = cmp::min((canvas_left / (self.cell_size + 1) as f64).floor() as usize, self.size - 1);
-        self.cell_vec[(self.size / 2 + c) + 2 * self.size * (self.size / 2 + r)] = match self.cell_vec[(self.size / 2 + c) + 2 * self.size * (self.size / 2 + r)] {
-            Cell::Alive => Cell::Dead,
-            Cell::Dead => Cell::Alive,
+        if let Some(lattice) = &mut self.lattice {
+            // only a single layer can be clicked on
+            if let Some(z) = self.slice {
+                let cell = if lattice.get(c, r, z) == Cell::Alive { Cell::Dead } else { Cell::Alive };
+                lattice.set(c, r, z, cell);
+                ctx.link().send_message(Msg::Render(true));
+            }
+            return Ok(());
+        }
+        let (x, y) = (self.size / 2 + c, self.size / 2 + r);
+        if let Some(line) = &mut self.line {
+            // only the newest row can change
+            let state = match (self.brush, line.row()[x]) {
+                (Some(brush), _) => brush.state(),
+                (None, 0) => 1,
+                (None, _) => 0,
+            };
+            line.set(x, state);
+            ctx.link().send_message(Msg::Render(true));
+            return Ok(());
+        }
+        let cell = match (self.brush, self.stepper.get(x, y)) {
+            (Some(brush), _) => brush,
+            (None, Cell::Dead) => Cell::Alive,
+            (None, Cell::Alive | Cell::State(_)) => Cell::Dead,
         };
+        self.stepper.set(x, y, cell);
+        self.history.set(x, y, cell);
         ctx.link().send_message(Msg::Render(true));
+        Ok(())
     }
 
 }
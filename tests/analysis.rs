@@ -0,0 +1,99 @@
+use gameoflife::analysis::{self, Analysis, Classification};
+use gameoflife::library;
+use gameoflife::pattern::Pattern;
+use gameoflife::random;
+use gameoflife::rule::Rule;
+use gameoflife::Cell;
+
+fn classify(pattern: &Pattern) -> Analysis {
+    analysis::classify(pattern, &Rule::default(), 128).unwrap()
+}
+
+fn named(name: &str) -> Analysis {
+    classify(&library::get(name).unwrap())
+}
+
+#[test]
+fn still_lifes_and_oscillators() {
+    assert_eq!(named("block").classification, Classification::StillLife);
+    assert_eq!(named("blinker").classification, Classification::Oscillator { period: 2 });
+    assert_eq!(named("pentadecathlon").classification, Classification::Oscillator { period: 15 });
+    assert_eq!(named("pentadecathlon").classification.to_string(), "p15 oscillator");
+}
+
+#[test]
+fn spaceships_report_period_and_displacement() {
+    let glider = named("glider");
+    assert_eq!(glider.classification, Classification::Spaceship { period: 4, dx: 1, dy: 1 });
+    assert_eq!(glider.classification.to_string(), "c/4 diagonal spaceship");
+
+    let lwss = named("lightweight spaceship");
+    assert_eq!(lwss.classification, Classification::Spaceship { period: 4, dx: -2, dy: 0 });
+    assert_eq!(lwss.classification.to_string(), "c/2 orthogonal spaceship");
+}
+
+#[test]
+fn patterns_that_settle_report_when() {
+    // a tromino becomes a block after one generation
+    let tromino = classify(&Pattern::from_plaintext("OO\nO.\n").unwrap());
+    assert_eq!(tromino, Analysis { classification: Classification::StillLife, settled_at: 1 });
+
+    let single = classify(&Pattern::from_plaintext("O\n").unwrap());
+    assert_eq!(single, Analysis { classification: Classification::Dies, settled_at: 1 });
+}
+
+#[test]
+fn methuselahs_are_unknown_within_a_short_run() {
+    assert_eq!(named("r-pentomino").classification, Classification::Unknown);
+}
+
+#[test]
+fn other_rules_are_respected() {
+    // under HighLife a lone blinker is still a p2 oscillator, but under B3/S2
+    // a block dies
+    let highlife: Rule = "B36/S23".parse().unwrap();
+    let blinker = library::get("blinker").unwrap();
+    assert_eq!(analysis::classify(&blinker, &highlife, 16).unwrap().classification, Classification::Oscillator { period: 2 });
+
+    let block = library::get("block").unwrap();
+    let rule: Rule = "B3/S2".parse().unwrap();
+    assert_eq!(analysis::classify(&block, &rule, 16).unwrap().classification, Classification::Dies);
+}
+
+#[test]
+fn patterns_that_drift_before_settling_are_followed() {
+    // this soup wanders for a hundred generations before leaving a glider,
+    // so it is moved back to the middle of the grid on the way
+    let soup = random::soup(37, 5, 5, 0.4);
+    let highlife: Rule = "B36/S23".parse().unwrap();
+    let analysis = analysis::classify(&soup, &highlife, 150).unwrap();
+    assert_eq!(analysis, Analysis { classification: Classification::Spaceship { period: 4, dx: 1, dy: 1 }, settled_at: 99 });
+}
+
+#[test]
+fn second_order_rules_are_classified_by_both_generations() {
+    // under B3/S23R a block goes 4, 4, 0, 4, ... as the empty generation
+    // before it comes back every third generation
+    let rule: Rule = "B3/S23R".parse().unwrap();
+    let block = library::get("block").unwrap();
+    let analysis = analysis::classify(&block, &rule, 16).unwrap();
+    assert_eq!(analysis, Analysis { classification: Classification::Oscillator { period: 3 }, settled_at: 0 });
+
+    // an empty grid is not dead while the generation before is not
+    let empty = Pattern { width: 2, height: 2, cells: vec![Cell::Dead; 4] };
+    let analysis = analysis::classify_phase(&empty, &block, 0, &rule, 16).unwrap();
+    assert_eq!(analysis.classification, Classification::Oscillator { period: 3 });
+}
+
+#[test]
+fn block_rules_repeat_in_cells_not_partitions() {
+    // every block stays as it is, so a glider is a still life even though
+    // the partition only comes back every other generation
+    let identity: Rule = "MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;15".parse().unwrap();
+    let glider = library::get("glider").unwrap();
+    for generation in [0, 1] {
+        let previous = Pattern { cells: vec![Cell::Dead; glider.cells.len()], ..glider.clone() };
+        let analysis = analysis::classify_phase(&glider, &previous, generation, &identity, 16).unwrap();
+        assert_eq!(analysis, Analysis { classification: Classification::StillLife, settled_at: 0 });
+    }
+}
@@ -0,0 +1,79 @@
+use gameoflife::analysis::Classification;
+use gameoflife::apgcode;
+use gameoflife::error::Error;
+use gameoflife::library;
+use gameoflife::pattern::Pattern;
+use gameoflife::rule::Rule;
+
+fn encode(pattern: &Pattern) -> Option<String> {
+    apgcode::encode(pattern, &Rule::default()).unwrap()
+}
+
+#[test]
+fn library_patterns_have_their_catagolue_codes() {
+    for (name, code) in [
+        ("block", "xs4_33"),
+        ("blinker", "xp2_7"),
+        ("glider", "xq4_153"),
+        ("lightweight spaceship", "xq4_6frc"),
+        ("pentadecathlon", "xp15_4r4z4r4"),
+    ] {
+        assert_eq!(encode(&library::get(name).unwrap()).as_deref(), Some(code), "{}", name);
+    }
+}
+
+#[test]
+fn codes_do_not_depend_on_phase_or_orientation() {
+    let glider = library::get("glider").unwrap();
+    for orientation in glider.orientations() {
+        assert_eq!(encode(&orientation).as_deref(), Some("xq4_153"));
+    }
+    let vertical = Pattern::from_plaintext("O\nO\nO\n").unwrap();
+    assert_eq!(encode(&vertical).as_deref(), Some("xp2_7"));
+}
+
+#[test]
+fn patterns_are_named_by_what_they_settle_into() {
+    // a pre-block becomes a block
+    let pre_block = Pattern::from_plaintext("OO\nO.\n").unwrap();
+    assert_eq!(encode(&pre_block).as_deref(), Some("xs4_33"));
+    assert_eq!(encode(&Pattern::from_plaintext("O\n").unwrap()), None);
+}
+
+#[test]
+fn decoding_inverts_encoding() {
+    for code in ["xs4_33", "xp2_7", "xq4_153", "xs6_696", "xs12_g8o653z11", "xq4_6frc", "xp15_4r4z4r4", "xs14_g88m952z121"] {
+        let pattern = apgcode::decode(code).unwrap();
+        assert_eq!(encode(&pattern).as_deref(), Some(code));
+    }
+    assert_eq!(apgcode::decode("xs4_33").unwrap(), library::get("block").unwrap());
+}
+
+#[test]
+fn wechsler_codes_round_trip_with_long_gaps() {
+    let text = "O...............................................O\n\n\n\n\n\n\n.O\n";
+    let pattern = Pattern::from_plaintext(text).unwrap();
+    let wechsler = apgcode::to_wechsler(&pattern);
+    assert_eq!(wechsler, "1yzy41z04");
+    assert_eq!(apgcode::from_wechsler(&wechsler).unwrap(), pattern);
+}
+
+#[test]
+fn unknown_objects_have_no_code() {
+    let block = library::get("block").unwrap();
+    assert_eq!(apgcode::encode_as(&block, &Rule::default(), Classification::Unknown).unwrap(), None);
+    assert_eq!(encode(&library::get("r-pentomino").unwrap()), None);
+}
+
+#[test]
+fn malformed_codes_are_parse_errors() {
+    for code in ["", "33", "xs4", "xr4_33", "xsfour_33", "xs5_33", "xp2_7!", "xs4_y"] {
+        assert!(matches!(apgcode::decode(code), Err(Error::Parse(_))), "{}", code);
+    }
+}
+
+#[test]
+fn library_lookups_accept_apgcodes() {
+    assert_eq!(library::get("xp2_7"), library::get("blinker").map(|blinker| blinker.transposed()));
+    assert_eq!(library::get("not a pattern"), None);
+}
@@ -0,0 +1,56 @@
+use gameoflife::census::{self, Census, CensusOptions};
+
+fn census(soups: usize, seed: u64) -> Census {
+    Census::run(CensusOptions { seed, soups, ..CensusOptions::default() }).unwrap()
+}
+
+#[test]
+fn full_soups_settle_into_known_objects() {
+    // a full 2x2 soup is a block, and a full 3x3 one becomes a traffic light
+    let block = Census::run(CensusOptions { soups: 1, soup_size: 2, density: 1.0, ..CensusOptions::default() }).unwrap();
+    assert_eq!(block.counts.into_iter().collect::<Vec<_>>(), [("xs4_33".to_string(), 1)]);
+
+    let traffic_light = Census::run(CensusOptions { soups: 1, soup_size: 3, density: 1.0, ..CensusOptions::default() }).unwrap();
+    assert_eq!(traffic_light.counts.into_iter().collect::<Vec<_>>(), [("xp2_7".to_string(), 4)]);
+}
+
+#[test]
+fn common_objects_have_canonical_codes() {
+    let census = census(20, 1);
+    for code in ["xs4_33", "xp2_7", "xs6_696", "xs5_253", "xs7_2596", "xq4_153"] {
+        assert!(census.counts.contains_key(code), "{} missing from {:?}", code, census.counts);
+    }
+    // every phase and orientation of the glider gets the same code
+    assert!(census.counts.keys().all(|code| !code.starts_with("xq4_") || code == "xq4_153"));
+}
+
+#[test]
+fn censuses_are_reproducible_from_their_seed() {
+    let first = census(5, 42);
+    let second = census(5, 42);
+    assert_eq!(first.counts, second.counts);
+    assert_eq!(first.report(), second.report());
+    assert_ne!(first.counts, census(5, 43).counts);
+
+    // neighbouring seeds share no soups
+    let seeds: Vec<u64> = (0..100).map(|index| census::soup_seed(42, index)).collect();
+    assert!((0..100).all(|index| !seeds.contains(&census::soup_seed(43, index))));
+}
+
+#[test]
+fn soups_that_do_not_settle_are_pathological() {
+    let census = Census::run(CensusOptions { soups: 3, max_generations: 10, ..CensusOptions::default() }).unwrap();
+    assert_eq!(census.counts[census::PATHOLOGICAL], 3);
+}
+
+#[test]
+fn report_lists_counts_and_rare_soups() {
+    let census = census(3, 7);
+    let report = census.report();
+    for (code, count) in &census.counts {
+        assert!(report.contains(&format!("{:<40} {}", code, count)));
+    }
+    let (rare, _) = census.counts.iter().find(|(_, count)| **count <= census.options.rare).unwrap();
+    assert!(report.contains(&format!("# {} (", rare)));
+    assert!(report.contains(&format!("## soup {}", census.samples[rare][0])));
+}
@@ -0,0 +1,359 @@
+use gameoflife::life3d::{Lattice, Rule3D};
+use gameoflife::node::Node;
+use gameoflife::reference;
+use gameoflife::rule::{Chances, Neighbourhood, Range, Rule, Shape, CERTAIN};
+use gameoflife::stepper::StepperKind;
+use gameoflife::Cell;
+use proptest::prelude::*;
+
+fn rule() -> impl Strategy<Value = Rule> {
+    (any::<[bool; 9]>(), any::<[bool; 9]>()).prop_map(|(birth, survival)| {
+        let birth: Vec<usize> = (0..9).filter(|count| birth[*count]).collect();
+        let survival: Vec<usize> = (0..9).filter(|count| survival[*count]).collect();
+        Rule::new(&birth, &survival)
+    })
+}
+
+fn generations_rule() -> impl Strategy<Value = Rule> {
+    (rule(), 3..8u8).prop_map(|(rule, states)| {
+        Rule::generations(&rule.births().collect::<Vec<_>>(), &rule.survivals().collect::<Vec<_>>(), states).unwrap()
+    })
+}
+
+// Hensel's letters for each neighbour count.
+const LETTERS: [&str; 9] = ["", "ce", "ceaikn", "ceaiknjqry", "ceaiknjqrytwz", "ceaiknjqry", "ceaikn", "ce", ""];
+
+// Conditions such as `2ac3-k`, with any letters for each count.
+fn conditions() -> impl Strategy<Value = String> {
+    prop::collection::vec((any::<bool>(), any::<bool>(), any::<u16>()), 9).prop_map(|choices| {
+        let mut text = String::new();
+        for (count, (present, negated, letters)) in choices.into_iter().enumerate() {
+            if !present {
+                continue;
+            }
+            text.push_str(&count.to_string());
+            let chosen: String = LETTERS[count].chars().enumerate().filter(|(i, _)| letters >> i & 1 == 1).map(|(_, c)| c).collect();
+            if negated && !chosen.is_empty() {
+                text.push('-');
+            }
+            text.push_str(&chosen);
+        }
+        text
+    })
+}
+
+fn isotropic_rule() -> impl Strategy<Value = Rule> {
+    (conditions(), conditions()).prop_map(|(birth, survival)| format!("B{}/S{}", birth, survival).parse().unwrap())
+}
+
+// A totalistic rule on any part of the Moore neighbourhood.
+fn neighbourhood_rule() -> impl Strategy<Value = Rule> {
+    let neighbourhood = prop_oneof![
+        Just(Neighbourhood::VonNeumann),
+        Just(Neighbourhood::Hexagonal),
+        any::<u8>().prop_map(Neighbourhood::from_mask),
+    ];
+    (rule(), neighbourhood).prop_map(|(rule, neighbourhood)| rule.with_neighbourhood(neighbourhood))
+}
+
+// A Larger than Life rule with a small radius, possibly with dying states.
+fn range_rule() -> impl Strategy<Value = Rule> {
+    let shape = prop_oneof![Just(Shape::Moore), Just(Shape::VonNeumann), Just(Shape::Circular)];
+    (1..=4usize, shape, any::<bool>(), 0..40usize, 0..20usize, 0..40usize, 0..20usize, prop_oneof![Just(2u8), 3..6u8]).prop_map(
+        |(radius, shape, middle, birth, birth_span, survival, survival_span, states)| {
+            let range = Range { radius, shape, middle, birth: (birth, birth + birth_span), survival: (survival, survival + survival_span) };
+            Rule::larger_than_life(range, states).unwrap()
+        },
+    )
+}
+
+// A block rule with any new block for each block.
+fn block_rule() -> impl Strategy<Value = Rule> {
+    any::<[u8; 16]>().prop_map(|blocks| Rule::block(blocks.map(|block| block % 16)))
+}
+
+// A Life-like or isotropic rule left to chance.
+fn stochastic_rule() -> impl Strategy<Value = Rule> {
+    (prop_oneof![rule(), isotropic_rule()], 0..=CERTAIN, prop_oneof![Just(0), 0..CERTAIN / 10], any::<u64>())
+        .prop_map(|(rule, birth, noise, seed)| rule.stochastic(Chances { birth, noise, seed }).unwrap())
+}
+
+fn rule_3d() -> impl Strategy<Value = Rule3D> {
+    (0..=26usize, 0..8usize, 1..=26usize, 0..8usize)
+        .prop_map(|(survival, survival_span, birth, birth_span)| {
+            Rule3D::new((survival, (survival + survival_span).min(26)), (birth, (birth + birth_span).min(26))).unwrap()
+        })
+}
+
+fn grid(side: usize) -> impl Strategy<Value = Vec<Cell>> {
+    prop::collection::vec(prop::bool::weighted(0.4), side * side)
+        .prop_map(|bits| bits.into_iter().map(|alive| if alive { Cell::Alive } else { Cell::Dead }).collect())
+}
+
+// A 4 or 8 cube of cells, x first, then y, then z.
+fn cube() -> impl Strategy<Value = (usize, Vec<Cell>)> {
+    prop::sample::select(vec![4, 8]).prop_flat_map(|side| {
+        let cells = prop::collection::vec(prop::bool::weighted(0.3), side * side * side)
+            .prop_map(|bits| bits.into_iter().map(|alive| if alive { Cell::Alive } else { Cell::Dead }).collect());
+        (Just(side), cells)
+    })
+}
+
+fn sized_grid(sides: impl Strategy<Value = usize>) -> impl Strategy<Value = (usize, Vec<Cell>)> {
+    sides.prop_flat_map(|side| (Just(side), grid(side)))
+}
+
+// A grid and a Generations rule whose states it uses.
+fn generations_grid(sides: impl Strategy<Value = usize>) -> impl Strategy<Value = (usize, Vec<Cell>, Rule)> {
+    (sides, generations_rule()).prop_flat_map(|(side, rule)| {
+        let cells = prop::collection::vec(0..rule.states(), side * side)
+            .prop_map(|states| states.into_iter().map(Cell::from_state).collect());
+        (Just(side), cells, Just(rule))
+    })
+}
+
+fn reference_run(cells: Vec<Cell>, side: usize, rule: &Rule, generations: usize) -> Vec<Cell> {
+    let mut cells = cells;
+    for generation in 0..generations {
+        cells = reference::step_at(&cells, side, rule, generation);
+    }
+    cells
+}
+
+fn power_of_two() -> impl Strategy<Value = usize> {
+    prop::sample::select(vec![4, 8, 16, 32])
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(48))]
+
+    #[test]
+    fn evolve_matches_reference((side, cells) in sized_grid(power_of_two()), rule in rule()) {
+        let expected = reference::step(&cells, side, &rule);
+        let actual = Node::new(cells).unwrap().evolve(&rule);
+        prop_assert_eq!(actual, expected, "under {}", rule);
+    }
+
+    #[test]
+    fn evolve_matches_reference_for_conway((side, cells) in sized_grid(power_of_two())) {
+        let rule = Rule::default();
+        let expected = reference::step(&cells, side, &rule);
+        let actual = Node::new(cells).unwrap().evolve(&rule);
+        prop_assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn quadtree_stepper_matches_reference((side, cells) in sized_grid(power_of_two()), rule in rule(), generations in 1..4usize) {
+        let mut stepper = StepperKind::Quadtree.create(side, rule).unwrap();
+        stepper.load(&cells).unwrap();
+        stepper.step(generations);
+
+        let mut expected = cells;
+        for _ in 0..generations {
+            expected = reference::step(&expected, side, &rule);
+        }
+        prop_assert_eq!(stepper.cells(), expected);
+    }
+
+    // long steps go on by powers of two at once while the pattern is clear
+    // of the edge, and a generation at a time once it is near
+    #[test]
+    fn quadtree_jumps_match_dense(patch in grid(8), rule in prop_oneof![rule(), range_rule()], generations in 1..200usize) {
+        let side = 64;
+        let mut cells = vec![Cell::Dead; side * side];
+        for y in 0..8 {
+            for x in 0..8 {
+                cells[28 + x + (28 + y) * side] = patch[x + y * 8];
+            }
+        }
+        let mut grids = vec![];
+        for kind in StepperKind::ALL {
+            let mut stepper = kind.create(side, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            stepper.step(generations);
+            prop_assert_eq!(stepper.generation(), generations);
+            grids.push(stepper.cells());
+        }
+        prop_assert_eq!(&grids[0], &grids[1], "under {}", rule);
+    }
+
+    // states beyond the rule's are dead on both engines, whether loaded or set
+    #[test]
+    fn engines_agree_on_states_the_rule_lacks(
+        (side, states) in power_of_two().prop_flat_map(|side| (Just(side), prop::collection::vec(0..12u8, side * side))),
+        rule in prop_oneof![rule(), generations_rule()],
+        generations in 0..3usize,
+    ) {
+        let cells: Vec<Cell> = states.into_iter().map(Cell::from_state).collect();
+        let mut grids = vec![];
+        for kind in StepperKind::ALL {
+            let mut stepper = kind.create(side, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            stepper.set(0, 0, Cell::State(rule.states()));
+            prop_assert_eq!(stepper.get(0, 0), Cell::Dead);
+            stepper.step(generations);
+            grids.push(stepper.cells());
+        }
+        prop_assert_eq!(&grids[0], &grids[1]);
+    }
+
+    #[test]
+    fn dense_stepper_matches_reference((side, cells) in sized_grid(1..140usize), rule in rule(), generations in 1..6usize) {
+        let mut stepper = StepperKind::Dense.create(side, rule).unwrap();
+        stepper.load(&cells).unwrap();
+        stepper.step(generations);
+
+        let mut expected = cells;
+        for _ in 0..generations {
+            expected = reference::step(&expected, side, &rule);
+        }
+        prop_assert_eq!(stepper.population(), expected.iter().filter(|cell| **cell == Cell::Alive).count());
+        prop_assert_eq!(stepper.cells(), expected);
+    }
+
+    #[test]
+    fn evolve_matches_reference_for_generations((side, cells, rule) in generations_grid(power_of_two())) {
+        let expected = reference::step(&cells, side, &rule);
+        let actual = Node::new(cells).unwrap().evolve(&rule);
+        prop_assert_eq!(actual, expected, "under {}", rule);
+    }
+
+    #[test]
+    fn steppers_match_reference_for_generations((side, cells, rule) in generations_grid(power_of_two()), generations in 1..6usize) {
+        let expected = reference_run(cells.clone(), side, &rule, generations);
+        for kind in StepperKind::ALL {
+            let mut stepper = kind.create(side, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            stepper.step(generations);
+            prop_assert_eq!(stepper.cells(), expected.clone(), "{} under {}", kind, rule);
+        }
+    }
+
+    #[test]
+    fn dense_stepper_matches_reference_for_generations((side, cells, rule) in generations_grid(1..140usize), generations in 1..6usize) {
+        let mut stepper = StepperKind::Dense.create(side, rule).unwrap();
+        stepper.load(&cells).unwrap();
+        stepper.step(generations);
+        prop_assert_eq!(stepper.cells(), reference_run(cells, side, &rule, generations));
+    }
+
+    #[test]
+    fn evolve_matches_reference_for_isotropic_rules((side, cells) in sized_grid(power_of_two()), rule in isotropic_rule()) {
+        let expected = reference::step(&cells, side, &rule);
+        let actual = Node::new(cells).unwrap().evolve(&rule);
+        prop_assert_eq!(actual, expected, "under {}", rule);
+    }
+
+    #[test]
+    fn steppers_match_reference_for_isotropic_rules((side, cells) in sized_grid(1..140usize), rule in isotropic_rule(), generations in 1..4usize) {
+        let expected = reference_run(cells.clone(), side, &rule, generations);
+        let mut kinds = vec![StepperKind::Dense];
+        if side.is_power_of_two() && side >= 4 {
+            kinds.push(StepperKind::Quadtree);
+        }
+        for kind in kinds {
+            let mut stepper = kind.create(side, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            stepper.step(generations);
+            prop_assert_eq!(stepper.cells(), expected.clone(), "{} under {}", kind, rule);
+        }
+    }
+
+    #[test]
+    fn steppers_match_reference_for_other_neighbourhoods((side, cells) in sized_grid(1..140usize), rule in neighbourhood_rule(), generations in 1..4usize) {
+        let expected = reference_run(cells.clone(), side, &rule, generations);
+        let mut kinds = vec![StepperKind::Dense];
+        if side.is_power_of_two() && side >= 4 {
+            kinds.push(StepperKind::Quadtree);
+        }
+        for kind in kinds {
+            let mut stepper = kind.create(side, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            stepper.step(generations);
+            prop_assert_eq!(stepper.cells(), expected.clone(), "{} under {}", kind, rule);
+        }
+    }
+
+    #[test]
+    fn steppers_match_reference_for_larger_than_life((side, cells) in sized_grid(prop::sample::select(vec![8, 16, 32, 64])), rule in range_rule(), generations in 1..4usize) {
+        let expected = reference_run(cells.clone(), side, &rule, generations);
+        for kind in StepperKind::ALL {
+            let mut stepper = kind.create(side, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            stepper.step(generations);
+            prop_assert_eq!(stepper.cells(), expected.clone(), "{} under {}", kind, rule);
+        }
+    }
+
+    #[test]
+    fn steppers_match_reference_for_block_rules((side, cells) in sized_grid(power_of_two()), rule in block_rule(), generations in 1..6usize) {
+        let expected = reference_run(cells.clone(), side, &rule, generations);
+        for kind in StepperKind::ALL {
+            let mut stepper = kind.create(side, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            stepper.step(generations);
+            prop_assert_eq!(stepper.cells(), expected.clone(), "{} under {}", kind, rule);
+        }
+    }
+
+    #[test]
+    fn dense_stepper_matches_reference_for_block_rules((side, cells) in sized_grid(1..140usize), rule in block_rule(), generations in 1..6usize) {
+        let mut stepper = StepperKind::Dense.create(side, rule).unwrap();
+        stepper.load(&cells).unwrap();
+        stepper.step(generations);
+        prop_assert_eq!(stepper.cells(), reference_run(cells, side, &rule, generations));
+    }
+
+    #[test]
+    fn steppers_match_reference_for_second_order_rules((side, cells) in sized_grid(power_of_two()), rule in prop_oneof![rule(), isotropic_rule()], generations in 1..6usize) {
+        let rule = rule.second_order().unwrap();
+        let (mut previous, mut expected) = (vec![Cell::Dead; side * side], cells.clone());
+        for _ in 0..generations {
+            let next = reference::step_second_order(&previous, &expected, side, &rule);
+            previous = std::mem::replace(&mut expected, next);
+        }
+        for kind in StepperKind::ALL {
+            let mut stepper = kind.create(side, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            stepper.step(generations);
+            prop_assert_eq!(stepper.cells(), expected.clone(), "{} under {}", kind, rule);
+            prop_assert_eq!(stepper.previous(), previous.clone(), "{} under {}", kind, rule);
+        }
+    }
+
+    #[test]
+    fn steppers_match_reference_for_stochastic_rules((side, cells) in sized_grid(power_of_two()), rule in stochastic_rule(), generations in 1..6usize) {
+        let expected = reference_run(cells.clone(), side, &rule, generations);
+        for kind in StepperKind::ALL {
+            let mut stepper = kind.create(side, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            stepper.step(generations);
+            prop_assert_eq!(stepper.cells(), expected.clone(), "{} under {}", kind, rule);
+        }
+    }
+
+    #[test]
+    fn dense_stepper_matches_reference_for_stochastic_rules((side, cells) in sized_grid(1..140usize), rule in stochastic_rule(), generations in 1..4usize) {
+        let mut stepper = StepperKind::Dense.create(side, rule).unwrap();
+        stepper.load(&cells).unwrap();
+        stepper.step(generations);
+        prop_assert_eq!(stepper.cells(), reference_run(cells, side, &rule, generations));
+    }
+
+    #[test]
+    fn lattice_matches_reference((side, cells) in cube(), rule in rule_3d(), generations in 1..4usize) {
+        let mut lattice = Lattice::new(side, rule).unwrap();
+        lattice.load(&cells).unwrap();
+        lattice.step(generations);
+        let mut expected = cells;
+        for _ in 0..generations {
+            expected = reference::step_3d(&expected, side, &rule);
+        }
+        prop_assert_eq!(lattice.cells(), expected, "under {}", rule);
+    }
+
+    #[test]
+    fn rule_round_trips_through_display(rule in prop_oneof![rule(), generations_rule(), isotropic_rule(), neighbourhood_rule(), range_rule(), block_rule(), rule().prop_map(|rule| rule.second_order().unwrap()), stochastic_rule()]) {
+        prop_assert_eq!(rule.to_string().parse::<Rule>(), Ok(rule));
+    }
+}
@@ -0,0 +1,91 @@
+use gameoflife::elementary::{self, LineRule, SpaceTime};
+use gameoflife::Cell;
+
+const WIDTH: usize = 41;
+
+// A diagram of `height` rows started from a single cell in the middle.
+fn from_one_cell(rule: &str, height: usize) -> SpaceTime {
+    let mut line = SpaceTime::new(rule.parse().unwrap(), WIDTH, height).unwrap();
+    line.set(WIDTH / 2, 1);
+    line
+}
+
+fn text(row: &[u8]) -> String {
+    row.iter().map(|state| char::from(b'0' + state)).collect::<String>().trim_matches('0').to_string()
+}
+
+#[test]
+fn rules_are_written_as_in_golly() {
+    assert_eq!("W30".parse::<LineRule>(), Ok(LineRule::Elementary(30)));
+    assert_eq!("T777K3".parse::<LineRule>(), Ok(LineRule::Totalistic { colours: 3, code: 777 }));
+    for rule in ["W0", "W110", "W255", "T777K3", "T0K2", "T1099511627775K7"] {
+        assert_eq!(rule.parse::<LineRule>().unwrap().to_string(), rule);
+    }
+    assert!(elementary::is_line_rule("W30") && elementary::is_line_rule("t777k3"));
+    assert!(!elementary::is_line_rule("WireWorld") && !elementary::is_line_rule("B3/S23"));
+}
+
+#[test]
+fn rule_30_grows_its_familiar_triangle() {
+    let mut line = from_one_cell("W30", 8);
+    let mut rows = vec![text(line.row())];
+    for _ in 0..3 {
+        line.step(1);
+        rows.push(text(line.row()));
+    }
+    assert_eq!(rows, ["1", "111", "11001", "1101111"]);
+    assert_eq!(line.generation(), 3);
+}
+
+#[test]
+fn rule_90_draws_pascals_triangle_mod_2() {
+    let mut line = from_one_cell("W90", 32);
+    for n in 0..16 {
+        for k in 0..=n {
+            // cell 2k - n from the middle is C(n, k) mod 2
+            let x = WIDTH / 2 + 2 * k - n;
+            assert_eq!(line.row()[x], (k & n == k) as u8, "row {} cell {}", n, k);
+        }
+        line.step(1);
+    }
+}
+
+#[test]
+fn totalistic_rules_look_up_the_sum_in_their_code() {
+    // 777 is 1001210 in base 3, so sums 1 to 6 give colours 1, 2, 1, 0, 0, 1
+    let rule: LineRule = "T777K3".parse().unwrap();
+    assert_eq!((0..=6).map(|sum| rule.next(0, 0, sum)).collect::<Vec<_>>(), [0, 1, 2, 1, 0, 0, 1]);
+    let mut line = from_one_cell("T777K3", 8);
+    line.step(1);
+    assert_eq!(text(line.row()), "111");
+    line.step(1);
+    assert_eq!(text(line.row()), "12121");
+}
+
+#[test]
+fn the_diagram_scrolls_once_it_is_full() {
+    let mut line = from_one_cell("W30", 4);
+    let mut rows = vec![line.row().to_vec()];
+    assert_eq!(line.get(WIDTH / 2, 0), Cell::Alive);
+    assert_eq!(line.get(WIDTH / 2, 1), Cell::Dead);
+    for _ in 0..10 {
+        line.step(1);
+        rows.push(line.row().to_vec());
+    }
+    let cells = line.cells();
+    assert_eq!(cells.len(), WIDTH * 4);
+    for (y, row) in rows[7..].iter().enumerate() {
+        let expected: Vec<Cell> = row.iter().map(|state| Cell::from_state(*state)).collect();
+        assert_eq!(&cells[y * WIDTH..(y + 1) * WIDTH], &expected[..], "row {}", y);
+    }
+}
+
+#[test]
+fn changing_rule_keeps_the_row() {
+    let mut line = from_one_cell("T777K3", 8);
+    line.step(2);
+    line.set_rule("W90".parse().unwrap());
+    assert_eq!(text(line.row()), "10101");
+    line.step(1);
+    assert_eq!(text(line.row()), "1000001");
+}
@@ -0,0 +1,132 @@
+use gameoflife::elementary::{LineRule, SpaceTime};
+use gameoflife::error::Error;
+use gameoflife::explorer::Family;
+use gameoflife::life3d::{Lattice, Rule3D};
+use gameoflife::metrics::{Metrics, MetricsOptions};
+use gameoflife::node::Node;
+use gameoflife::pattern::{Pattern, MAX_SIDE};
+use gameoflife::predecessor::{self, Boundary, PredecessorOptions};
+use gameoflife::rule::Rule;
+use gameoflife::ruletable;
+use gameoflife::sat::Cnf;
+use gameoflife::search::{self, SearchOptions};
+use gameoflife::stepper::StepperKind;
+use gameoflife::Cell;
+
+#[test]
+fn node_rejects_grids_without_a_power_of_two_side() {
+    for length in [0, 1, 3, 9, 36, 100, 1000] {
+        assert!(matches!(Node::new(vec![Cell::Dead; length]), Err(Error::InvalidDimensions(_))), "{} cells", length);
+    }
+    for length in [4, 16, 64, 256] {
+        assert!(Node::new(vec![Cell::Dead; length]).is_ok(), "{} cells", length);
+    }
+}
+
+#[test]
+fn steppers_reject_invalid_sides_and_grids() {
+    for side in [0, 2, 3, 12] {
+        assert!(matches!(StepperKind::Quadtree.create(side, Rule::default()), Err(Error::InvalidDimensions(_))));
+    }
+    assert!(matches!(StepperKind::Dense.create(0, Rule::default()), Err(Error::InvalidDimensions(_))));
+
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(16, Rule::default()).unwrap();
+        assert!(matches!(stepper.load(&[Cell::Alive; 15]), Err(Error::InvalidDimensions(_))));
+    }
+
+    for side in [0, 2, 12] {
+        assert!(matches!(Lattice::new(side, "4555".parse().unwrap()), Err(Error::InvalidDimensions(_))));
+    }
+    let mut lattice = Lattice::new(4, "4555".parse().unwrap()).unwrap();
+    assert!(matches!(lattice.load(&[Cell::Dead; 16]), Err(Error::InvalidDimensions(_))));
+
+    let rule = LineRule::Elementary(30);
+    assert!(matches!(SpaceTime::new(rule, 0, 4), Err(Error::InvalidDimensions(_))));
+    let mut line = SpaceTime::new(rule, 8, 4).unwrap();
+    assert!(matches!(line.load(&[0; 7]), Err(Error::InvalidDimensions(_))));
+
+    for (width, period) in [(0, 1), (4, 0), (4, 9)] {
+        let options = SearchOptions { width, period, ..SearchOptions::default() };
+        assert!(matches!(search::search(&options), Err(Error::InvalidDimensions(_))));
+    }
+
+    for options in [MetricsOptions { soups: 0, ..MetricsOptions::default() }, MetricsOptions { soup_size: 200, ..MetricsOptions::default() }] {
+        assert!(matches!(Metrics::measure(options), Err(Error::InvalidDimensions(_))));
+    }
+    assert!(matches!(line.load(&[0, 0, 2, 0, 0, 0, 0, 0]), Err(Error::Parse(_))));
+}
+
+#[test]
+fn rle_larger_than_the_largest_grid_is_rejected() {
+    let too_big = MAX_SIDE + 1;
+    let headers = [format!("x = {}, y = 1\no!", too_big), format!("x = 1, y = {}\no!", too_big), format!("x = {}, y = {}\no!", usize::MAX, usize::MAX)];
+    let runs = [format!("{}o!", too_big), format!("o{}$o!", too_big), format!("{}oo!", MAX_SIDE)];
+    for rle in headers.iter().chain(&runs) {
+        assert!(matches!(Pattern::from_rle(rle), Err(Error::InvalidDimensions(_))), "{}", rle);
+    }
+    let widest = Pattern::from_rle(&format!("x = {}, y = 1\n{}o!", MAX_SIDE, MAX_SIDE)).unwrap();
+    assert_eq!((widest.width, widest.height), (MAX_SIDE, 1));
+}
+
+#[test]
+fn malformed_input_is_a_parse_error() {
+    assert!(matches!(Pattern::from_plaintext(".O.\n.X.\n"), Err(Error::Parse(_))));
+    assert!(matches!("B39/S23".parse::<Rule>(), Err(Error::Parse(_))));
+    assert!(matches!("Life".parse::<Rule>(), Err(Error::Parse(_))));
+    for rule in ["/2/1", "/2/x", "/2/300", "B2/S/3", "B2/C3/S", "B1a/S", "B0c/S", "B2-/S", "B3k-/S", "B5/SV", "B2a/SH", "B3/S23@XY", "B3/S23@123", "B3/S23V/", "R0,C0,M0,S1..2,B3..3,NM", "R17,C0,M0,S1..2,B3..3,NM", "R2,C0,M0,S1..2,NM", "R2,C0,M2,S1..2,B3..3,NM", "R2,C0,M0,S1-2,B3..3,NM", "MS,D0;1;2", "MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;16", "MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;15;0", "B3/S23RR", "R", "B3/S23:", "B3/S23:P1.5", "B3/S23:P-0.1", "B3/S23:Px", "B3/S23:X1", "B3/S23:P0.5:P0.4", "B3/S23:S-1"] {
+        assert!(matches!(rule.parse::<Rule>(), Err(Error::Parse(_))), "{}", rule);
+    }
+    for rule in ["W256", "W", "W-1", "T777", "T777K1", "T777K8", "T2187K3", "Tx2K2"] {
+        assert!(matches!(rule.parse::<LineRule>(), Err(Error::Parse(_))), "{}", rule);
+    }
+    for rule in ["455", "45555", "4,5,6", "5,4,1,1", "27,27,1,1", "45x5"] {
+        assert!(matches!(rule.parse::<Rule3D>(), Err(Error::Parse(_))), "{}", rule);
+    }
+    assert!(matches!("hashlife".parse::<StepperKind>(), Err(Error::Parse(_))));
+    assert!(matches!("mutation".parse::<Family>(), Err(Error::Parse(_))));
+    assert!(matches!("wrapped".parse::<Boundary>(), Err(Error::Parse(_))));
+    for dimacs in ["1 -2 0\n", "p cnf 2\n1 0\n", "p dnf 2 1\n1 0\n", "p cnf 2 1\n1 x 0\n", "p cnf 1 1\n2 0\n", "p cnf 2 2\n1 0\n"] {
+        assert!(matches!(Cnf::from_dimacs(dimacs), Err(Error::Parse(_))), "{}", dimacs);
+    }
+    let wireworld = Pattern { width: 2, height: 1, cells: vec![Cell::Alive, Cell::State(2)] };
+    assert!(matches!(predecessor::encode(&wireworld, &PredecessorOptions::default()), Err(Error::Parse(_))));
+    for rle in ["2o$2q!", "x = a, y = 2\n2o$2o!", "99999999999999999999o!"] {
+        assert!(matches!(Pattern::from_rle(rle), Err(Error::Parse(_))), "{}", rle);
+    }
+
+    let table = "n_states:3\nneighborhood:Moore\nsymmetries:none\nvar a={0,1}\n";
+    let rule_files = [
+        format!("@TABLE\n{}", table),
+        "@RULE Empty\n".to_string(),
+        format!("@RULE Short\n@TABLE\n{}0,1,1", table),
+        format!("@RULE State\n@TABLE\n{}0,3,0,0,0,0,0,0,0,1", table),
+        format!("@RULE Output\n@TABLE\n{}0,1,0,0,0,0,0,0,0,a", table),
+        "@RULE Root\n@TREE\nnum_states=2\nnum_neighbors=4\nnum_nodes=1\n1 0 1\n".to_string(),
+        "@RULE Child\n@TREE\nnum_states=2\nnum_neighbors=4\nnum_nodes=1\n2 0 5\n".to_string(),
+        format!("@RULE Colours\n@TABLE\n{}@COLORS\n1 255 0\n", table),
+    ];
+    for file in rule_files {
+        assert!(matches!(ruletable::parse(&file), Err(Error::Parse(_))), "{}", file);
+    }
+}
+
+#[test]
+fn other_rule_families_are_reported_as_unsupported() {
+    // second-order and stochastic rules need exactly two states
+    for rule in ["/2/3", "WireWorld", "B3/S23R", "B3/S23:N0.1", "R2,C0,M0,S1..2,B3..3,NM"] {
+        let options = SearchOptions { rule: rule.parse().unwrap(), ..SearchOptions::default() };
+        assert!(matches!(search::search(&options), Err(Error::UnsupportedRule(_))), "{}", rule);
+        let options = PredecessorOptions { rule: rule.parse().unwrap(), ..PredecessorOptions::default() };
+        let blinker = Pattern::from_plaintext("OOO\n").unwrap();
+        assert!(matches!(predecessor::predecessors(&blinker, &options), Err(Error::UnsupportedRule(_))), "{}", rule);
+    }
+    for rule in ["R5,C0,M1,S34..58,B34..45,N+", "/2/3R", "WireWorldR", "MS,D15;1;2;3;4;5;6;7;8;9;10;11;12;13;14;0R", "/2/3:P0.5", "B3/S23R:P0.5", "WireWorld:N0.1"] {
+        assert!(matches!(rule.parse::<Rule>(), Err(Error::UnsupportedRule(_))), "{}", rule);
+    }
+
+    for setting in ["neighborhood:triangularMoore", "symmetries:rotate6"] {
+        let file = format!("@RULE Unsupported\n@TABLE\nn_states:2\n{}\n0,1,0,0,0,0,0,0,0,1\n", setting);
+        assert!(matches!(ruletable::parse(&file), Err(Error::UnsupportedRule(_))), "{}", setting);
+    }
+}
@@ -0,0 +1,77 @@
+use gameoflife::explorer::{self, Family, Preview, PREVIEWS};
+use gameoflife::metrics::Behaviour;
+use gameoflife::random::Random;
+use gameoflife::rule::{Neighbourhood, Rule};
+
+// The counts in one of the rules and not the other.
+fn differences(a: &Rule, b: &Rule) -> usize {
+    let births = (0..=8).filter(|count| a.births().any(|c| c == *count) != b.births().any(|c| c == *count));
+    let survivals = (0..=8).filter(|count| a.survivals().any(|c| c == *count) != b.survivals().any(|c| c == *count));
+    births.count() + survivals.count()
+}
+
+#[test]
+fn random_rules_come_from_the_seed() {
+    let current = Rule::default();
+    let rules = explorer::rules(Family::Random, &current, PREVIEWS, 5).unwrap();
+    assert_eq!(rules.len(), PREVIEWS);
+    assert_eq!(explorer::rules(Family::Random, &current, PREVIEWS, 5).unwrap(), rules);
+    assert_ne!(explorer::rules(Family::Random, &current, PREVIEWS, 6).unwrap(), rules);
+    for (i, rule) in rules.iter().enumerate() {
+        assert!(!rule.births().any(|count| count == 0), "{}", rule);
+        assert!(!rules[..i].contains(rule) && *rule != current, "{}", rule);
+    }
+}
+
+#[test]
+fn mutations_change_one_count() {
+    let current: Rule = "/2/3".parse().unwrap();
+    let rules = explorer::rules(Family::Mutations, &current, PREVIEWS, 1).unwrap();
+    assert_eq!(rules.len(), PREVIEWS);
+    for rule in &rules {
+        assert_eq!(differences(rule, &current), 1, "{}", rule);
+        assert_eq!(rule.states(), 3);
+    }
+
+    // von Neumann rules have only nine counts to change
+    let current: Rule = "B1/S1V".parse().unwrap();
+    let rules = explorer::rules(Family::Mutations, &current, PREVIEWS, 1).unwrap();
+    assert_eq!(rules.len(), 9);
+    assert!(rules.iter().all(|rule| rule.neighbourhood() == Neighbourhood::VonNeumann));
+
+    let mut random = Random::new(1);
+    let reversible: Rule = "B3/S23R".parse().unwrap();
+    assert!(explorer::mutation(&reversible, &mut random).unwrap().is_second_order());
+}
+
+#[test]
+fn rules_without_counts_cannot_mutate() {
+    let mut random = Random::new(1);
+    for rule in ["WireWorld", "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15", "B2-a/S12"] {
+        let rule: Rule = rule.parse().unwrap();
+        assert!(explorer::mutation(&rule, &mut random).is_err(), "{}", rule);
+    }
+}
+
+#[test]
+fn previews_start_from_the_same_soup() {
+    let (mut life, mut highlife) = (Preview::new(Rule::default(), 3).unwrap(), Preview::new("B36/S23".parse().unwrap(), 3).unwrap());
+    assert_eq!(life.stepper().cells(), highlife.stepper().cells());
+    assert!(life.stepper().population() > 0);
+    for _ in 0..20 {
+        life.step();
+        highlife.step();
+    }
+    assert_eq!(life.stepper().generation(), 20);
+    assert_ne!(life.stepper().cells(), highlife.stepper().cells());
+}
+
+#[test]
+fn previews_guess_how_their_rule_behaves() {
+    let life = Preview::new(Rule::default(), 1).unwrap();
+    assert_eq!(life.behaviour, Behaviour::Complex);
+    assert!(life.is_lively());
+    let dies = Preview::new("B3/S".parse().unwrap(), 1).unwrap();
+    assert_eq!(dies.behaviour, Behaviour::Stable);
+    assert!(!dies.is_lively());
+}
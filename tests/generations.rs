@@ -0,0 +1,67 @@
+use gameoflife::analysis::{self, Classification};
+use gameoflife::pattern::Pattern;
+use gameoflife::rule::Rule;
+use gameoflife::stepper::StepperKind;
+use gameoflife::Cell;
+
+const A: Cell = Cell::Alive;
+const D: Cell = Cell::Dead;
+
+fn brians_brain() -> Rule {
+    "/2/3".parse().unwrap()
+}
+
+#[test]
+fn generations_rules_parse_in_either_notation() {
+    let rule = brians_brain();
+    assert_eq!(rule, Rule::generations(&[2], &[], 3).unwrap());
+    assert_eq!("B2/S/C3".parse::<Rule>().unwrap(), rule);
+    assert_eq!(rule.states(), 3);
+    assert_eq!(rule.to_string(), "/2/3");
+
+    let star_wars: Rule = "345/2/4".parse().unwrap();
+    assert_eq!(star_wars, Rule::generations(&[2], &[3, 4, 5], 4).unwrap());
+    assert_eq!(star_wars.to_string(), "345/2/4");
+
+    // two states is an ordinary life-like rule
+    assert_eq!("23/3/2".parse::<Rule>().unwrap(), Rule::default());
+}
+
+#[test]
+fn dying_cells_pass_through_each_state() {
+    let star_wars: Rule = "345/2/4".parse().unwrap();
+    assert_eq!(star_wars.next(Cell::Alive, 0b1111), Cell::Alive);
+    assert_eq!(star_wars.next(Cell::Alive, 0b1), Cell::State(2));
+    assert_eq!(star_wars.next(Cell::State(2), 0b11), Cell::State(3));
+    assert_eq!(star_wars.next(Cell::State(3), 0b11), Cell::Dead);
+    assert_eq!(star_wars.next(Cell::Dead, 0b11), Cell::Alive);
+}
+
+#[test]
+fn dying_cells_block_births() {
+    let rule = brians_brain();
+    for kind in StepperKind::ALL {
+        // (3, 3) has two live neighbours, so is born unless it is still dying
+        for (centre, expected) in [(Cell::Dead, Cell::Alive), (Cell::State(2), Cell::Dead)] {
+            let mut stepper = kind.create(8, rule).unwrap();
+            stepper.set(2, 2, Cell::Alive);
+            stepper.set(4, 4, Cell::Alive);
+            stepper.set(3, 3, centre);
+            stepper.step(1);
+            assert_eq!(stepper.get(3, 3), expected, "{}", kind);
+            assert_eq!(stepper.get(2, 2), Cell::State(2), "{}", kind);
+        }
+    }
+}
+
+#[test]
+fn brians_brain_has_a_speed_of_light_spaceship() {
+    // two live cells pushed along by the two dying cells behind them
+    let ship = Pattern { width: 2, height: 2, cells: vec![A, A, Cell::State(2), Cell::State(2)] };
+    let analysis = analysis::classify(&ship, &brians_brain(), 16).unwrap();
+    assert_eq!(analysis.classification, Classification::Spaceship { period: 1, dx: 0, dy: -1 });
+
+    // without them it fizzles out into a cloud of debris
+    let pair = Pattern { width: 2, height: 2, cells: vec![A, A, D, D] };
+    assert_ne!(analysis::classify(&pair, &brians_brain(), 16).unwrap().classification, analysis.classification);
+}
@@ -0,0 +1,152 @@
+use gameoflife::library;
+use gameoflife::pattern::{self, Pattern};
+use gameoflife::rule::Rule;
+use gameoflife::stepper::{Stepper, StepperKind};
+use gameoflife::Cell;
+
+fn start(kind: StepperKind, name: &str, side: usize) -> Box<dyn Stepper> {
+    let pattern = library::get(name).unwrap();
+    let mut stepper = kind.create(side, Rule::default()).unwrap();
+    stepper.load(&pattern.centred(side)).unwrap();
+    stepper
+}
+
+fn snapshot(stepper: &dyn Stepper) -> Pattern {
+    Pattern::from_grid(&stepper.cells(), stepper.side())
+}
+
+fn position(stepper: &dyn Stepper) -> (usize, usize) {
+    let (left, top, _, _) = pattern::bounds(&stepper.cells(), stepper.side()).unwrap();
+    (left, top)
+}
+
+// Runs until generation `until`, returning the population at every generation.
+fn populations(stepper: &mut dyn Stepper, until: usize) -> Vec<usize> {
+    let mut populations = vec![stepper.population()];
+    for _ in 0..until {
+        stepper.step(1);
+        populations.push(stepper.population());
+    }
+    populations
+}
+
+#[test]
+fn block_is_a_still_life() {
+    for kind in StepperKind::ALL {
+        let mut stepper = start(kind, "block", 16);
+        let initial = stepper.cells();
+        for _ in 0..4 {
+            stepper.step(1);
+            assert_eq!(stepper.cells(), initial, "{} engine", kind);
+        }
+    }
+}
+
+#[test]
+fn blinker_has_period_two() {
+    for kind in StepperKind::ALL {
+        let mut stepper = start(kind, "blinker", 16);
+        let initial = stepper.cells();
+        stepper.step(1);
+        assert_ne!(stepper.cells(), initial, "{} engine", kind);
+        assert_eq!(stepper.population(), 3, "{} engine", kind);
+        assert_eq!(snapshot(stepper.as_ref()), Pattern::from_plaintext("O\nO\nO\n").unwrap());
+        stepper.step(1);
+        assert_eq!(stepper.cells(), initial, "{} engine", kind);
+    }
+}
+
+#[test]
+fn glider_moves_one_cell_diagonally_every_four_generations() {
+    for kind in StepperKind::ALL {
+        let mut stepper = start(kind, "glider", 32);
+        let shape = snapshot(stepper.as_ref());
+        let (x, y) = position(stepper.as_ref());
+        for period in 1..=3 {
+            stepper.step(4);
+            assert_eq!(snapshot(stepper.as_ref()), shape, "{} engine", kind);
+            assert_eq!(position(stepper.as_ref()), (x + period, y + period), "{} engine", kind);
+        }
+    }
+}
+
+#[test]
+fn gosper_gun_has_period_thirty_and_emits_a_glider_each_period() {
+    let gun = library::get("gosper glider gun").unwrap();
+    // in the top left corner, the gliders head across the whole grid and
+    // only reach the far edge after 180 generations
+    let (side, corner) = (64, 1);
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(side, Rule::default()).unwrap();
+        for y in 0..gun.height {
+            for x in 0..gun.width {
+                stepper.set(corner + x, corner + y, gun.get(x, y));
+            }
+        }
+        let gun_area = |stepper: &dyn Stepper| -> Vec<Cell> {
+            (corner..corner + gun.height)
+                .flat_map(|y| (corner..corner + gun.width).map(move |x| (x, y)))
+                .map(|(x, y)| stepper.get(x, y))
+                .collect()
+        };
+
+        let mut counts = vec![stepper.population()];
+        let mut phases = vec![];
+        for generation in 1..=180 {
+            stepper.step(1);
+            counts.push(stepper.population());
+            if generation % 30 == 0 {
+                phases.push(gun_area(stepper.as_ref()));
+            }
+        }
+        assert_eq!(counts[0], 36, "{} engine", kind);
+        // from generation 60 on the gun repeats itself every period
+        assert!(phases[1..].iter().all(|phase| *phase == phases[1]), "{} engine", kind);
+        // the population grows, but never by more than one glider per period
+        assert!(counts[30] > counts[0], "{} engine", kind);
+        for t in 0..=150 {
+            assert_eq!(counts[t + 30], counts[t] + 5, "{} engine at generation {}", kind, t);
+        }
+    }
+}
+
+// The long runs step straight to the generations they check, which the
+// quadtree engine takes by powers of two while the pattern is clear of the
+// edge.
+#[test]
+fn r_pentomino_stabilises_at_generation_1103_with_population_116() {
+    let mut checkpoints = vec![];
+    for kind in StepperKind::ALL {
+        let mut stepper = start(kind, "r-pentomino", 1024);
+        assert_eq!(stepper.population(), 5, "{} engine", kind);
+        stepper.step(200);
+        checkpoints.push(stepper.cells());
+        stepper.step(902);
+        assert_ne!(stepper.population(), 116, "{} engine", kind);
+        stepper.step(1);
+        assert_eq!(stepper.population(), 116, "{} engine", kind);
+
+        // what is left is still lifes, period-2 oscillators and escaping gliders
+        let settled = stepper.cells();
+        let later = populations(stepper.as_mut(), 8);
+        assert!(later.iter().all(|population| *population == 116), "{} engine", kind);
+        stepper.step(4);
+        assert_ne!(stepper.cells(), settled, "{} engine", kind);
+    }
+    // the engines agree along the way too
+    assert_eq!(checkpoints[0], checkpoints[1]);
+}
+
+#[test]
+fn acorn_stabilises_at_generation_5206_with_population_633() {
+    for kind in StepperKind::ALL {
+        let mut stepper = start(kind, "acorn", 4096);
+        assert_eq!(stepper.population(), 7, "{} engine", kind);
+        stepper.step(5205);
+        assert_ne!(stepper.population(), 633, "{} engine", kind);
+        stepper.step(1);
+        assert_eq!(stepper.population(), 633, "{} engine", kind);
+        let later = populations(stepper.as_mut(), 8);
+        assert!(later.iter().all(|population| *population == 633), "{} engine", kind);
+    }
+}
@@ -0,0 +1,59 @@
+use gameoflife::pattern::Pattern;
+use gameoflife::random;
+use gameoflife::reference;
+use gameoflife::rule::Rule;
+
+const LETTERS: [&str; 9] = ["", "ce", "ceaikn", "ceaiknjqry", "ceaiknjqrytwz", "ceaiknjqry", "ceaikn", "ce", ""];
+
+#[test]
+fn each_neighbourhood_has_exactly_one_letter() {
+    for (count, letters) in LETTERS.iter().enumerate().filter(|(_, letters)| !letters.is_empty()) {
+        let rules: Vec<Rule> = letters.chars().map(|letter| format!("B{}{}/S", count, letter).parse().unwrap()).collect();
+        for neighbourhood in (0..=255u8).filter(|n| n.count_ones() as usize == count) {
+            let matches = rules.iter().filter(|rule| rule.is_born(neighbourhood)).count();
+            assert_eq!(matches, 1, "{:08b}", neighbourhood);
+        }
+        let all: Rule = format!("B{}{}/S", count, letters).parse().unwrap();
+        assert_eq!(all, Rule::new(&[count], &[]));
+    }
+}
+
+#[test]
+fn letters_pick_out_arrangements() {
+    // neighbours are numbered clockwise from north
+    let just_friends: Rule = "B2-a/S12".parse().unwrap();
+    assert!(!just_friends.is_totalistic());
+    assert!(!just_friends.is_born(0b0000_0011), "two adjacent neighbours");
+    assert!(just_friends.is_born(0b0000_0101), "north and east");
+    assert!(just_friends.is_born(0b0001_0001), "north and south");
+    assert!(just_friends.survives(0b0000_0011));
+
+    let rule: Rule = "B3-k/S23".parse().unwrap();
+    assert!(!rule.is_born(0b0010_0101), "north, east and south-west");
+    assert!(rule.is_born(0b0000_0111));
+    assert_eq!(rule.survivals().collect::<Vec<_>>(), vec![2, 3]);
+}
+
+#[test]
+fn rules_print_in_hensel_notation() {
+    for rule in ["B2-a/S12", "B3-k/S23", "B2ce3ai4t/S1e2k5", "B3/S23", "B2a/S/C3"] {
+        assert_eq!(rule.parse::<Rule>().unwrap().to_string(), rule);
+    }
+    // the shorter of the two ways of listing letters is used
+    assert_eq!("B2ceaik/S".parse::<Rule>().unwrap().to_string(), "B2-n/S");
+    assert_eq!("B3-cekijnqry/S".parse::<Rule>().unwrap().to_string(), "B3a/S");
+}
+
+#[test]
+fn isotropic_rules_commute_with_rotations_and_reflections() {
+    const SIDE: usize = 24;
+    for rule in ["B2-a/S12", "B3-k/S23", "B2ce3ai4t/S1e2k5", "B3n4w5y/S2-c3q6i"] {
+        let rule: Rule = rule.parse().unwrap();
+        let soup = random::soup(1, 12, 12, 0.4);
+        let stepped = Pattern { width: SIDE, height: SIDE, cells: reference::step(&soup.centred(SIDE), SIDE, &rule) };
+        for (orientation, expected) in soup.orientations().into_iter().zip(stepped.orientations()) {
+            let actual = reference::step(&orientation.centred(SIDE), SIDE, &rule);
+            assert_eq!(actual, expected.cells, "under {}", rule);
+        }
+    }
+}
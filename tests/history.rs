@@ -0,0 +1,62 @@
+use gameoflife::history::{History, Ramp};
+use gameoflife::library;
+use gameoflife::stepper::StepperKind;
+use gameoflife::rule::Rule;
+use gameoflife::Cell;
+
+const SIDE: usize = 16;
+
+fn run(name: &str, generations: usize) -> History {
+    let mut stepper = StepperKind::Dense.create(SIDE, Rule::default()).unwrap();
+    stepper.load(&library::get(name).unwrap().centred(SIDE)).unwrap();
+    let mut history = History::new(&stepper.cells(), SIDE);
+    for _ in 0..generations {
+        stepper.step(1);
+        history.step(&stepper.cells());
+    }
+    history
+}
+
+fn envelope(history: &History) -> usize {
+    (0..SIDE).flat_map(|y| (0..SIDE).map(move |x| (x, y))).filter(|(x, y)| history.ever_alive(*x, *y)).count()
+}
+
+#[test]
+fn ages_count_generations_alive() {
+    // the blinker's centre never dies, while its ends die and are reborn
+    let history = run("blinker", 5);
+    assert_eq!(history.age(7, 7), 6);
+    assert_eq!(history.age(7, 6), 1);
+    assert_eq!(history.age(6, 7), 0);
+
+    let block = run("block", 10);
+    assert_eq!(block.age(7, 7), 11);
+}
+
+#[test]
+fn history_covers_every_cell_ever_alive() {
+    // a blinker's envelope is a plus sign
+    assert_eq!(envelope(&run("blinker", 4)), 5);
+    // a glider leaves a trail
+    assert!(envelope(&run("glider", 8)) > envelope(&run("glider", 4)));
+}
+
+#[test]
+fn edits_count_as_births_and_deaths() {
+    let mut history = run("block", 3);
+    history.set(7, 7, Cell::Dead);
+    assert_eq!(history.age(7, 7), 0);
+    assert!(history.ever_alive(7, 7));
+    history.set(0, 0, Cell::Alive);
+    assert_eq!(history.age(0, 0), 1);
+    assert!(history.ever_alive(0, 0));
+}
+
+#[test]
+fn ramps_run_from_newborn_to_old() {
+    let ramp = Ramp { from: [0xFF, 0x00, 0x00], to: [0x00, 0x00, 0xFF], span: 10 };
+    assert_eq!(ramp.colour(1), "#FF0000");
+    assert_eq!(ramp.colour(6), "#800080");
+    assert_eq!(ramp.colour(11), "#0000FF");
+    assert_eq!(ramp.colour(1000), "#0000FF");
+}
@@ -0,0 +1,80 @@
+use gameoflife::random;
+use gameoflife::reference;
+use gameoflife::rule::{Range, Rule, Shape};
+use gameoflife::stepper::StepperKind;
+use gameoflife::Cell;
+
+fn run_all(rule: &Rule, cells: &[Cell], side: usize, generations: usize) -> Vec<Cell> {
+    let mut expected = cells.to_vec();
+    for _ in 0..generations {
+        expected = reference::step(&expected, side, rule);
+    }
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(side, *rule).unwrap();
+        stepper.load(cells).unwrap();
+        stepper.step(generations);
+        assert!(stepper.cells() == expected, "{} disagrees with the reference under {}", kind, rule);
+    }
+    expected
+}
+
+#[test]
+fn rules_parse_and_print() {
+    let bosco: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+    let range = bosco.range().unwrap();
+    assert_eq!(range, Range { radius: 5, shape: Shape::Moore, middle: true, birth: (34, 45), survival: (34, 58) });
+    assert_eq!(bosco.radius(), 5);
+    assert_eq!(bosco.states(), 2);
+    assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+
+    // two states may also be written C2
+    assert_eq!("r5,c2,m1,s34..58,b34..45,nm".parse::<Rule>().unwrap(), bosco);
+    let generations: Rule = "R3,C4,M0,S2..7,B5..9,NC".parse().unwrap();
+    assert_eq!(generations.states(), 4);
+    assert_eq!(generations.to_string(), "R3,C4,M0,S2..7,B5..9,NC");
+}
+
+#[test]
+fn neighbourhoods_have_the_expected_sizes() {
+    let size = |radius, shape| Range { radius, shape, middle: false, birth: (0, 0), survival: (0, 0) }.size();
+    assert_eq!(size(1, Shape::Moore), 8);
+    assert_eq!(size(5, Shape::Moore), 120);
+    assert_eq!(size(10, Shape::Moore), 440);
+    assert_eq!(size(1, Shape::VonNeumann), 4);
+    assert_eq!(size(2, Shape::VonNeumann), 12);
+    // a 5x5 square without its corners
+    assert_eq!(size(2, Shape::Circular), 20);
+
+    // a single cell is born into every cell of its neighbourhood
+    for shape in ["NM", "NN", "NC"] {
+        let rule: Rule = format!("R2,C0,M0,S0..0,B1..1,{}", shape).parse().unwrap();
+        let mut cells = vec![Cell::Dead; 16 * 16];
+        cells[8 + 8 * 16] = Cell::Alive;
+        let next = run_all(&rule, &cells, 16, 1);
+        let population = next.iter().filter(|cell| **cell == Cell::Alive).count();
+        assert_eq!(population, rule.range().unwrap().size() + 1, "{}", rule);
+    }
+}
+
+#[test]
+fn radius_one_with_the_middle_counted_is_life() {
+    let life_like: Rule = "R1,C0,M1,S3..4,B3..3,NM".parse().unwrap();
+    let side = 64;
+    let soup = random::soup(7, 32, 32, 0.4).centred(side);
+    let mut life = soup.clone();
+    for _ in 0..8 {
+        life = reference::step(&life, side, &Rule::default());
+    }
+    assert_eq!(run_all(&life_like, &soup, side, 8), life);
+}
+
+#[test]
+fn quadtree_leaves_grow_with_the_radius() {
+    // larger radii need larger leaves, and sides of two or more leaves go
+    // through the nine-way recombination
+    for (rule, side) in [("R5,C0,M1,S34..58,B34..45,NM", 64), ("R10,C0,M1,S120..230,B120..170,NM", 128), ("R4,C3,M0,S10..30,B12..25,NC", 32)] {
+        let rule: Rule = rule.parse().unwrap();
+        let soup = random::soup(3, side / 2, side / 2, 0.5).centred(side);
+        run_all(&rule, &soup, side, 3);
+    }
+}
@@ -0,0 +1,122 @@
+use gameoflife::life3d::{self, Lattice, Octree, Rule3D};
+use gameoflife::reference;
+use gameoflife::Cell;
+
+const SIDE: usize = 16;
+
+#[test]
+fn rules_are_written_in_bays_notation() {
+    let rule: Rule3D = "4555".parse().unwrap();
+    assert_eq!((rule.survival(), rule.birth()), ((4, 5), (5, 5)));
+    for text in ["4555", "5766", "0000", "10,21,12,13"] {
+        assert_eq!(text.parse::<Rule3D>().unwrap().to_string(), text);
+    }
+    assert_eq!("5,7,6,6".parse::<Rule3D>().unwrap().to_string(), "5766");
+    assert!(life3d::is_3d_rule("5766") && life3d::is_3d_rule("10,21,12,13"));
+    assert!(!life3d::is_3d_rule("B3/S23") && !life3d::is_3d_rule("23/3") && !life3d::is_3d_rule("W30"));
+}
+
+#[test]
+fn the_octree_holds_the_cells() {
+    let cells = life3d::soup(2, 8, 8, 0.5);
+    let tree = Octree::new(&cells, 8).unwrap();
+    assert_eq!(tree.population(), cells.iter().filter(|cell| **cell == Cell::Alive).count());
+    for z in 0..8 {
+        for y in 0..8 {
+            for x in 0..8 {
+                assert_eq!(tree.get(x, y, z), cells[x + y * 8 + z * 64], "({}, {}, {})", x, y, z);
+            }
+        }
+    }
+}
+
+#[test]
+fn equal_cubes_are_shared() {
+    let cells = life3d::soup(4, 8, 8, 0.5);
+    let tree = Octree::new(&cells, 8).unwrap();
+    assert_eq!(Octree::new(&cells, 8).unwrap(), tree);
+    let flipped = if tree.get(1, 2, 3) == Cell::Alive { Cell::Dead } else { Cell::Alive };
+    let edited = tree.set(1, 2, 3, flipped);
+    assert_ne!(edited, tree);
+    assert_eq!(edited.set(1, 2, 3, tree.get(1, 2, 3)), tree);
+    assert_eq!(tree.cells(), cells);
+}
+
+#[test]
+fn evolve_matches_the_reference() {
+    for side in [2, 4, 8, 16] {
+        for rule in ["4555", "5766", "0,3,1,3", "2,3,0,1"] {
+            let rule: Rule3D = rule.parse().unwrap();
+            let cells = life3d::soup(9, side, side, 0.4);
+            let expected = reference::step_3d(&cells, side, &rule);
+            assert_eq!(Octree::new(&cells, side).unwrap().evolve(&rule), expected, "side {} under {}", side, rule);
+        }
+    }
+}
+
+#[test]
+fn large_sparse_lattices_match_small_ones() {
+    // a soup in the middle of a 128 cube runs as it does in a 16 cube, as
+    // long as it stays clear of the smaller one's faces
+    let rule: Rule3D = "4555".parse().unwrap();
+    let (small, large) = (16, 128);
+    let mut lattices = [small, large].map(|side| {
+        let mut lattice = Lattice::new(side, rule).unwrap();
+        lattice.load(&life3d::soup(5, side, 6, 0.4)).unwrap();
+        lattice
+    });
+    for lattice in &mut lattices {
+        lattice.step(3);
+    }
+    let offset = (large - small) / 2;
+    for z in 0..small {
+        for y in 0..small {
+            for x in 0..small {
+                assert_eq!(lattices[1].get(x + offset, y + offset, z + offset), lattices[0].get(x, y, z), "({}, {}, {})", x, y, z);
+            }
+        }
+    }
+    assert_eq!(lattices[1].population(), lattices[0].population());
+}
+
+#[test]
+fn lattices_match_the_reference() {
+    for rule in ["4555", "5766", "2,6,4,5"] {
+        let rule: Rule3D = rule.parse().unwrap();
+        let mut expected = life3d::soup(7, SIDE, 10, 0.3);
+        let mut lattice = Lattice::new(SIDE, rule).unwrap();
+        lattice.load(&expected).unwrap();
+        for generation in 1..=8 {
+            expected = reference::step_3d(&expected, SIDE, &rule);
+            lattice.step(1);
+            assert_eq!(lattice.cells(), expected, "{} at generation {}", rule, generation);
+        }
+        assert_eq!(lattice.generation(), 8);
+    }
+    // the smallest sides stop at different levels of the octree
+    for side in [4, 8, 32] {
+        let rule: Rule3D = "4555".parse().unwrap();
+        let mut expected = life3d::soup(3, side, side / 2 + 1, 0.4);
+        let mut lattice = Lattice::new(side, rule).unwrap();
+        lattice.load(&expected).unwrap();
+        for generation in 1..=4 {
+            expected = reference::step_3d(&expected, side, &rule);
+            lattice.step(1);
+            assert_eq!(lattice.cells(), expected, "side {} at generation {}", side, generation);
+        }
+    }
+}
+
+#[test]
+fn five_neighbours_bring_a_cell_to_life() {
+    // four around (8, 8, 8) in its layer and one above it
+    let mut lattice = Lattice::new(SIDE, "4555".parse().unwrap()).unwrap();
+    for (x, y, z) in [(7, 8, 8), (9, 8, 8), (8, 7, 8), (8, 9, 8), (8, 8, 9)] {
+        lattice.set(x, y, z, Cell::Alive);
+    }
+    lattice.step(1);
+    assert_eq!(lattice.get(8, 8, 8), Cell::Alive);
+    assert_eq!(lattice.slice(8)[8 + 8 * SIDE], Cell::Alive);
+    // each of the five had at most two neighbours, too few to survive
+    assert_eq!(lattice.get(7, 8, 8), Cell::Dead);
+}
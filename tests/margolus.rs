@@ -0,0 +1,91 @@
+use gameoflife::node::Node;
+use gameoflife::reference;
+use gameoflife::rule::Rule;
+use gameoflife::stepper::StepperKind;
+use gameoflife::Cell;
+
+const BILLIARD_BALLS: &str = "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15";
+const CRITTERS: &str = "MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0";
+const TRON: &str = "MS,D15;1;2;3;4;5;6;7;8;9;10;11;12;13;14;0";
+
+#[test]
+fn block_rules_print_as_they_are_written() {
+    for rule in [BILLIARD_BALLS, CRITTERS, TRON] {
+        let parsed: Rule = rule.parse().unwrap();
+        assert_eq!(parsed.to_string(), rule);
+        assert!(!parsed.is_totalistic());
+    }
+    assert_eq!(CRITTERS.parse::<Rule>().unwrap().blocks().unwrap()[0], 15);
+}
+
+#[test]
+fn billiard_balls_travel_diagonally() {
+    const SIDE: usize = 16;
+    let rule: Rule = BILLIARD_BALLS.parse().unwrap();
+    let mut cells = vec![Cell::Dead; SIDE * SIDE];
+    cells[2 + 2 * SIDE] = Cell::Alive;
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(SIDE, rule).unwrap();
+        stepper.load(&cells).unwrap();
+        // the partition shifts each generation, carrying the ball with it
+        for generation in 1..=10 {
+            stepper.step(1);
+            assert_eq!(stepper.get(2 + generation, 2 + generation), Cell::Alive, "{} at {}", kind, generation);
+            assert_eq!(stepper.population(), 1);
+            assert_eq!(stepper.generation(), generation);
+        }
+    }
+}
+
+#[test]
+fn block_rules_run_backwards_under_the_inverse_rule() {
+    // far enough from the edge that no block is cut off by it
+    const SIDE: usize = 64;
+    let rule: Rule = BILLIARD_BALLS.parse().unwrap();
+    let mut inverse = [0; 16];
+    for (block, next) in rule.blocks().unwrap().iter().enumerate() {
+        inverse[*next as usize] = block as u8;
+    }
+    let inverse = Rule::block(inverse);
+
+    let start = gameoflife::random::soup(7, 12, 12, 0.5).centred(SIDE);
+    let mut cells = start.clone();
+    for generation in 0..20 {
+        cells = reference::step_at(&cells, SIDE, &rule, generation);
+    }
+    assert_ne!(cells, start);
+    // each generation is undone with the partition it was made with
+    for generation in (0..20).rev() {
+        cells = reference::step_at(&cells, SIDE, &inverse, generation);
+    }
+    assert_eq!(cells, start);
+}
+
+#[test]
+fn converting_engines_keeps_the_partition() {
+    const SIDE: usize = 16;
+    let rule: Rule = BILLIARD_BALLS.parse().unwrap();
+    let mut cells = vec![Cell::Dead; SIDE * SIDE];
+    cells[4 + 4 * SIDE] = Cell::Alive;
+    let mut stepper = StepperKind::Dense.create(SIDE, rule).unwrap();
+    stepper.load(&cells).unwrap();
+    stepper.step(3);
+    let mut stepper = gameoflife::stepper::convert(stepper.as_ref(), StepperKind::Quadtree).unwrap();
+    stepper.step(3);
+    assert_eq!(stepper.get(10, 10), Cell::Alive);
+}
+
+#[test]
+fn block_results_do_not_stand_in_for_successors() {
+    // a node worked out block by block, then the same node as the padding
+    // around its centre, which `next` takes the successor of
+    let rule: Rule = "MS,D0;2;1;3;8;10;9;11;4;6;5;7;12;14;13;15".parse().unwrap();
+    let mut cells = vec![Cell::Dead; 8 * 8];
+    for (x, y) in [(2, 2), (3, 3), (4, 3), (5, 5)] {
+        cells[x + y * 8] = Cell::Alive;
+    }
+    let padded = Node::new(cells.clone()).unwrap();
+    assert_eq!(padded.apply_blocks(&rule).side(), 8);
+    let centre: Vec<Cell> = (2..6).flat_map(|y| (2..6).map(move |x| (x, y))).map(|(x, y)| cells[x + y * 8]).collect();
+    assert_eq!(Node::new(centre).unwrap().next(&rule, 0).side(), 4);
+}
@@ -0,0 +1,49 @@
+use gameoflife::metrics::{Behaviour, Metrics, MetricsOptions};
+
+fn measure(rule: &str, soups: usize) -> Metrics {
+    Metrics::measure(MetricsOptions { soups, rule: rule.parse().unwrap(), ..MetricsOptions::default() }).unwrap()
+}
+
+#[test]
+fn full_soups_settle_with_their_period() {
+    // a full 3x3 soup becomes a traffic light, four blinkers
+    let options = MetricsOptions { soups: 3, soup_size: 3, density: 1.0, ..MetricsOptions::default() };
+    let metrics = Metrics::measure(options).unwrap();
+    assert_eq!(metrics.periods.into_iter().collect::<Vec<_>>(), [(2, 3)]);
+    assert!(metrics.runs.iter().all(|run| run.initial == 9 && run.population == 12));
+    assert_eq!(metrics.growth, 12.0 / 9.0);
+}
+
+#[test]
+fn known_rules_get_the_expected_behaviour() {
+    let dies = measure("B3/S", 4);
+    assert_eq!(dies.behaviour, Behaviour::Stable);
+    assert_eq!((dies.density, dies.sensitivity), (0.0, 0.0));
+    assert_eq!(dies.settled(), 4);
+
+    let life = measure("B3/S23", 16);
+    assert_eq!(life.behaviour, Behaviour::Complex);
+    assert_eq!(life.behaviour.class(), 4);
+    assert!(life.transient > 100.0 && life.sensitivity > 0.5, "{}", life);
+
+    for rule in ["B3/S012345678", "B2/S"] {
+        let metrics = measure(rule, 4);
+        assert_eq!(metrics.behaviour, Behaviour::Explosive, "{}", metrics);
+        assert!(metrics.growth > 10.0, "{}", metrics);
+    }
+
+    // noise keeps Life from ever settling; it grows a soup several times
+    // over, so enough soups are run to keep it short of explosive on average
+    let noisy = measure("B3/S23:N0.001:S1", 8);
+    assert_eq!(noisy.behaviour, Behaviour::Chaotic, "{}", noisy);
+    assert!(noisy.periods.is_empty());
+}
+
+#[test]
+fn metrics_are_reproducible_from_their_seed() {
+    let first = measure("B36/S23", 4);
+    assert_eq!(first.runs, measure("B36/S23", 4).runs);
+    let other = Metrics::measure(MetricsOptions { seed: 1, soups: 4, rule: "B36/S23".parse().unwrap(), ..MetricsOptions::default() }).unwrap();
+    assert_ne!(first.runs, other.runs);
+    assert!(first.to_string().starts_with("B36/S23\tcomplex\t"), "{}", first);
+}
@@ -0,0 +1,61 @@
+use gameoflife::rule::{Neighbourhood, Rule};
+use gameoflife::stepper::StepperKind;
+use gameoflife::Cell;
+
+const SIDE: usize = 32;
+
+// Runs a single live cell in the middle of the grid and returns the
+// population after each generation.
+fn populations(rule: &str, generations: usize) -> Vec<usize> {
+    let rule: Rule = rule.parse().unwrap();
+    let mut results = Vec::new();
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(SIDE, rule).unwrap();
+        stepper.set(SIDE / 2, SIDE / 2, Cell::Alive);
+        let mut populations = Vec::new();
+        for _ in 0..generations {
+            stepper.step(1);
+            populations.push(stepper.population());
+        }
+        results.push(populations);
+    }
+    assert!(results.windows(2).all(|pair| pair[0] == pair[1]), "engines disagree under {}", rule);
+    results.remove(0)
+}
+
+#[test]
+fn neighbourhoods_parse_and_print() {
+    let von_neumann: Rule = "B1/S1234V".parse().unwrap();
+    assert_eq!(von_neumann.neighbourhood(), Neighbourhood::VonNeumann);
+    assert_eq!(von_neumann, Rule::new(&[1], &[1, 2, 3, 4]).with_neighbourhood(Neighbourhood::VonNeumann));
+    assert_eq!(von_neumann.to_string(), "B1/S1234V");
+
+    let hexagonal: Rule = "b2s34h".parse().unwrap();
+    assert_eq!(hexagonal.neighbourhood(), Neighbourhood::Hexagonal);
+    assert_eq!(hexagonal.to_string(), "B2/S34H");
+    assert_eq!("34/2/3H".parse::<Rule>().unwrap().to_string(), "34/2/3H");
+
+    // masks that name a known neighbourhood are read as that neighbourhood
+    assert_eq!("B1/S1234@55".parse::<Rule>().unwrap(), von_neumann);
+    assert_eq!("B1/S1@11".parse::<Rule>().unwrap().to_string(), "B1/S1@11");
+}
+
+#[test]
+fn growth_follows_the_shape_of_the_neighbourhood() {
+    // a single cell that never dies grows into a diamond, a hexagon or a
+    // square, one ring a generation
+    let diamond = populations("B1234/S01234V", 5);
+    assert_eq!(diamond, (1..=5).map(|n| 2 * n * n + 2 * n + 1).collect::<Vec<_>>());
+    let hexagon = populations("B123456/S0123456H", 5);
+    assert_eq!(hexagon, (1..=5).map(|n| 3 * n * n + 3 * n + 1).collect::<Vec<_>>());
+    let square = populations("B12345678/S012345678", 5);
+    assert_eq!(square, (1..=5).map(|n| (2 * n + 1) * (2 * n + 1)).collect::<Vec<_>>());
+}
+
+#[test]
+fn custom_masks_count_only_their_neighbours() {
+    // with only the north neighbour counted, a cell grows a line southwards
+    assert_eq!(populations("B1/S01@01", 6), vec![2, 3, 4, 5, 6, 7]);
+    // with north and south, a cell spreads both ways
+    assert_eq!(populations("B1/S012@11", 4), vec![3, 5, 7, 9]);
+}
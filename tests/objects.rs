@@ -0,0 +1,99 @@
+use gameoflife::library;
+use gameoflife::objects::{self, Grouping};
+use gameoflife::pattern::Pattern;
+use gameoflife::rule::Rule;
+use gameoflife::Cell;
+
+const SIDE: usize = 32;
+
+// Places each pattern in an empty grid with its top-left corner at the given
+// position.
+fn grid(placements: &[(&Pattern, usize, usize)]) -> Vec<Cell> {
+    let mut cells = vec![Cell::Dead; SIDE * SIDE];
+    for (pattern, left, top) in placements {
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                if pattern.get(x, y) == Cell::Alive {
+                    cells[(left + x) + (top + y) * SIDE] = Cell::Alive;
+                }
+            }
+        }
+    }
+    cells
+}
+
+fn labels(cells: &[Cell], grouping: Grouping) -> Vec<String> {
+    let rule = Rule::default();
+    objects::objects(cells, SIDE, 1, &rule, 8, grouping)
+        .unwrap()
+        .iter()
+        .map(|object| object.label(&rule).unwrap())
+        .collect()
+}
+
+#[test]
+fn islands_depend_on_the_neighbourhood_distance() {
+    let block = library::get("block").unwrap();
+    // two blocks with a single empty column between them
+    let cells = grid(&[(&block, 4, 4), (&block, 7, 4)]);
+    assert_eq!(objects::islands(&cells, SIDE, 1).len(), 2);
+    assert_eq!(objects::islands(&cells, SIDE, 2).len(), 1);
+
+    let islands = objects::islands(&cells, SIDE, 1);
+    assert_eq!((islands[0].left, islands[0].top), (4, 4));
+    assert_eq!((islands[1].left, islands[1].top), (7, 4));
+    assert_eq!(islands[1].pattern, block);
+}
+
+#[test]
+fn separate_objects_are_labelled_individually() {
+    let block = library::get("block").unwrap();
+    let blinker = library::get("blinker").unwrap();
+    let glider = library::get("glider").unwrap();
+    let cells = grid(&[(&block, 2, 2), (&blinker, 20, 3), (&glider, 10, 20)]);
+    assert_eq!(labels(&cells, Grouping::Interacting), ["xs4_33", "xp2_7", "xq4_153"]);
+    assert_eq!(labels(&cells, Grouping::Touching), ["xs4_33", "xp2_7", "xq4_153"]);
+}
+
+#[test]
+fn pseudo_still_lifes_stay_whole_when_grouping_touching_islands() {
+    let block = library::get("block").unwrap();
+    let cells = grid(&[(&block, 4, 4), (&block, 7, 4)]);
+    assert_eq!(labels(&cells, Grouping::Interacting), ["xs4_33", "xs4_33"]);
+
+    let objects = objects::objects(&cells, SIDE, 1, &Rule::default(), 8, Grouping::Touching).unwrap();
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].islands.len(), 2);
+    assert_eq!((objects[0].left, objects[0].top, objects[0].pattern.width), (4, 4, 5));
+    assert_eq!(objects[0].label(&Rule::default()).unwrap(), "xs8_rr");
+}
+
+#[test]
+fn wider_distances_join_islands_before_grouping() {
+    let block = library::get("block").unwrap();
+    let cells = grid(&[(&block, 4, 4), (&block, 7, 4)]);
+    let objects = objects::objects(&cells, SIDE, 2, &Rule::default(), 8, Grouping::Interacting).unwrap();
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].islands.len(), 1);
+    assert_eq!(objects[0].label(&Rule::default()).unwrap(), "xs8_rr");
+
+    // two columns apart, the blocks are separate islands again
+    let cells = grid(&[(&block, 4, 4), (&block, 8, 4)]);
+    let objects = objects::objects(&cells, SIDE, 2, &Rule::default(), 8, Grouping::Interacting).unwrap();
+    assert_eq!(objects.len(), 2);
+}
+
+#[test]
+fn interacting_islands_form_one_object() {
+    // a glider about to hit a block
+    let block = library::get("block").unwrap();
+    let glider = library::get("glider").unwrap();
+    let cells = grid(&[(&glider, 4, 4), (&block, 9, 9)]);
+    let objects = objects::objects(&cells, SIDE, 1, &Rule::default(), 8, Grouping::Interacting).unwrap();
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].islands.len(), 2);
+
+    // the same pair far apart does not interact
+    let cells = grid(&[(&glider, 20, 4), (&block, 4, 20)]);
+    assert_eq!(labels(&cells, Grouping::Interacting), ["xq4_153", "xs4_33"]);
+}
@@ -0,0 +1,164 @@
+use gameoflife::error::Error;
+use gameoflife::library;
+use gameoflife::pattern::Pattern;
+use gameoflife::predecessor::{self, Boundary, PredecessorOptions};
+use gameoflife::random;
+use gameoflife::reference;
+use gameoflife::rule::Rule;
+use gameoflife::sat::{self, Cnf};
+use gameoflife::Cell;
+
+// `predecessor` stepped once by the reference implementation, as a grid with
+// a dead border of one cell around the predecessor's box.
+fn successor(predecessor: &Pattern, rule: &Rule) -> (Vec<Cell>, usize) {
+    let side = predecessor.width.max(predecessor.height) + 2;
+    let mut cells = vec![Cell::Dead; side * side];
+    for y in 0..predecessor.height {
+        for x in 0..predecessor.width {
+            cells[x + 1 + (y + 1) * side] = predecessor.get(x, y);
+        }
+    }
+    (reference::step(&cells, side, rule), side)
+}
+
+// Whether cell (x, y) of the grid from `successor` lies over the target,
+// and if so its state there.
+fn target_cell(target: &Pattern, margin: usize, x: usize, y: usize) -> Option<Cell> {
+    let (tx, ty) = ((x as isize) - 1 - margin as isize, (y as isize) - 1 - margin as isize);
+    let inside = tx >= 0 && ty >= 0 && (tx as usize) < target.width && (ty as usize) < target.height;
+    inside.then(|| target.get(tx as usize, ty as usize))
+}
+
+fn check(target: &Pattern, options: &PredecessorOptions, found: &[Pattern]) {
+    for (i, predecessor) in found.iter().enumerate() {
+        assert!(!found[..i].contains(predecessor), "{:?}", predecessor);
+        let (cells, side) = successor(predecessor, &options.rule);
+        for y in 0..side {
+            for x in 0..side {
+                let expected = match (target_cell(target, options.margin, x, y), options.boundary) {
+                    (Some(cell), _) => cell,
+                    (None, Boundary::Dead) => Cell::Dead,
+                    (None, Boundary::Free) => continue,
+                };
+                assert_eq!(cells[x + y * side], expected, "({}, {}) of\n{}", x, y, predecessor.to_plaintext());
+            }
+        }
+    }
+}
+
+#[test]
+fn predecessors_evolve_into_the_target() {
+    let blinker = library::get("blinker").unwrap();
+    let options = PredecessorOptions { limit: 20, ..PredecessorOptions::default() };
+    let found = predecessor::predecessors(&blinker, &options).unwrap();
+    // the other phase of the blinker is among them
+    assert_eq!(found.len(), 20);
+    assert!(found.iter().any(|found| Pattern::from_grid(&found.centred(8), 8) == blinker.transposed()));
+    check(&blinker, &options, &found);
+
+    for rule in ["B36/S23", "B2/S34H", "B1/S1234V"] {
+        let glider = library::get("glider").unwrap();
+        let options = PredecessorOptions { rule: rule.parse().unwrap(), boundary: Boundary::Free, limit: 3, ..PredecessorOptions::default() };
+        let found = predecessor::predecessors(&glider, &options).unwrap();
+        assert_eq!(found.len(), 3, "{}", rule);
+        check(&glider, &options, &found);
+    }
+}
+
+// Every pattern in the box around `target` that becomes it, tried one by one.
+fn brute_force(target: &Pattern, options: &PredecessorOptions) -> usize {
+    let (width, height) = (target.width + 2 * options.margin, target.height + 2 * options.margin);
+    (0..1u32 << (width * height))
+        .filter(|bits| {
+            let cells = (0..width * height).map(|i| if bits >> i & 1 == 1 { Cell::Alive } else { Cell::Dead }).collect();
+            let predecessor = Pattern { width, height, cells };
+            let (cells, side) = successor(&predecessor, &options.rule);
+            (0..side * side).all(|i| match (target_cell(target, options.margin, i % side, i / side), options.boundary) {
+                (Some(cell), _) => cells[i] == cell,
+                (None, Boundary::Dead) => cells[i] == Cell::Dead,
+                (None, Boundary::Free) => true,
+            })
+        })
+        .count()
+}
+
+#[test]
+fn every_predecessor_in_a_small_box_is_found() {
+    for rule in ["B3/S23", "B36/S23", "B2/S34H", "B1/S1234V", "B3/S"] {
+        for name in ["blinker", "block"] {
+            let target = library::get(name).unwrap();
+            // a free boundary leaves thousands under most rules
+            let boundaries = if rule == "B3/S" { &Boundary::ALL[..] } else { &[Boundary::Dead] };
+            for &boundary in boundaries {
+                let options = PredecessorOptions { rule: rule.parse().unwrap(), boundary, limit: usize::MAX, ..PredecessorOptions::default() };
+                let found = predecessor::predecessors(&target, &options).unwrap();
+                assert_eq!(found.len(), brute_force(&target, &options), "{} {} under {}", name, boundary, rule);
+                check(&target, &options, &found);
+            }
+        }
+    }
+}
+
+#[test]
+fn random_patches_have_predecessors_with_a_free_boundary() {
+    for seed in 0..8 {
+        let target = random::soup(seed, 8, 8, 0.5);
+        let options = PredecessorOptions { boundary: Boundary::Free, ..PredecessorOptions::default() };
+        let found = predecessor::predecessors(&target, &options).unwrap();
+        assert_eq!(found.len(), 1, "{}", target.to_plaintext());
+        check(&target, &options, &found);
+    }
+}
+
+#[test]
+fn orphans_have_no_predecessor() {
+    // without survival, the middle of a full 3x3 square was dead before,
+    // and so were all of its neighbours
+    let square = Pattern { width: 3, height: 3, cells: vec![Cell::Alive; 9] };
+    let options = PredecessorOptions { rule: "B3/S".parse().unwrap(), boundary: Boundary::Free, ..PredecessorOptions::default() };
+    assert!(predecessor::predecessors(&square, &options).unwrap().is_empty());
+    // Life itself has one
+    let options = PredecessorOptions { boundary: Boundary::Free, ..PredecessorOptions::default() };
+    assert_eq!(predecessor::predecessors(&square, &options).unwrap().len(), 1);
+
+    // a lone cell cannot survive alone, but three cells at the corners of a
+    // 3x3 box leave one in the middle
+    let cell = Pattern { width: 1, height: 1, cells: vec![Cell::Alive] };
+    for (margin, expected) in [(0, 0), (1, 1)] {
+        let options = PredecessorOptions { margin, ..PredecessorOptions::default() };
+        let found = predecessor::predecessors(&cell, &options).unwrap();
+        assert_eq!(found.len(), expected, "margin {}", margin);
+        check(&cell, &options, &found);
+    }
+    // nor is it an orphan, which a free boundary without a margin, where
+    // its neighbours would all have to be dead, cannot show
+    let options = PredecessorOptions { margin: 0, boundary: Boundary::Free, ..PredecessorOptions::default() };
+    assert!(matches!(predecessor::predecessors(&cell, &options), Err(Error::InvalidDimensions(_))));
+    let options = PredecessorOptions { boundary: Boundary::Free, ..PredecessorOptions::default() };
+    assert_eq!(predecessor::predecessors(&cell, &options).unwrap().len(), 1);
+}
+
+#[test]
+fn b0_rules_cannot_leave_the_boundary_dead() {
+    let block = library::get("block").unwrap();
+    let options = PredecessorOptions { rule: "B0/S8".parse().unwrap(), ..PredecessorOptions::default() };
+    assert!(matches!(predecessor::predecessors(&block, &options), Err(Error::UnsupportedRule(_))));
+    let options = PredecessorOptions { boundary: Boundary::Free, ..options };
+    assert!(predecessor::predecessors(&block, &options).is_ok());
+}
+
+#[test]
+fn encodings_round_trip_through_dimacs() {
+    let glider = library::get("glider").unwrap();
+    let options = PredecessorOptions::default();
+    let encoding = predecessor::encode(&glider, &options).unwrap();
+    assert_eq!((encoding.width, encoding.height), (5, 5));
+    assert_eq!(encoding.variable(1, 1), 7);
+
+    let text = encoding.to_dimacs(&options.rule);
+    assert!(text.starts_with("c predecessors under B3/S23 in a 5x5 box\n"), "{}", text);
+    let cnf = Cnf::from_dimacs(&text).unwrap();
+    assert_eq!(cnf, encoding.cnf);
+    let predecessor = encoding.decode(&sat::solve(&cnf).unwrap());
+    check(&glider, &options, &[predecessor]);
+}
@@ -0,0 +1,137 @@
+use gameoflife::analysis::{self, Classification};
+use gameoflife::library;
+use gameoflife::random;
+use gameoflife::rule::Rule;
+use gameoflife::stepper::{self, StepperKind};
+use gameoflife::Cell;
+
+const SIDE: usize = 32;
+
+#[test]
+fn a_trailing_r_makes_a_rule_second_order() {
+    let rule: Rule = "B3/S23R".parse().unwrap();
+    assert!(rule.is_second_order());
+    assert_eq!(rule.first_order(), Rule::default());
+    assert_eq!(Rule::default().second_order(), Ok(rule));
+    for text in ["B3/S23R", "B2-a/S12R", "B2/S34HR", "R2,C0,M1,S3..5,B3..4,NMR"] {
+        assert_eq!(text.parse::<Rule>().unwrap().to_string(), text);
+    }
+    assert!(!"B3/S23".parse::<Rule>().unwrap().is_second_order());
+}
+
+#[test]
+fn the_first_step_from_a_load_is_the_first_order_step() {
+    let rule: Rule = "B3/S23R".parse().unwrap();
+    let cells = random::soup(7, 16, 16, 0.4).centred(SIDE);
+    for kind in StepperKind::ALL {
+        let mut first_order = kind.create(SIDE, rule.first_order()).unwrap();
+        let mut second_order = kind.create(SIDE, rule).unwrap();
+        first_order.load(&cells).unwrap();
+        second_order.load(&cells).unwrap();
+        first_order.step(1);
+        second_order.step(1);
+        assert_eq!(second_order.cells(), first_order.cells(), "{}", kind);
+        assert_eq!(second_order.previous(), cells, "{}", kind);
+    }
+}
+
+#[test]
+fn reversing_time_retraces_the_run() {
+    for text in ["B3/S23R", "B2/S13VR", "B2-a/S12R"] {
+        let rule: Rule = text.parse().unwrap();
+        let cells = random::soup(3, 16, 16, 0.35).centred(SIDE);
+        for kind in StepperKind::ALL {
+            let mut stepper = kind.create(SIDE, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            let mut seen = vec![stepper.cells()];
+            for _ in 0..30 {
+                stepper.step(1);
+                seen.push(stepper.cells());
+            }
+            // the grid goes back a generation as soon as time is reversed
+            stepper.reverse().unwrap();
+            for generation in (0..30).rev() {
+                assert_eq!(stepper.cells(), seen[generation], "{} on {} at {}", text, kind, generation);
+                stepper.step(1);
+            }
+            // past the load the run goes on from the empty previous grid, and
+            // reversing again comes forwards through the same generations
+            stepper.step(9);
+            stepper.reverse().unwrap();
+            stepper.step(9);
+            assert_eq!(stepper.cells(), seen[0], "{} on {}", text, kind);
+            stepper.step(1);
+            assert_eq!(stepper.cells(), seen[1], "{} on {}", text, kind);
+        }
+    }
+}
+
+#[test]
+fn reversing_time_counts_the_generations_back_down() {
+    let rule: Rule = "B3/S23R".parse().unwrap();
+    let cells = random::soup(4, 16, 16, 0.35).centred(SIDE);
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(SIDE, rule).unwrap();
+        stepper.load(&cells).unwrap();
+        stepper.step(5);
+        let fifth = stepper.cells();
+        stepper.step(15);
+        assert_eq!(stepper.generation(), 20, "{}", kind);
+        stepper.reverse().unwrap();
+        assert_eq!(stepper.generation(), 19, "{}", kind);
+        stepper.step(19);
+        assert_eq!(stepper.generation(), 0, "{}", kind);
+        assert_eq!(stepper.cells(), cells, "{}", kind);
+
+        // the direction carries over to the other engine, and reversing
+        // again counts back up
+        for other in StepperKind::ALL {
+            let mut other = stepper::convert(stepper.as_ref(), other).unwrap();
+            assert!(other.is_reversed());
+            other.reverse().unwrap();
+            other.step(4);
+            assert_eq!(other.generation(), 5, "{} to {}", kind, other.kind());
+            assert_eq!(other.cells(), fifth, "{} to {}", kind, other.kind());
+        }
+    }
+}
+
+#[test]
+fn a_reversed_run_is_classified_from_both_generations() {
+    // a block under B3/S23R runs 4, 4, 0, 4, 4, 0, ..., so reversed where
+    // the grid goes back to the empty generation it is not dead but the same
+    // p3 oscillator
+    let rule: Rule = "B3/S23R".parse().unwrap();
+    let block = library::get("block").unwrap();
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(SIDE, rule).unwrap();
+        stepper.load(&block.centred(SIDE)).unwrap();
+        stepper.step(3);
+        stepper.reverse().unwrap();
+        assert_eq!(stepper.population(), 0, "{}", kind);
+        let (pattern, previous) = analysis::phase(stepper.as_ref());
+        let analysis = analysis::classify_phase(&pattern, &previous, stepper.generation(), &rule, 16).unwrap();
+        assert_eq!(analysis.classification, Classification::Oscillator { period: 3 }, "{}", kind);
+
+        let mut populations = vec![];
+        for _ in 0..6 {
+            populations.push(stepper.population());
+            stepper.step(1);
+        }
+        assert_eq!(populations, [0, 4, 4, 0, 4, 4], "{}", kind);
+    }
+}
+
+#[test]
+fn converting_engines_keeps_the_previous_generation() {
+    let rule: Rule = "B3/S23R".parse().unwrap();
+    let mut quadtree = StepperKind::Quadtree.create(SIDE, rule).unwrap();
+    quadtree.load(&random::soup(5, 16, 16, 0.4).centred(SIDE)).unwrap();
+    quadtree.step(10);
+    let mut dense = stepper::convert(quadtree.as_ref(), StepperKind::Dense).unwrap();
+    assert_eq!(dense.previous(), quadtree.previous());
+    quadtree.step(5);
+    dense.step(5);
+    assert_eq!(dense.cells(), quadtree.cells());
+    assert!(dense.cells().contains(&Cell::Alive));
+}
@@ -0,0 +1,212 @@
+use gameoflife::reference;
+use gameoflife::rule::Rule;
+use gameoflife::ruletable;
+use gameoflife::stepper::StepperKind;
+use gameoflife::Cell;
+
+// Golly's WireWorld: 1 is an electron head, 2 its tail and 3 copper.
+const WIREWORLD: &str = "@RULE WireWorld
+
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,1,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+var n={0,2,3}
+var o={0,2,3}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,i,j,k,l,m,n,o,1 # one head nearby
+3,1,1,i,j,k,l,m,n,1 # or two
+
+@COLORS
+1 0 128 255
+2 255 255 255
+3 255 128 0
+";
+
+fn wire(side: usize) -> Vec<Cell> {
+    let mut cells = vec![Cell::Dead; side * side];
+    let y = side / 2;
+    for x in 2..side - 2 {
+        cells[x + y * side] = Cell::State(3);
+    }
+    cells[2 + y * side] = Cell::State(2);
+    cells[3 + y * side] = Cell::Alive;
+    cells
+}
+
+#[test]
+fn wireworld_electrons_run_along_wires() {
+    const SIDE: usize = 16;
+    let rule = Rule::from_table(ruletable::parse(WIREWORLD).unwrap());
+    assert_eq!(rule.states(), 4);
+    assert_eq!(rule.to_string(), "WireWorld");
+    assert_eq!("WireWorld".parse::<Rule>().unwrap(), rule);
+
+    let mut cells = wire(SIDE);
+    for _ in 0..5 {
+        cells = reference::step(&cells, SIDE, &rule);
+    }
+    let row = &cells[SIDE * SIDE / 2..SIDE * SIDE / 2 + SIDE];
+    assert_eq!(row[8], Cell::Alive);
+    assert_eq!(row[7], Cell::State(2));
+    assert!(row[2..7].iter().chain(&row[9..SIDE - 2]).all(|cell| *cell == Cell::State(3)));
+
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(SIDE, rule).unwrap();
+        stepper.load(&wire(SIDE)).unwrap();
+        stepper.step(5);
+        assert_eq!(stepper.cells(), cells, "{}", kind);
+    }
+}
+
+#[test]
+fn trees_and_tables_agree() {
+    // a cell comes alive next to any live cell in the von Neumann neighbourhood
+    let table = "@RULE GrowTable
+@TABLE
+n_states:2
+neighborhood:vonNeumann
+symmetries:rotate4
+var a={0,1}
+var b={0,1}
+var c={0,1}
+0,1,a,b,c,1
+";
+    // the same rule as a tree over n, w, e and s, and then the cell
+    let tree = "@RULE GrowTree
+@TREE
+num_states=2
+num_neighbors=4
+num_nodes=9
+1 0 1
+1 1 1
+2 0 1
+2 1 1
+3 2 3
+3 3 3
+4 4 5
+4 5 5
+5 6 7
+";
+    const SIDE: usize = 32;
+    let table = Rule::from_table(ruletable::parse(table).unwrap());
+    let tree = Rule::from_table(ruletable::parse(tree).unwrap());
+    let soup = gameoflife::random::soup(3, 8, 8, 0.3).centred(SIDE);
+    let (mut by_table, mut by_tree) = (soup.clone(), soup);
+    for _ in 0..6 {
+        by_table = reference::step(&by_table, SIDE, &table);
+        by_tree = reference::step(&by_tree, SIDE, &tree);
+        assert_eq!(by_table, by_tree);
+    }
+}
+
+#[test]
+fn symmetries_and_bound_variables() {
+    // without symmetry, a cell moves one step south each generation
+    let falling = "@RULE Falling
+@TABLE
+n_states:2
+neighborhood:vonNeumann
+symmetries:none
+0,1,0,0,0,1
+1,0,0,0,0,0
+";
+    const SIDE: usize = 8;
+    let rule = Rule::from_table(ruletable::parse(falling).unwrap());
+    let mut cells = vec![Cell::Dead; SIDE * SIDE];
+    cells[3 + 2 * SIDE] = Cell::Alive;
+    let cells = reference::step(&cells, SIDE, &rule);
+    assert_eq!(cells.iter().position(|cell| *cell == Cell::Alive), Some(3 + 3 * SIDE));
+    assert_eq!(cells.iter().filter(|cell| **cell == Cell::Alive).count(), 1);
+
+    // with rotate4 the same transition looks in all four directions
+    let spreading = falling.replace("Falling", "Spreading").replace("none", "rotate4");
+    let table = ruletable::parse(&spreading).unwrap();
+    for neighbour in [0, 2, 4, 6] {
+        let mut neighbours = [0; 8];
+        neighbours[neighbour] = 1;
+        assert_eq!(table.next(0, &neighbours), 1, "neighbour {}", neighbour);
+    }
+
+    // `a` appears twice, so north and south must be in the same state;
+    // the corners of the Moore neighbourhood are compact without commas
+    let bound = "@RULE Bound
+@TABLE
+n_states:3
+neighborhood:Moore
+symmetries:none
+var a={1,2}
+0a000a0001
+";
+    let table = ruletable::parse(bound).unwrap();
+    assert_eq!(table.next(0, &[1, 0, 0, 0, 1, 0, 0, 0]), 1);
+    assert_eq!(table.next(0, &[2, 0, 0, 0, 2, 0, 0, 0]), 1);
+    assert_eq!(table.next(0, &[1, 0, 0, 0, 2, 0, 0, 0]), 0);
+}
+
+#[test]
+fn colours_come_from_the_file() {
+    let table = ruletable::parse(WIREWORLD).unwrap();
+    assert_eq!(table.colour(1), Some([0, 128, 255]));
+    assert_eq!(table.colour(3), Some([255, 128, 0]));
+    assert_eq!(table.colour(0), None);
+
+    // a gradient across the live states
+    let gradient = WIREWORLD.replace("1 0 128 255\n2 255 255 255\n3 255 128 0", "0 0 255 255 0 0");
+    let table = ruletable::parse(&gradient).unwrap();
+    assert_eq!(table.colour(1), Some([0, 0, 255]));
+    assert_eq!(table.colour(2), Some([128, 0, 128]));
+    assert_eq!(table.colour(3), Some([255, 0, 0]));
+}
+
+#[test]
+fn reloading_a_rule_file_keeps_rules_made_from_the_old_one() {
+    // births on three neighbours, then whatever `survival` keeps alive, and
+    // every other live cell dies
+    let file = |survival: &str| {
+        let vars: String = "abcdefgh".chars().map(|var| format!("var {}={{0,1}}\n", var)).collect();
+        format!("@RULE Reloaded\n@TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:permute\n{}0,1,1,1,0,0,0,0,0,1\n{}1,a,b,c,d,e,f,g,h,0\n", vars, survival)
+    };
+    let mut cells = vec![Cell::Dead; 16 * 16];
+    for x in 6..9 {
+        cells[x + 8 * 16] = Cell::Alive;
+    }
+    let populations = |rule: Rule| {
+        StepperKind::ALL.map(|kind| {
+            let mut stepper = kind.create(16, rule).unwrap();
+            stepper.load(&cells).unwrap();
+            stepper.step(1);
+            stepper.population()
+        })
+    };
+    let first = Rule::from_table(ruletable::parse(&file("")).unwrap());
+    assert_eq!(populations(first), [2, 2]);
+
+    // the same file again is the same rule, and does not grow the registry
+    let count = || ruletable::names().iter().filter(|name| *name == "Reloaded").count();
+    assert_eq!(Rule::from_table(ruletable::parse(&file("")).unwrap()), first);
+    assert_eq!(count(), 1);
+
+    // an edited file, now with survival on two or three neighbours, is a new
+    // rule that the name finds, while the first rule runs as before
+    let second = Rule::from_table(ruletable::parse(&file("1,1,1,0,0,0,0,0,0,1\n1,1,1,1,0,0,0,0,0,1\n")).unwrap());
+    assert_ne!(second, first);
+    assert_eq!("Reloaded".parse::<Rule>().unwrap(), second);
+    assert_eq!(populations(second), [3, 3]);
+    assert_eq!(populations(first), [2, 2]);
+    assert_eq!(count(), 2);
+}
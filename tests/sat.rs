@@ -0,0 +1,76 @@
+use gameoflife::random::Random;
+use gameoflife::sat::{self, Cnf, Literal};
+
+// Whether any assignment at all satisfies `cnf`, by trying every one.
+fn satisfiable(cnf: &Cnf) -> bool {
+    (0..1u32 << cnf.variables).any(|bits| {
+        let assignment: Vec<bool> = (0..cnf.variables).map(|v| bits >> v & 1 == 1).collect();
+        cnf.satisfied_by(&assignment)
+    })
+}
+
+#[test]
+fn random_formulas_agree_with_trying_every_assignment() {
+    let mut rng = Random::new(7);
+    let mut outcomes = [0, 0];
+    for _ in 0..400 {
+        // around the ratio of clauses to variables where 3-SAT is hardest
+        let variables = 3 + rng.below(10) as usize;
+        let mut cnf = Cnf { variables, clauses: vec![] };
+        for _ in 0..(variables * 43 / 10) {
+            let clause: Vec<Literal> = (0..3)
+                .map(|_| {
+                    let variable = 1 + rng.below(variables as u64) as Literal;
+                    if rng.chance(0.5) { variable } else { -variable }
+                })
+                .collect();
+            cnf.add(clause);
+        }
+        let solution = sat::solve(&cnf);
+        assert_eq!(solution.is_some(), satisfiable(&cnf), "{}", cnf.to_dimacs(&[]));
+        if let Some(assignment) = &solution {
+            assert!(cnf.satisfied_by(assignment));
+        }
+        outcomes[solution.is_some() as usize] += 1;
+    }
+    assert!(outcomes[0] > 40 && outcomes[1] > 40, "{:?}", outcomes);
+}
+
+#[test]
+fn pigeons_do_not_fit_in_fewer_holes() {
+    for holes in 1..6 {
+        for pigeons in [holes, holes + 1] {
+            let mut cnf = Cnf::new();
+            let sits = |pigeon: usize, hole: usize| (1 + hole + pigeon * holes) as Literal;
+            for pigeon in 0..pigeons {
+                cnf.add((0..holes).map(|hole| sits(pigeon, hole)).collect());
+            }
+            for hole in 0..holes {
+                for a in 0..pigeons {
+                    for b in a + 1..pigeons {
+                        cnf.add(vec![-sits(a, hole), -sits(b, hole)]);
+                    }
+                }
+            }
+            assert_eq!(sat::solve(&cnf).is_some(), pigeons == holes, "{} pigeons, {} holes", pigeons, holes);
+        }
+    }
+}
+
+#[test]
+fn formulas_round_trip_through_dimacs() {
+    let mut cnf = Cnf::new();
+    let (a, b, c) = (cnf.variable(), cnf.variable(), cnf.variable());
+    cnf.add(vec![a, -b]);
+    cnf.add(vec![b, c, -a]);
+    cnf.add(vec![-c]);
+    let text = cnf.to_dimacs(&["a small example".to_string()]);
+    assert!(text.starts_with("c a small example\np cnf 3 3\n1 -2 0\n"), "{}", text);
+    assert_eq!(Cnf::from_dimacs(&text).unwrap(), cnf);
+
+    // clauses may span lines, and unused variables are kept
+    let spread = Cnf::from_dimacs("p cnf 5 2\n1 -2\n 0 3 0\n").unwrap();
+    assert_eq!(spread, Cnf { variables: 5, clauses: vec![vec![1, -2], vec![3]] });
+    assert_eq!(sat::solve(&spread).map(|assignment| assignment.len()), Some(5));
+    assert!(sat::solve(&Cnf::from_dimacs("p cnf 1 2\n1 0\n-1 0\n").unwrap()).is_none());
+}
@@ -0,0 +1,104 @@
+use gameoflife::analysis::{self, Classification};
+use gameoflife::library;
+use gameoflife::objects::{self, Grouping};
+use gameoflife::pattern::Pattern;
+use gameoflife::rule::Rule;
+use gameoflife::search::{self, SearchOptions};
+
+fn codes(width: usize, height: usize, period: usize) -> Vec<String> {
+    let options = SearchOptions { width, height, period, ..SearchOptions::default() };
+    search::search(&options).unwrap().into_iter().map(|found| found.code).collect()
+}
+
+#[test]
+fn every_small_still_life_is_found_once() {
+    let found = search::search(&SearchOptions::default()).unwrap();
+    let codes: Vec<&str> = found.iter().map(|found| found.code.as_str()).collect();
+    assert_eq!(
+        codes,
+        ["xs4_252", "xs4_33", "xs5_253", "xs6_25a4", "xs6_356", "xs6_39c", "xs6_696", "xs6_bd", "xs7_178c", "xs7_2596", "xs7_25ac", "xs8_35ac", "xs8_6996"]
+    );
+    for found in &found {
+        assert!(found.pattern.width <= 4 && found.pattern.height <= 4, "{}", found.code);
+        let analysis = analysis::classify(&found.pattern, &Rule::default(), 10).unwrap();
+        assert_eq!(analysis.classification, Classification::StillLife, "{}", found.code);
+    }
+}
+
+#[test]
+fn oscillators_have_exactly_the_period_asked_for() {
+    // the blinker, clock, beacon and toad, but none of the still lifes
+    assert_eq!(codes(4, 4, 2), ["xp2_7", "xp2_2a54", "xp2_318c", "xp2_7e"]);
+    assert_eq!(codes(5, 5, 2), ["xp2_7", "xp2_2a54", "xp2_318c", "xp2_7e", "xp2_31ago"]);
+    assert!(codes(5, 5, 3).is_empty());
+}
+
+#[test]
+fn only_strict_objects_are_found() {
+    // two blocks fit in a 6x2 box, either way round, but only as a pseudo
+    // still life
+    assert_eq!(codes(6, 2, 1), ["xs4_33", "xs6_bd"]);
+    // only islands are told apart, so the long barge, two tubs whose cells
+    // touch, is kept whole
+    assert!(codes(5, 5, 1).iter().any(|code| code == "xs8_25ak8"));
+    let options = SearchOptions { width: 6, height: 6, period: 2, ..SearchOptions::default() };
+    for found in search::search(&options).unwrap() {
+        let side = 8;
+        let objects = objects::objects(&found.pattern.centred(side), side, 1, &options.rule, 2, Grouping::Interacting).unwrap();
+        assert_eq!(objects.len(), 1, "{}", found.code);
+    }
+}
+
+#[test]
+fn boxes_find_the_same_objects_either_way_round() {
+    assert_eq!(codes(5, 3, 1), codes(3, 5, 1));
+    // the blinker needs room for both phases
+    assert_eq!(codes(3, 3, 2), ["xp2_7"]);
+    assert!(codes(3, 2, 2).is_empty());
+}
+
+#[test]
+fn results_round_trip_through_rle() {
+    let options = SearchOptions { width: 5, height: 5, period: 2, ..SearchOptions::default() };
+    let found = search::search(&options).unwrap();
+    let report = search::report(&found, &options);
+    for (found, rle) in found.iter().zip(report.split("#N ").skip(1)) {
+        assert!(rle.starts_with(&found.code));
+        assert_eq!(Pattern::from_text(rle).unwrap(), found.pattern);
+        let body = rle.lines().last().unwrap();
+        assert_eq!(library::get(body).unwrap(), found.pattern, "{}", body);
+    }
+
+    // long rows are wrapped, and multi-state cells are written as letters
+    for name in ["gosper glider gun", "xor gate"] {
+        let pattern = library::get(name).unwrap();
+        let rle = pattern.to_rle(&Rule::default());
+        assert!(rle.lines().all(|line| line.len() <= 70), "{}", rle);
+        assert_eq!(Pattern::from_rle(&rle).unwrap(), pattern, "{}", name);
+    }
+    let glider = Pattern::from_rle("#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+    assert_eq!(glider, library::get("glider").unwrap());
+}
+
+#[test]
+fn reports_load_with_their_rule() {
+    let highlife: Rule = "B36/S23".parse().unwrap();
+    let options = SearchOptions { rule: highlife, width: 4, height: 4, ..SearchOptions::default() };
+    let found = search::search(&options).unwrap();
+    let report = search::report(&found, &options);
+    for (found, record) in found.iter().zip(report.split("#N ").skip(1)) {
+        let record = format!("#N {}", record);
+        assert_eq!(library::get(&record).unwrap(), found.pattern, "{}", record);
+        assert_eq!(library::rule(&record), Some(highlife), "{}", record);
+    }
+    // the whole report loads as its first result
+    assert_eq!(library::get(&report).unwrap(), found[0].pattern);
+    assert_eq!(library::rule(&report), Some(highlife));
+
+    // rules with commas of their own are read to the end of the header
+    let bosco = "x = 1, y = 1, rule = R5,C0,M1,S34..58,B34..45,NM\no!";
+    assert_eq!(Pattern::rle_rule(bosco).unwrap(), Some("R5,C0,M1,S34..58,B34..45,NM".parse().unwrap()));
+    assert_eq!(Pattern::rle_rule("3o!").unwrap(), None);
+    assert!(Pattern::rle_rule("x = 3, y = 1, rule = B9/S\n3o!").is_err());
+    assert_eq!(library::rule("bo$2bo$3o!"), None);
+}
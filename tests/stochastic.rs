@@ -0,0 +1,73 @@
+use gameoflife::library;
+use gameoflife::random;
+use gameoflife::rule::{Chances, Rule, CERTAIN};
+use gameoflife::stepper::StepperKind;
+use gameoflife::Cell;
+
+const SIDE: usize = 32;
+
+fn run(rule: &str, kind: StepperKind, generations: usize) -> Vec<Cell> {
+    let mut stepper = kind.create(SIDE, rule.parse().unwrap()).unwrap();
+    stepper.load(&random::soup(11, 16, 16, 0.4).centred(SIDE)).unwrap();
+    stepper.step(generations);
+    stepper.cells()
+}
+
+#[test]
+fn chances_follow_the_rule() {
+    let rule: Rule = "B3/S23:P0.9:N0.001:S7".parse().unwrap();
+    assert_eq!(rule.chances(), Some(Chances { birth: 900_000, noise: 1_000, seed: 7 }));
+    assert!(rule.is_stochastic());
+    for text in ["B3/S23:P0.9:N0.001:S7", "B36/S23:P0.5:S0", "B2/S:N0.000001:S18446744073709551615", "B2-a/S12:P0:S1", "B2/S13V:N0.5:S1"] {
+        assert_eq!(text.parse::<Rule>().unwrap().to_string(), text);
+    }
+    // parts come in any order, and certain births without noise change nothing
+    assert_eq!("B3/S23:S7:N0.001:P0.9".parse::<Rule>(), Ok(rule));
+    assert!(!"B3/S23:S7".parse::<Rule>().unwrap().is_stochastic());
+    assert_eq!(Rule::default().stochastic(Chances { birth: CERTAIN, noise: 0, seed: 7 }).unwrap().to_string(), "B3/S23:S7");
+}
+
+#[test]
+fn seeds_reproduce_runs_on_every_engine() {
+    let expected = run("B3/S23:P0.8:N0.002:S1", StepperKind::Dense, 20);
+    for kind in StepperKind::ALL {
+        assert_eq!(run("B3/S23:P0.8:N0.002:S1", kind, 20), expected, "{}", kind);
+    }
+    assert_ne!(run("B3/S23:P0.8:N0.002:S2", StepperKind::Dense, 20), expected);
+    assert_ne!(run("B3/S23", StepperKind::Dense, 20), expected);
+}
+
+#[test]
+fn births_that_never_succeed_starve_a_blinker() {
+    let blinker = library::get("blinker").unwrap().centred(SIDE);
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(SIDE, "B3/S23:P0".parse().unwrap()).unwrap();
+        stepper.load(&blinker).unwrap();
+        stepper.step(1);
+        assert_eq!(stepper.population(), 1, "{}", kind);
+        stepper.step(1);
+        assert_eq!(stepper.population(), 0, "{}", kind);
+    }
+}
+
+#[test]
+fn noise_flips_cells_at_its_rate() {
+    // under B/S nothing survives, so each generation is noise alone
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(SIDE, "B/S:N0.05:S3".parse().unwrap()).unwrap();
+        let mut total = 0;
+        for _ in 0..20 {
+            stepper.step(1);
+            total += stepper.population();
+        }
+        // about 0.05 of 1024 cells each time
+        assert!((800..1250).contains(&total), "{} on {}", total, kind);
+    }
+}
+
+#[test]
+fn the_quadtree_engine_hands_stochastic_rules_to_the_dense_one() {
+    let mut stepper = StepperKind::Quadtree.create(SIDE, "B3/S23:P0.9".parse().unwrap()).unwrap();
+    stepper.step(1);
+    assert_eq!(stepper.suggestion(), Some(StepperKind::Dense));
+}
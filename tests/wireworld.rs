@@ -0,0 +1,101 @@
+use gameoflife::library;
+use gameoflife::pattern::Pattern;
+use gameoflife::rule::Rule;
+use gameoflife::ruletable;
+use gameoflife::stepper::StepperKind;
+use gameoflife::Cell;
+
+const SIDE: usize = 32;
+
+// Runs a library circuit with electrons sent into the inputs on the given
+// rows, and returns the generations in which a head reaches (x, y) of the
+// circuit, which must be the same on every engine.
+fn pulses(name: &str, inputs: &[usize], (x, y): (usize, usize), generations: usize) -> Vec<usize> {
+    let circuit = library::get(name).unwrap();
+    let rule = library::rule(name).unwrap();
+    let (left, top) = ((SIDE - circuit.width) / 2, (SIDE - circuit.height) / 2);
+    let mut cells = circuit.centred(SIDE);
+    for row in inputs {
+        cells[left + (top + row) * SIDE] = Cell::State(2);
+        cells[left + 1 + (top + row) * SIDE] = Cell::Alive;
+    }
+    let mut seen: Option<Vec<usize>> = None;
+    for kind in StepperKind::ALL {
+        let mut stepper = kind.create(SIDE, rule).unwrap();
+        stepper.load(&cells).unwrap();
+        let mut times = vec![];
+        for generation in 1..=generations {
+            stepper.step(1);
+            if stepper.get(left + x, top + y) == Cell::Alive {
+                times.push(generation);
+            }
+        }
+        if let Some(seen) = &seen {
+            assert_eq!(&times, seen, "{} on the {} engine", name, kind);
+        }
+        seen = Some(times);
+    }
+    seen.unwrap()
+}
+
+#[test]
+fn wireworld_is_built_in() {
+    let rule: Rule = "WireWorld".parse().unwrap();
+    assert_eq!(rule.states(), 4);
+    assert_eq!(library::rule("xor gate"), Some(rule));
+    assert_eq!(library::rule("glider"), None);
+
+    let table = ruletable::parse(ruletable::WIREWORLD).unwrap();
+    assert_eq!(table.name, "WireWorld");
+    assert_eq!(table.colour(1), Some([0, 64, 255]));
+    assert_eq!(table.colour(2), Some([255, 64, 0]));
+    assert_eq!(table.colour(3), Some([255, 200, 0]));
+
+    // a file of the same name is a different rule, and circuits keep theirs
+    let recoloured = ruletable::WIREWORLD.replace("3 255 200 0", "3 255 128 0");
+    let loaded = Rule::from_table(ruletable::parse(&recoloured).unwrap());
+    assert_eq!(loaded.to_string(), "WireWorld");
+    assert_ne!(loaded, Rule::wireworld());
+    assert_eq!(library::rule("xor gate"), Some(Rule::wireworld()));
+}
+
+#[test]
+fn diodes_only_let_signals_through_one_way() {
+    assert_eq!(pulses("diode", &[1], (9, 1), 20).len(), 1);
+    // turned around, the diode blocks the same electron
+    let reversed = library::get("diode").unwrap().flipped();
+    let rule = library::rule("diode").unwrap();
+    let mut cells = reversed.centred(SIDE);
+    let (left, top) = ((SIDE - reversed.width) / 2, (SIDE - reversed.height) / 2);
+    cells[left + (top + 1) * SIDE] = Cell::State(2);
+    cells[left + 1 + (top + 1) * SIDE] = Cell::Alive;
+    let mut stepper = StepperKind::Dense.create(SIDE, rule).unwrap();
+    stepper.load(&cells).unwrap();
+    for _ in 0..20 {
+        stepper.step(1);
+        assert_ne!(stepper.get(left + 9, top + 1), Cell::Alive);
+    }
+}
+
+#[test]
+fn gates_follow_their_truth_tables() {
+    let or = |inputs: &[usize]| pulses("or gate", inputs, (10, 2), 20).len();
+    assert_eq!([or(&[]), or(&[0]), or(&[4]), or(&[0, 4])], [0, 1, 1, 1]);
+    let xor = |inputs: &[usize]| pulses("xor gate", inputs, (19, 4), 30).len();
+    assert_eq!([xor(&[]), xor(&[1]), xor(&[7]), xor(&[1, 7])], [0, 1, 1, 0]);
+}
+
+#[test]
+fn clocks_tick_every_ten_generations() {
+    let times = pulses("clock", &[], (11, 1), 50);
+    assert_eq!(times.windows(2).map(|pair| pair[1] - pair[0]).collect::<Vec<_>>(), vec![10; times.len() - 1]);
+    assert_eq!(times.len(), 5);
+}
+
+#[test]
+fn plaintext_keeps_digit_states() {
+    let clock = library::get("clock").unwrap();
+    assert_eq!(clock.get(1, 0), Cell::State(2));
+    assert_eq!(clock.get(2, 0), Cell::Alive);
+    assert_eq!(Pattern::from_plaintext(&clock.to_plaintext()).unwrap(), clock);
+}